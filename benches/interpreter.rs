@@ -0,0 +1,105 @@
+// Times scanning, parsing and interpreting representative programs. Run with
+// `cargo bench`, or `cargo bench -- fib` for the benchmarks matching "fib".
+
+use std::{
+    env,
+    hint::black_box,
+    io,
+    time::{Duration, Instant},
+};
+
+use teci_lang::{
+    interpreter::Interpreter, parser::Parser, scanner::Scanner, stmt::Stmt, token::Token,
+};
+
+const PROGRAMS: [(&str, &str); 4] = [
+    ("fib", include_str!("programs/fib.tc")),
+    ("counters", include_str!("programs/counters.tc")),
+    ("strings", include_str!("programs/strings.tc")),
+    ("nested_loops", include_str!("programs/nested_loops.tc")),
+];
+
+const WARM_UP: Duration = Duration::from_millis(300);
+const MEASUREMENT: Duration = Duration::from_secs(1);
+const SAMPLES: usize = 20;
+
+fn main() {
+    // Cargo passes `--bench`
+    let filter = env::args().skip(1).find(|arg| !arg.starts_with("--"));
+    let bench = |name: String, routine: &mut dyn FnMut()| {
+        if filter
+            .as_ref()
+            .is_none_or(|filter| name.contains(filter.as_str()))
+        {
+            report(&name, &measure(routine));
+        }
+    };
+
+    for (program, source) in PROGRAMS {
+        let tokens = scan(source);
+        let statements = parse(tokens.clone());
+
+        bench(format!("scan/{program}"), &mut || {
+            black_box(scan(black_box(source)));
+        });
+        bench(format!("parse/{program}"), &mut || {
+            black_box(parse(black_box(tokens.clone())));
+        });
+        bench(format!("interpret/{program}"), &mut || {
+            let interpreter = Interpreter::new();
+            interpreter.set_output(Box::new(io::sink()));
+            interpreter
+                .interpret(black_box(&statements))
+                .expect("benchmark programs run");
+        });
+    }
+}
+
+fn scan(source: &str) -> Vec<Token> {
+    Scanner::new(source.to_string())
+        .scan_tokens()
+        .expect("benchmark programs scan")
+}
+
+fn parse(tokens: Vec<Token>) -> Vec<Stmt> {
+    Parser::new(tokens)
+        .parse()
+        .expect("benchmark programs parse")
+}
+
+// The mean time per iteration of each sample, like criterion does: a sample
+// runs the routine as many times as fit in its share of the measurement time
+fn measure(routine: &mut dyn FnMut()) -> Vec<Duration> {
+    let start = Instant::now();
+    let mut iterations = 0u32;
+    while start.elapsed() < WARM_UP {
+        routine();
+        iterations += 1;
+    }
+    let per_iteration = start.elapsed() / iterations;
+    let per_sample = (MEASUREMENT / SAMPLES as u32)
+        .div_duration_f64(per_iteration)
+        .max(1.0) as u32;
+
+    (0..SAMPLES)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..per_sample {
+                routine();
+            }
+            start.elapsed() / per_sample
+        })
+        .collect()
+}
+
+fn report(name: &str, samples: &[Duration]) {
+    let mut samples = samples.to_vec();
+    samples.sort();
+    println!(
+        "{:<24} time: [{:?} {:?} {:?}]",
+        name,
+        samples[0],
+        samples[samples.len() / 2],
+        samples[samples.len() - 1]
+    );
+}
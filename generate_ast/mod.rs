@@ -15,15 +15,21 @@ pub fn generate_ast(output_dir: &str) -> io::Result<()> {
         output_dir,
         "Expr",
         &[],
-        &["token", "object", "error"],
+        &["token", "object", "error", "pattern"],
         &[
             "Assign     : Token name, Box<Expr> value",
             "Binary     : Box<Expr> left, Token operator, Box<Expr> right",
             "Call       : Box<Expr> callee, Token paren, Vec<Expr> arguments",
-            "Grouping   : Box<Expr> expression",
+            "Conditional: Box<Expr> condition, Box<Expr> then_branch, Box<Expr> else_branch",
+            "Get        : Box<Expr> object, Token name",
+            "Grouping   : Token paren, Box<Expr> expression",
             "Logical    : Box<Expr> left, Token operator, Box<Expr> right",
-            "Literal    : Option<Object> value",
+            "Literal    : Token token, Option<Object> value",
+            "Match      : Token keyword, Box<Expr> value, Vec<MatchArm> arms, Token brace",
             "Unary      : Token operator, Box<Expr> right",
+            "Tuple      : Token paren, Vec<Expr> elements",
+            "TupleAssign: Token paren, Pattern target, Box<Expr> value",
+            "Update     : Token name, Token operator, bool prefix",
             "Variable   : Token name",
         ],
     )?;
@@ -32,17 +38,20 @@ pub fn generate_ast(output_dir: &str) -> io::Result<()> {
         output_dir,
         "Stmt",
         &["std::rc::Rc"],
-        &["error", "expr", "token"],
+        &["error", "expr", "pattern", "token", "types"],
         &[
             "Break      : Token token",
-            "Block      : Vec<Stmt> statements",
-            "If         : Expr condition, Box<Stmt> then_branch, Option<Box<Stmt>> else_branch",
-            "Expression : Expr expression",
-            "Function   : Token name, Rc<Vec<Token>> params, Rc<Vec<Stmt>> body",
-            "Print      : Expr expression",
-            "Return     : Token _keyword, Option<Expr> value",
-            "Let        : Token name, Option<Expr> initializer",
-            "While      : Expr condition, Box<Stmt> body",
+            "Block      : Token brace, Vec<Stmt> statements",
+            "Destructure: Token keyword, Pattern pattern, Expr initializer, bool constant",
+            "ForIn      : Token keyword, Token name, Expr iterable, Box<Stmt> body",
+            "If         : Token keyword, Expr condition, Box<Stmt> then_branch, Option<Box<Stmt>> else_branch",
+            "Expression : Token token, Expr expression",
+            "Function   : Token name, Rc<Vec<Token>> params, Vec<Type> param_types, Type returns, Rc<Vec<Stmt>> body",
+            "Print      : Token keyword, Expr expression",
+            "Return     : Token keyword, Option<Expr> value",
+            "Let        : Token name, Option<Type> annotation, Option<Expr> initializer, bool constant",
+            "While      : Token keyword, Expr condition, Box<Stmt> body",
+            "Yield      : Token keyword, Expr value",
         ],
     )
 }
@@ -0,0 +1,710 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    ops::{Add, Mul, Neg, Sub},
+};
+
+// Arbitrary precision integers and fractions, for the numbers that do not fit
+// an i64. Magnitudes are little endian base 2^32 digits ("limbs") without
+// leading zeros, so every value has exactly one representation and the
+// derived Eq and Hash are the numeric ones.
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    // Never set for zero
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+fn trim(mut limbs: Vec<u32>) -> Vec<u32> {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+    limbs
+}
+
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut sum = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+    for (i, limb) in long.iter().enumerate() {
+        let s = *limb as u64 + *short.get(i).unwrap_or(&0) as u64 + carry;
+        sum.push(s as u32);
+        carry = s >> 32;
+    }
+    sum.push(carry as u32);
+    trim(sum)
+}
+
+// a - b, for a >= b
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, limb) in a.iter().enumerate() {
+        let d = *limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        difference.push(d as u32);
+        borrow = (d < 0) as i64;
+    }
+    trim(difference)
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let mut product = vec![0u32; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, y) in b.iter().enumerate() {
+            let p = *x as u64 * *y as u64 + product[i + j] as u64 + carry;
+            product[i + j] = p as u32;
+            carry = p >> 32;
+        }
+        product[i + b.len()] = carry as u32;
+    }
+    trim(product)
+}
+
+fn divrem_small(a: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder = 0u64;
+    for i in (0..a.len()).rev() {
+        let current = (remainder << 32) | a[i] as u64;
+        quotient[i] = (current / divisor as u64) as u32;
+        remainder = current % divisor as u64;
+    }
+    (trim(quotient), remainder as u32)
+}
+
+// Shifts left by less than 32 bits, always adding a limb for the overflow
+fn shl_bits(a: &[u32], shift: u32) -> Vec<u32> {
+    let mut shifted = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0u32;
+    for limb in a {
+        if shift == 0 {
+            shifted.push(*limb);
+        } else {
+            shifted.push((limb << shift) | carry);
+            carry = limb >> (32 - shift);
+        }
+    }
+    shifted.push(carry);
+    shifted
+}
+
+fn shr_bits(a: &[u32], shift: u32) -> Vec<u32> {
+    let mut shifted = Vec::with_capacity(a.len());
+    for i in 0..a.len() {
+        if shift == 0 {
+            shifted.push(a[i]);
+        } else {
+            let high = a.get(i + 1).map_or(0, |next| next << (32 - shift));
+            shifted.push((a[i] >> shift) | high);
+        }
+    }
+    trim(shifted)
+}
+
+// Long division (Knuth's algorithm D), for a non-zero divisor
+fn divrem_magnitude(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_magnitude(a, b) == Ordering::Less {
+        return (Vec::new(), a.to_vec());
+    }
+    if b.len() == 1 {
+        let (quotient, remainder) = divrem_small(a, b[0]);
+        return (quotient, trim(vec![remainder]));
+    }
+
+    // Scale both so the divisor's top limb has its top bit set, which keeps
+    // the quotient digit estimates off by at most two
+    let shift = b.last().unwrap().leading_zeros();
+    let mut v = shl_bits(b, shift);
+    v.pop();
+    let mut u = shl_bits(a, shift);
+    let n = v.len();
+    let m = u.len() - n - 1;
+    let mut quotient = vec![0u32; m + 1];
+
+    for j in (0..=m).rev() {
+        let top = ((u[j + n] as u64) << 32) | u[j + n - 1] as u64;
+        let mut qhat = top / v[n - 1] as u64;
+        let mut rhat = top % v[n - 1] as u64;
+        while qhat >= 1 << 32 || qhat * v[n - 2] as u64 > ((rhat << 32) | u[j + n - 2] as u64) {
+            qhat -= 1;
+            rhat += v[n - 1] as u64;
+            if rhat >= 1 << 32 {
+                break;
+            }
+        }
+
+        let mut borrow = 0i64;
+        let mut carry = 0u64;
+        for i in 0..n {
+            let p = qhat * v[i] as u64 + carry;
+            carry = p >> 32;
+            let t = u[i + j] as i64 - borrow - (p & 0xFFFF_FFFF) as i64;
+            u[i + j] = t as u32;
+            borrow = (t < 0) as i64;
+        }
+        let t = u[j + n] as i64 - borrow - carry as i64;
+        u[j + n] = t as u32;
+
+        // The estimate was one too big: add the divisor back
+        if t < 0 {
+            qhat -= 1;
+            let mut carry = 0u64;
+            for i in 0..n {
+                let s = u[i + j] as u64 + v[i] as u64 + carry;
+                u[i + j] = s as u32;
+                carry = s >> 32;
+            }
+            u[j + n] = u[j + n].wrapping_add(carry as u32);
+        }
+        quotient[j] = qhat as u32;
+    }
+
+    (trim(quotient), shr_bits(&u[..n], shift))
+}
+
+// The two's complement negation of a fixed width number
+fn negate_limbs(limbs: &[u32]) -> Vec<u32> {
+    let mut negated = Vec::with_capacity(limbs.len());
+    let mut carry = true;
+    for limb in limbs {
+        let (sum, overflow) = (!limb).overflowing_add(carry as u32);
+        negated.push(sum);
+        carry = overflow;
+    }
+    negated
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        Self {
+            negative: false,
+            magnitude: Vec::new(),
+        }
+    }
+
+    fn new(negative: bool, magnitude: Vec<u32>) -> Self {
+        let magnitude = trim(magnitude);
+        Self {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+
+    // 2^exponent
+    pub fn power_of_two(exponent: u32) -> Self {
+        let mut magnitude = vec![0u32; exponent as usize / 32];
+        magnitude.push(1 << (exponent % 32));
+        Self::new(false, magnitude)
+    }
+
+    pub fn parse(digits: &str) -> Option<Self> {
+        let (negative, digits) = match digits.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, digits),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        let mut magnitude = Vec::new();
+        for chunk in digits.as_bytes().chunks(9) {
+            let scale = 10u32.pow(chunk.len() as u32);
+            let value: u32 = std::str::from_utf8(chunk).ok()?.parse().ok()?;
+            magnitude = mul_magnitude(&magnitude, &[scale]);
+            magnitude = add_magnitude(&magnitude, &[value]);
+        }
+        Some(Self::new(negative, magnitude))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    // How many bits the magnitude takes, 0 for zero
+    pub fn bits(&self) -> u64 {
+        match self.magnitude.last() {
+            Some(last) => self.magnitude.len() as u64 * 32 - last.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    pub fn abs(&self) -> Self {
+        Self::new(false, self.magnitude.clone())
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+        let value = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0u64, |acc, limb| (acc << 32) | *limb as u64);
+        if self.negative {
+            0i64.checked_sub_unsigned(value)
+        } else {
+            i64::try_from(value).ok()
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let value = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0.0, |acc, limb| acc * 4_294_967_296.0 + *limb as f64);
+        if self.negative { -value } else { value }
+    }
+
+    // Division rounding toward zero, with the remainder taking the sign of
+    // the dividend
+    pub fn divrem(&self, divisor: &BigInt) -> Option<(BigInt, BigInt)> {
+        if divisor.is_zero() {
+            return None;
+        }
+        let (quotient, remainder) = divrem_magnitude(&self.magnitude, &divisor.magnitude);
+        Some((
+            Self::new(self.negative != divisor.negative, quotient),
+            Self::new(self.negative, remainder),
+        ))
+    }
+
+    // Division rounding down
+    pub fn div_floor(&self, divisor: &BigInt) -> Option<BigInt> {
+        let (quotient, remainder) = self.divrem(divisor)?;
+        if !remainder.is_zero() && remainder.negative != divisor.negative {
+            Some(&quotient - &BigInt::from(1))
+        } else {
+            Some(quotient)
+        }
+    }
+
+    pub fn pow(&self, mut exponent: u64) -> BigInt {
+        let mut base = self.clone();
+        let mut result = BigInt::from(1);
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = &result * &base;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = &base * &base;
+            }
+        }
+        result
+    }
+
+    // Applies `op` limb by limb to both numbers in two's complement, as if
+    // they were sign extended forever
+    pub fn bitwise(&self, other: &BigInt, op: impl Fn(u32, u32) -> u32) -> BigInt {
+        let len = self.magnitude.len().max(other.magnitude.len()) + 1;
+        let (a, b) = (self.twos_complement(len), other.twos_complement(len));
+        let limbs: Vec<u32> = a.iter().zip(&b).map(|(a, b)| op(*a, *b)).collect();
+        if limbs[len - 1] >> 31 == 1 {
+            Self::new(true, negate_limbs(&limbs))
+        } else {
+            Self::new(false, limbs)
+        }
+    }
+
+    fn twos_complement(&self, len: usize) -> Vec<u32> {
+        let mut limbs = self.magnitude.clone();
+        limbs.resize(len, 0);
+        if self.negative {
+            negate_limbs(&limbs)
+        } else {
+            limbs
+        }
+    }
+
+    pub fn gcd(&self, other: &BigInt) -> BigInt {
+        let (mut a, mut b) = (self.abs(), other.abs());
+        while !b.is_zero() {
+            let (_, remainder) = a.divrem(&b).unwrap();
+            a = b;
+            b = remainder;
+        }
+        a
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let magnitude = value.unsigned_abs();
+        Self::new(value < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.magnitude, &other.magnitude),
+            (true, true) => cmp_magnitude(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+    fn add(self, rhs: Self) -> BigInt {
+        if self.negative == rhs.negative {
+            return BigInt::new(
+                self.negative,
+                add_magnitude(&self.magnitude, &rhs.magnitude),
+            );
+        }
+        match cmp_magnitude(&self.magnitude, &rhs.magnitude) {
+            Ordering::Less => {
+                BigInt::new(rhs.negative, sub_magnitude(&rhs.magnitude, &self.magnitude))
+            }
+            _ => BigInt::new(
+                self.negative,
+                sub_magnitude(&self.magnitude, &rhs.magnitude),
+            ),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+    fn sub(self, rhs: Self) -> BigInt {
+        self + &-rhs
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+    fn mul(self, rhs: Self) -> BigInt {
+        BigInt::new(
+            self.negative != rhs.negative,
+            mul_magnitude(&self.magnitude, &rhs.magnitude),
+        )
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // Nine decimal digits at a time, least significant first
+        let mut chunks = Vec::new();
+        let mut magnitude = self.magnitude.clone();
+        while !magnitude.is_empty() {
+            let (quotient, remainder) = divrem_small(&magnitude, 1_000_000_000);
+            chunks.push(remainder);
+            magnitude = quotient;
+        }
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", chunks.pop().unwrap())?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+// A fraction in lowest terms, with a positive denominator
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rational {
+    numerator: BigInt,
+    denominator: BigInt,
+}
+
+impl Rational {
+    pub fn new(numerator: BigInt, denominator: BigInt) -> Option<Self> {
+        if denominator.is_zero() {
+            return None;
+        }
+        let divisor = numerator.gcd(&denominator);
+        let divisor = if denominator.is_negative() {
+            -&divisor
+        } else {
+            divisor
+        };
+        Some(Self {
+            numerator: numerator.divrem(&divisor)?.0,
+            denominator: denominator.divrem(&divisor)?.0,
+        })
+    }
+
+    pub fn from_integer(value: BigInt) -> Self {
+        Self {
+            numerator: value,
+            denominator: BigInt::from(1),
+        }
+    }
+
+    // The exact value of a finite float
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+        let bits = value.to_bits();
+        let exponent = ((bits >> 52) & 0x7FF) as i64;
+        let fraction = bits & ((1 << 52) - 1);
+        let (mantissa, exponent) = if exponent == 0 {
+            (fraction, -1074)
+        } else {
+            (fraction | (1 << 52), exponent - 1075)
+        };
+
+        let mut mantissa = BigInt::from(mantissa as i64);
+        if value < 0.0 {
+            mantissa = -&mantissa;
+        }
+        if exponent >= 0 {
+            let scale = BigInt::power_of_two(exponent as u32);
+            Some(Self::from_integer(&mantissa * &scale))
+        } else {
+            Self::new(mantissa, BigInt::power_of_two((-exponent) as u32))
+        }
+    }
+
+    pub fn numerator(&self) -> &BigInt {
+        &self.numerator
+    }
+
+    pub fn denominator(&self) -> &BigInt {
+        &self.denominator
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.denominator == BigInt::from(1)
+    }
+
+    pub fn floor(&self) -> BigInt {
+        self.numerator.div_floor(&self.denominator).unwrap()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.numerator.to_f64() / self.denominator.to_f64()
+    }
+
+    pub fn pow(&self, exponent: u64) -> Rational {
+        // Powers of coprime numbers are coprime, so this is in lowest terms
+        Rational {
+            numerator: self.numerator.pow(exponent),
+            denominator: self.denominator.pow(exponent),
+        }
+    }
+
+    pub fn checked_div(&self, rhs: &Rational) -> Option<Rational> {
+        Rational::new(
+            &self.numerator * &rhs.denominator,
+            &self.denominator * &rhs.numerator,
+        )
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.numerator * &other.denominator).cmp(&(&other.numerator * &self.denominator))
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for &Rational {
+    type Output = Rational;
+    fn neg(self) -> Rational {
+        Rational {
+            numerator: -&self.numerator,
+            denominator: self.denominator.clone(),
+        }
+    }
+}
+
+impl Add for &Rational {
+    type Output = Rational;
+    fn add(self, rhs: Self) -> Rational {
+        Rational::new(
+            &(&self.numerator * &rhs.denominator) + &(&rhs.numerator * &self.denominator),
+            &self.denominator * &rhs.denominator,
+        )
+        .unwrap()
+    }
+}
+
+impl Sub for &Rational {
+    type Output = Rational;
+    fn sub(self, rhs: Self) -> Rational {
+        self + &-rhs
+    }
+}
+
+impl Mul for &Rational {
+    type Output = Rational;
+    fn mul(self, rhs: Self) -> Rational {
+        Rational::new(
+            &self.numerator * &rhs.numerator,
+            &self.denominator * &rhs.denominator,
+        )
+        .unwrap()
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+impl From<BigInt> for Rational {
+    fn from(value: BigInt) -> Self {
+        Self::from_integer(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(digits: &str) -> BigInt {
+        BigInt::parse(digits).unwrap()
+    }
+
+    #[test]
+    fn t_parse_and_print() {
+        for digits in [
+            "0",
+            "-1",
+            "4294967296",
+            "-9223372036854775808",
+            "265252859812191058636308480000000",
+            "1000000000000000000000000000000000001",
+        ] {
+            assert_eq!(big(digits).to_string(), digits);
+        }
+        assert_eq!(big("-0"), BigInt::zero());
+        assert_eq!(big("007").to_string(), "7");
+        assert!(BigInt::parse("12a").is_none());
+        assert!(BigInt::parse("").is_none());
+    }
+
+    #[test]
+    fn t_arithmetic() {
+        let factorial = (1..=30).fold(BigInt::from(1), |acc, n| &acc * &BigInt::from(n));
+        assert_eq!(factorial, big("265252859812191058636308480000000"));
+        assert_eq!(
+            &big("18446744073709551616") - &big("18446744073709551617"),
+            BigInt::from(-1)
+        );
+        assert_eq!(&BigInt::from(-5) + &BigInt::from(3), BigInt::from(-2));
+        assert_eq!(
+            &BigInt::from(i64::MAX) + &BigInt::from(1),
+            big("9223372036854775808")
+        );
+        assert_eq!(BigInt::from(i64::MIN).to_i64(), Some(i64::MIN));
+        assert_eq!(big("9223372036854775808").to_i64(), None);
+    }
+
+    #[test]
+    fn t_division() {
+        let a = big("265252859812191058636308480000000");
+        let b = big("4294967311000000000007");
+        let (q, r) = a.divrem(&b).unwrap();
+        assert_eq!(&(&q * &b) + &r, a);
+        assert!(r < b);
+
+        let (q, r) = BigInt::from(-7).divrem(&BigInt::from(2)).unwrap();
+        assert_eq!((q, r), (BigInt::from(-3), BigInt::from(-1)));
+        assert_eq!(
+            BigInt::from(-7).div_floor(&BigInt::from(2)),
+            Some(BigInt::from(-4))
+        );
+        assert!(a.divrem(&BigInt::zero()).is_none());
+        assert_eq!(
+            big("-12345678901234567890").gcd(&big("9876543210")),
+            BigInt::from(90)
+        );
+    }
+
+    #[test]
+    fn t_bitwise() {
+        let and = |a: i64, b: i64| BigInt::from(a).bitwise(&BigInt::from(b), |x, y| x & y);
+        let or = |a: i64, b: i64| BigInt::from(a).bitwise(&BigInt::from(b), |x, y| x | y);
+        let xor = |a: i64, b: i64| BigInt::from(a).bitwise(&BigInt::from(b), |x, y| x ^ y);
+        for (a, b) in [
+            (12, 10),
+            (-12, 10),
+            (12, -10),
+            (-12, -10),
+            (i64::MIN, -1),
+            (0, -1),
+        ] {
+            assert_eq!(and(a, b), BigInt::from(a & b));
+            assert_eq!(or(a, b), BigInt::from(a | b));
+            assert_eq!(xor(a, b), BigInt::from(a ^ b));
+        }
+        let big = BigInt::power_of_two(100);
+        assert_eq!(big.bitwise(&BigInt::from(-1), |x, y| x & y), big);
+        assert_eq!(
+            (-&big).bitwise(&BigInt::from(-1), |x, y| x ^ y),
+            &big - &BigInt::from(1)
+        );
+        assert_eq!(BigInt::from(2).pow(100), big);
+        assert_eq!(BigInt::from(-3).pow(3), BigInt::from(-27));
+        assert_eq!(BigInt::from(7).pow(0), BigInt::from(1));
+    }
+
+    #[test]
+    fn t_rationals() {
+        let third = Rational::new(BigInt::from(1), BigInt::from(3)).unwrap();
+        let two_sixths = Rational::new(BigInt::from(-2), BigInt::from(-6)).unwrap();
+        assert_eq!(third, two_sixths);
+        assert_eq!(third.to_string(), "1/3");
+        assert_eq!((&(&third + &third) + &third).to_string(), "1");
+        assert_eq!((&third - &BigInt::from(1).into()).to_string(), "-2/3");
+        assert_eq!((&third - &BigInt::from(1).into()).floor(), BigInt::from(-1));
+        assert!(third < Rational::from_f64(0.34).unwrap());
+        assert_eq!(Rational::from_f64(-2.5).unwrap().to_string(), "-5/2");
+        assert_eq!(
+            Rational::from_f64(1e20).unwrap().to_string(),
+            "100000000000000000000"
+        );
+        assert!(Rational::new(BigInt::from(1), BigInt::zero()).is_none());
+    }
+}
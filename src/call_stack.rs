@@ -0,0 +1,110 @@
+use crate::{error::TeciResult, object::Object, token::Token};
+
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+#[derive(Debug, Clone)]
+pub struct CallFrame {
+    pub callee: String,
+    pub call_site: Token,
+    pub arguments: Vec<Object>,
+}
+
+impl CallFrame {
+    pub fn signature(&self) -> String {
+        let arguments: Vec<String> = self.arguments.iter().map(|a| a.to_string()).collect();
+        format!("{}({})", self.callee, arguments.join(", "))
+    }
+}
+
+#[derive(Debug)]
+pub struct CallStack {
+    frames: Vec<CallFrame>,
+    max_depth: usize,
+}
+
+impl CallStack {
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            frames: Vec::new(),
+            max_depth,
+        }
+    }
+
+    pub fn push(&mut self, frame: CallFrame) -> Result<(), TeciResult> {
+        if self.frames.len() >= self.max_depth {
+            return Err(TeciResult::runtime_error(
+                frame.call_site,
+                &format!("Stack overflow (maximum call depth is {})", self.max_depth),
+            ));
+        }
+        self.frames.push(frame);
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Option<CallFrame> {
+        self.frames.pop()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn frames(&self) -> &[CallFrame] {
+        &self.frames
+    }
+
+    pub fn truncate(&mut self, depth: usize) {
+        self.frames.truncate(depth);
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    pub fn take(&mut self) -> Vec<CallFrame> {
+        std::mem::take(&mut self.frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token_type::TokenType;
+
+    fn frame(callee: &str) -> CallFrame {
+        CallFrame {
+            callee: callee.to_string(),
+            call_site: Token::new(TokenType::RightParen, ")", None, 1),
+            arguments: vec![Object::Int(1), Object::Str("a".into())],
+        }
+    }
+
+    #[test]
+    fn t_push_and_pop() {
+        let mut stack = CallStack::new(DEFAULT_MAX_DEPTH);
+        assert!(stack.push(frame("f")).is_ok());
+        assert!(stack.push(frame("g")).is_ok());
+        assert_eq!(stack.depth(), 2);
+        assert_eq!(stack.pop().unwrap().callee, "g");
+        assert_eq!(stack.frames()[0].callee, "f");
+        stack.truncate(0);
+        assert!(stack.pop().is_none());
+    }
+
+    #[test]
+    fn t_max_depth() {
+        let mut stack = CallStack::new(2);
+        assert!(stack.push(frame("f")).is_ok());
+        assert!(stack.push(frame("f")).is_ok());
+        assert!(matches!(
+            stack.push(frame("f")),
+            Err(TeciResult::RuntimeError { .. })
+        ));
+        assert_eq!(stack.take().len(), 2);
+    }
+
+    #[test]
+    fn t_signature() {
+        assert_eq!(frame("f").signature(), "f(1, a)");
+    }
+}
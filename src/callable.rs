@@ -2,12 +2,36 @@ use std::fmt::Debug;
 use std::fmt::{self, Display};
 use std::rc::Rc;
 
-use crate::{error::TeciResult, interpreter::Interpreter, object::Object};
+use crate::{
+    error::TeciResult, gc::Traced, interpreter::Interpreter, object::Object, sandbox::Capability,
+    token::Token,
+};
 
 pub trait TeciCallable {
     fn arity(&self) -> usize;
     fn call(&self, interpreter: &Interpreter, args: Vec<Object>) -> Result<Object, TeciResult>;
+    // Like `call`, but may give back the call it ends with as a
+    // `TeciResult::TailCall` instead of making it
+    fn call_once(
+        &self,
+        interpreter: &Interpreter,
+        args: Vec<Object>,
+    ) -> Result<Object, TeciResult> {
+        self.call(interpreter, args)
+    }
+    fn name(&self) -> String;
     fn to_string(&self) -> String;
+    // The name in the declaration of a function written in teci, which tells
+    // it apart from others with the same name
+    fn declaration(&self) -> Option<&Token> {
+        None
+    }
+    // What a sandbox must grant for the callable to be called
+    fn capability(&self) -> Option<Capability> {
+        None
+    }
+    // Visits the environments and values the callable keeps alive
+    fn trace(&self, _visit: &mut dyn FnMut(Traced)) {}
 }
 
 #[derive(Clone)]
@@ -42,7 +66,31 @@ impl TeciCallable for Callable {
         self.func.call(interpreter, args)
     }
 
+    fn call_once(
+        &self,
+        interpreter: &Interpreter,
+        args: Vec<Object>,
+    ) -> Result<Object, TeciResult> {
+        self.func.call_once(interpreter, args)
+    }
+
+    fn name(&self) -> String {
+        self.func.name()
+    }
+
     fn to_string(&self) -> String {
         self.func.to_string()
     }
+
+    fn declaration(&self) -> Option<&Token> {
+        self.func.declaration()
+    }
+
+    fn capability(&self) -> Option<Capability> {
+        self.func.capability()
+    }
+
+    fn trace(&self, visit: &mut dyn FnMut(Traced)) {
+        self.func.trace(visit)
+    }
 }
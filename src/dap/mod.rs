@@ -0,0 +1,687 @@
+use std::{
+    cell::{Cell, RefCell},
+    io::{self, BufRead, Write},
+    rc::Rc,
+};
+
+use crate::{
+    debugger::{DebugHook, PauseReason, StepMode, Stepper, statement_line},
+    dialect::Dialect,
+    envirnoment::Environment,
+    error::TeciResult,
+    interpreter::Interpreter,
+    json::{self, Json},
+    parser::Parser,
+    scanner::Scanner,
+    stmt::Stmt,
+};
+
+// teci runs scripts on a single thread, which is the only one reported
+const THREAD_ID: usize = 1;
+
+// Reads and writes Debug Adapter Protocol messages, which are JSON bodies
+// framed by a `Content-Length` header
+pub struct Connection<R: BufRead, W: Write> {
+    input: RefCell<R>,
+    output: RefCell<W>,
+    seq: Cell<usize>,
+}
+
+impl<R: BufRead, W: Write> Connection<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self {
+            input: RefCell::new(input),
+            output: RefCell::new(output),
+            seq: Cell::new(1),
+        }
+    }
+
+    pub fn read_message(&self) -> Option<Json> {
+        json::read_framed(&mut *self.input.borrow_mut())
+    }
+
+    fn send(&self, mut fields: Vec<(&str, Json)>) {
+        let seq = self.seq.get();
+        self.seq.set(seq + 1);
+        fields.insert(0, ("seq", seq.into()));
+        json::write_framed(&mut *self.output.borrow_mut(), &Json::object(fields));
+    }
+
+    pub fn respond(&self, request: &Json, body: Json) {
+        self.send(vec![
+            ("type", Json::str("response")),
+            ("request_seq", request_seq(request)),
+            ("success", true.into()),
+            ("command", Json::str(command(request))),
+            ("body", body),
+        ]);
+    }
+
+    pub fn respond_error(&self, request: &Json, message: &str) {
+        self.send(vec![
+            ("type", Json::str("response")),
+            ("request_seq", request_seq(request)),
+            ("success", false.into()),
+            ("command", Json::str(command(request))),
+            ("message", Json::str(message)),
+        ]);
+    }
+
+    pub fn event(&self, event: &str, body: Json) {
+        self.send(vec![
+            ("type", Json::str("event")),
+            ("event", Json::str(event)),
+            ("body", body),
+        ]);
+    }
+}
+
+fn request_seq(request: &Json) -> Json {
+    request.get("seq").cloned().unwrap_or(Json::Null)
+}
+
+fn command(request: &Json) -> &str {
+    request.get("command").and_then(Json::as_str).unwrap_or("")
+}
+
+fn argument<'a>(request: &'a Json, name: &str) -> Option<&'a Json> {
+    request.get("arguments")?.get(name)
+}
+
+fn source(path: &str) -> Json {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    Json::object(vec![("name", Json::str(name)), ("path", Json::str(path))])
+}
+
+fn set_breakpoints<R: BufRead, W: Write>(
+    connection: &Connection<R, W>,
+    stepper: &Stepper,
+    request: &Json,
+) {
+    stepper.clear_breakpoints();
+    let lines: Vec<usize> = argument(request, "breakpoints")
+        .and_then(Json::as_array)
+        .unwrap_or(&[])
+        .iter()
+        .filter_map(|b| b.get("line").and_then(Json::as_usize))
+        .collect();
+    let breakpoints = lines
+        .iter()
+        .map(|line| {
+            stepper.add_breakpoint(*line);
+            Json::object(vec![("verified", true.into()), ("line", (*line).into())])
+        })
+        .collect();
+    connection.respond(
+        request,
+        Json::object(vec![("breakpoints", Json::Array(breakpoints))]),
+    );
+}
+
+fn describe_error(error: &TeciResult) -> String {
+    match error {
+        TeciResult::Backtrace { error, .. } => describe_error(error),
+        TeciResult::ParseError { token, message }
+        | TeciResult::ResolveError { token, message }
+        | TeciResult::TypeError { token, message }
+        | TeciResult::RuntimeError { token, message } => {
+            format!("Error in line {}: {}", token.line, message)
+        }
+        TeciResult::TeciError { line, message } => format!("Error in line {}: {}", line, message),
+        TeciResult::SystemError { message } => message.clone(),
+        _ => "Unknown error".to_string(),
+    }
+}
+
+// Sends whatever the script prints as `output` events, one per line
+struct OutputEvents<R: BufRead, W: Write> {
+    connection: Rc<Connection<R, W>>,
+    buffer: Vec<u8>,
+}
+
+impl<R: BufRead, W: Write> Write for OutputEvents<R, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            self.connection.event(
+                "output",
+                Json::object(vec![
+                    ("category", Json::str("stdout")),
+                    (
+                        "output",
+                        Json::String(String::from_utf8_lossy(&line).into()),
+                    ),
+                ]),
+            );
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct DapHook<R: BufRead, W: Write> {
+    connection: Rc<Connection<R, W>>,
+    stepper: Rc<Stepper>,
+    path: String,
+    stop_on_entry: Cell<bool>,
+    evaluating: Cell<bool>,
+    // Expressions to evaluate are written in the program's dialect
+    dialect: Dialect,
+    // Environments listed by the last `scopes` request, indexed by their
+    // variables reference minus one
+    scopes: RefCell<Vec<Rc<RefCell<Environment>>>>,
+}
+
+impl<R: BufRead, W: Write> DapHook<R, W> {
+    fn pause(
+        &self,
+        interpreter: &Interpreter,
+        line: usize,
+        reason: &str,
+    ) -> Result<(), TeciResult> {
+        self.scopes.borrow_mut().clear();
+        self.connection.event(
+            "stopped",
+            Json::object(vec![
+                ("reason", Json::str(reason)),
+                ("threadId", THREAD_ID.into()),
+                ("allThreadsStopped", true.into()),
+            ]),
+        );
+
+        let depth = interpreter.call_depth();
+        loop {
+            let Some(request) = self.connection.read_message() else {
+                return Err(TeciResult::Terminate);
+            };
+            let resume = match command(&request) {
+                "continue" => Some(StepMode::Continue),
+                "next" => Some(StepMode::Over(depth)),
+                "stepIn" => Some(StepMode::Into),
+                "stepOut" => Some(StepMode::Out(depth)),
+                _ => None,
+            };
+            if let Some(mode) = resume {
+                self.stepper.set_mode(mode);
+                self.connection.respond(
+                    &request,
+                    Json::object(vec![("allThreadsContinued", true.into())]),
+                );
+                return Ok(());
+            }
+
+            match command(&request) {
+                "threads" => self.connection.respond(&request, threads()),
+                "stackTrace" => self.stack_trace(interpreter, &request, line),
+                "scopes" => self.scopes(interpreter, &request),
+                "variables" => self.variables(&request),
+                "evaluate" => self.evaluate(interpreter, &request),
+                "setBreakpoints" => set_breakpoints(&self.connection, &self.stepper, &request),
+                "disconnect" | "terminate" => {
+                    self.connection.respond(&request, Json::Null);
+                    return Err(TeciResult::Terminate);
+                }
+                _ => self.connection.respond(&request, Json::Null),
+            }
+        }
+    }
+
+    fn stack_trace(&self, interpreter: &Interpreter, request: &Json, line: usize) {
+        let mut frames = Vec::new();
+        let mut line = line;
+        for frame in interpreter.call_frames().iter().rev() {
+            frames.push((frame.callee.clone(), line));
+            line = frame.call_site.line;
+        }
+        frames.push(("<script>".to_string(), line));
+
+        let stack_frames: Vec<Json> = frames
+            .into_iter()
+            .enumerate()
+            .map(|(id, (name, line))| {
+                Json::object(vec![
+                    ("id", id.into()),
+                    ("name", Json::String(name)),
+                    ("source", source(&self.path)),
+                    ("line", line.into()),
+                    ("column", 1.into()),
+                ])
+            })
+            .collect();
+        let total = stack_frames.len();
+        self.connection.respond(
+            request,
+            Json::object(vec![
+                ("stackFrames", Json::Array(stack_frames)),
+                ("totalFrames", total.into()),
+            ]),
+        );
+    }
+
+    fn scopes(&self, interpreter: &Interpreter, request: &Json) {
+        let mut chain = Vec::new();
+        let mut environment = Some(interpreter.environment());
+        while let Some(env) = environment {
+            environment = env.borrow().enclosing();
+            chain.push(env);
+        }
+        // Only the innermost frame's environment is still around; outer
+        // frames can see the globals at least
+        let frame_id = argument(request, "frameId").and_then(Json::as_usize);
+        if frame_id != Some(0) {
+            chain.drain(..chain.len() - 1);
+        }
+
+        let last = chain.len() - 1;
+        let scopes: Vec<Json> = chain
+            .iter()
+            .enumerate()
+            .map(|(i, _)| {
+                let name = match i {
+                    i if i == last => "Globals".to_string(),
+                    0 => "Locals".to_string(),
+                    i => format!("Enclosing {}", i),
+                };
+                Json::object(vec![
+                    ("name", Json::String(name)),
+                    (
+                        "variablesReference",
+                        (self.scopes.borrow().len() + i + 1).into(),
+                    ),
+                    ("expensive", false.into()),
+                ])
+            })
+            .collect();
+        self.scopes.borrow_mut().extend(chain);
+        self.connection
+            .respond(request, Json::object(vec![("scopes", Json::Array(scopes))]));
+    }
+
+    fn variables(&self, request: &Json) {
+        let reference = argument(request, "variablesReference").and_then(Json::as_usize);
+        let Some(env) =
+            reference.and_then(|r| self.scopes.borrow().get(r.wrapping_sub(1)).cloned())
+        else {
+            self.connection
+                .respond_error(request, "Unknown variables reference");
+            return;
+        };
+
+        let variables: Vec<Json> = env
+            .borrow()
+            .bindings()
+            .into_iter()
+            .map(|(name, value)| {
+                Json::object(vec![
+                    ("name", Json::String(name)),
+                    ("value", Json::String(Interpreter::stringify(value))),
+                    ("variablesReference", 0.into()),
+                ])
+            })
+            .collect();
+        self.connection.respond(
+            request,
+            Json::object(vec![("variables", Json::Array(variables))]),
+        );
+    }
+
+    fn evaluate(&self, interpreter: &Interpreter, request: &Json) {
+        let expression = argument(request, "expression")
+            .and_then(Json::as_str)
+            .unwrap_or("");
+        let value = Scanner::new(expression.to_string())
+            .with_dialect(self.dialect.clone())
+            .scan_tokens()
+            .and_then(|tokens| Parser::new(tokens).parse_expression())
+            .and_then(|expr| {
+                // Calls made while evaluating must not pause the program
+                self.evaluating.set(true);
+                let value = interpreter.evaluate_detached(&expr);
+                self.evaluating.set(false);
+                value
+            });
+
+        match value {
+            Ok(value) => self.connection.respond(
+                request,
+                Json::object(vec![
+                    ("result", Json::String(Interpreter::stringify(value))),
+                    ("variablesReference", 0.into()),
+                ]),
+            ),
+            Err(e) => self.connection.respond_error(request, &describe_error(&e)),
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> DebugHook for DapHook<R, W> {
+    fn before_statement(
+        &self,
+        interpreter: &Interpreter,
+        statement: &Stmt,
+    ) -> Result<(), TeciResult> {
+        if self.evaluating.get() {
+            return Ok(());
+        }
+
+        let line = statement_line(statement);
+        match self
+            .stepper
+            .should_pause(statement, interpreter.call_depth())
+        {
+            Some(_) if self.stop_on_entry.replace(false) => self.pause(interpreter, line, "entry"),
+            Some(PauseReason::Step) => self.pause(interpreter, line, "step"),
+            Some(PauseReason::Breakpoint) => self.pause(interpreter, line, "breakpoint"),
+            None => Ok(()),
+        }
+    }
+}
+
+fn threads() -> Json {
+    Json::object(vec![(
+        "threads",
+        Json::Array(vec![Json::object(vec![
+            ("id", THREAD_ID.into()),
+            ("name", Json::str("main")),
+        ])]),
+    )])
+}
+
+struct Launch {
+    path: String,
+    source: String,
+    stop_on_entry: bool,
+}
+
+pub fn serve<R: BufRead + 'static, W: Write + 'static>(input: R, output: W) {
+    let connection = Rc::new(Connection::new(input, output));
+    let stepper = Rc::new(Stepper::new(StepMode::Continue));
+
+    // Configuration: wait for both the program to launch and the client to
+    // finish sending breakpoints
+    let mut launch = None;
+    let mut configured = false;
+    while launch.is_none() || !configured {
+        let Some(request) = connection.read_message() else {
+            return;
+        };
+        match command(&request) {
+            "initialize" => {
+                connection.respond(
+                    &request,
+                    Json::object(vec![
+                        ("supportsConfigurationDoneRequest", true.into()),
+                        ("supportsEvaluateForHovers", true.into()),
+                    ]),
+                );
+                connection.event("initialized", Json::Null);
+            }
+            "launch" => {
+                let path = argument(&request, "program")
+                    .and_then(Json::as_str)
+                    .unwrap_or("")
+                    .to_string();
+                match std::fs::read_to_string(&path) {
+                    Ok(source) => {
+                        launch = Some(Launch {
+                            path,
+                            source,
+                            stop_on_entry: argument(&request, "stopOnEntry")
+                                .and_then(Json::as_bool)
+                                .unwrap_or(false),
+                        });
+                        connection.respond(&request, Json::Null);
+                    }
+                    Err(e) => connection
+                        .respond_error(&request, &format!("Could not read {}: {}", path, e)),
+                }
+            }
+            "setBreakpoints" => set_breakpoints(&connection, &stepper, &request),
+            "configurationDone" => {
+                configured = true;
+                connection.respond(&request, Json::Null);
+            }
+            "threads" => connection.respond(&request, threads()),
+            "disconnect" => {
+                connection.respond(&request, Json::Null);
+                return;
+            }
+            _ => connection.respond(&request, Json::Null),
+        }
+    }
+    let Some(launch) = launch else {
+        return;
+    };
+
+    if launch.stop_on_entry {
+        stepper.set_mode(StepMode::Into);
+    }
+    let interpreter = Interpreter::new();
+    interpreter.set_output(Box::new(OutputEvents {
+        connection: Rc::clone(&connection),
+        buffer: Vec::new(),
+    }));
+    // Stdin carries the protocol, so to `input()` it is at its end
+    interpreter.set_input(Box::new(io::empty()));
+    interpreter.set_debug_hook(Rc::new(DapHook {
+        connection: Rc::clone(&connection),
+        stepper: Rc::clone(&stepper),
+        path: launch.path,
+        stop_on_entry: Cell::new(launch.stop_on_entry),
+        evaluating: Cell::new(false),
+        dialect: Dialect::of_source(&launch.source, true)
+            .and_then(Result::ok)
+            .unwrap_or_else(Dialect::classic),
+        scopes: RefCell::new(Vec::new()),
+    }));
+
+    let result = Scanner::new(launch.source)
+        .scan_tokens()
+        .and_then(|tokens| Parser::new(tokens).parse())
+        .and_then(|statements| interpreter.interpret(&statements));
+    let exit_code = match &result {
+        Ok(_) => 0,
+        Err(TeciResult::Backtrace { .. }) => 70,
+        Err(_) => 65,
+    };
+    if let Err(e) = &result {
+        connection.event(
+            "output",
+            Json::object(vec![
+                ("category", Json::str("stderr")),
+                ("output", Json::String(format!("{}\n", describe_error(e)))),
+            ]),
+        );
+    }
+    connection.event("exited", Json::object(vec![("exitCode", exit_code.into())]));
+    connection.event("terminated", Json::Null);
+
+    while let Some(request) = connection.read_message() {
+        connection.respond(&request, Json::Null);
+        if command(&request) == "disconnect" {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const PROGRAM: &str = "fun add(a, b) {
+    let sum = a + b;
+    return sum;
+}
+let x = 1;
+print add(x, 2);
+print x;
+";
+
+    // Requests recorded from an editor session that sets a breakpoint inside
+    // `add`, inspects it and lets the program finish
+    const SESSION: &[&str] = &[
+        r#"{"seq":1,"type":"request","command":"initialize","arguments":{"clientID":"vscode","adapterID":"teci","linesStartAt1":true}}"#,
+        r#"{"seq":2,"type":"request","command":"launch","arguments":{"program":"PROGRAM","stopOnEntry":false}}"#,
+        r#"{"seq":3,"type":"request","command":"setBreakpoints","arguments":{"source":{"path":"PROGRAM"},"breakpoints":[{"line":3}]}}"#,
+        r#"{"seq":4,"type":"request","command":"configurationDone"}"#,
+        r#"{"seq":5,"type":"request","command":"threads"}"#,
+        r#"{"seq":6,"type":"request","command":"stackTrace","arguments":{"threadId":1}}"#,
+        r#"{"seq":7,"type":"request","command":"scopes","arguments":{"frameId":0}}"#,
+        r#"{"seq":8,"type":"request","command":"variables","arguments":{"variablesReference":1}}"#,
+        r#"{"seq":9,"type":"request","command":"evaluate","arguments":{"expression":"sum * 10","frameId":0,"context":"hover"}}"#,
+        r#"{"seq":10,"type":"request","command":"next","arguments":{"threadId":1}}"#,
+        r#"{"seq":11,"type":"request","command":"continue","arguments":{"threadId":1}}"#,
+        r#"{"seq":12,"type":"request","command":"disconnect"}"#,
+    ];
+
+    fn frame(message: &str) -> String {
+        format!("Content-Length: {}\r\n\r\n{}", message.len(), message)
+    }
+
+    fn replay(session: &[&str]) -> Vec<Json> {
+        let path = std::env::temp_dir().join(format!("teci-dap-{}.tc", std::process::id()));
+        std::fs::write(&path, PROGRAM).unwrap();
+        let path = path.to_string_lossy().replace('\\', "\\\\");
+        let input: String = session
+            .iter()
+            .map(|m| frame(&m.replace("PROGRAM", &path)))
+            .collect();
+
+        let output = Rc::new(RefCell::new(Vec::new()));
+        serve(
+            Cursor::new(input.into_bytes()),
+            SharedBuffer(Rc::clone(&output)),
+        );
+
+        let output = output.borrow();
+        let connection = Connection::new(Cursor::new(output.clone()), Vec::new());
+        std::iter::from_fn(|| connection.read_message()).collect()
+    }
+
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn response<'a>(messages: &'a [Json], command: &str) -> &'a Json {
+        messages
+            .iter()
+            .find(|m| {
+                m.get("type").and_then(Json::as_str) == Some("response")
+                    && m.get("command").and_then(Json::as_str) == Some(command)
+            })
+            .unwrap_or_else(|| panic!("no response to {}", command))
+    }
+
+    fn events<'a>(messages: &'a [Json], event: &str) -> Vec<&'a Json> {
+        messages
+            .iter()
+            .filter(|m| m.get("event").and_then(Json::as_str) == Some(event))
+            .filter_map(|m| m.get("body"))
+            .collect()
+    }
+
+    #[test]
+    fn t_breakpoint_session() {
+        let messages = replay(SESSION);
+
+        let breakpoints = response(&messages, "setBreakpoints").get("body").unwrap();
+        assert_eq!(
+            breakpoints.to_string(),
+            r#"{"breakpoints":[{"verified":true,"line":3}]}"#
+        );
+
+        let stopped = events(&messages, "stopped");
+        assert_eq!(stopped.len(), 2);
+        assert_eq!(
+            stopped[0].get("reason").and_then(Json::as_str),
+            Some("breakpoint")
+        );
+        assert_eq!(
+            stopped[1].get("reason").and_then(Json::as_str),
+            Some("step")
+        );
+
+        let frames = response(&messages, "stackTrace")
+            .get("body")
+            .and_then(|b| b.get("stackFrames"))
+            .and_then(Json::as_array)
+            .unwrap();
+        let frames: Vec<(&str, usize)> = frames
+            .iter()
+            .map(|f| {
+                (
+                    f.get("name").and_then(Json::as_str).unwrap(),
+                    f.get("line").and_then(Json::as_usize).unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(frames, vec![("add", 3), ("<script>", 6)]);
+
+        let scopes = response(&messages, "scopes")
+            .get("body")
+            .unwrap()
+            .to_string();
+        assert!(scopes.contains(r#""name":"Locals","variablesReference":1"#));
+        assert!(scopes.contains(r#""name":"Globals""#));
+
+        let variables = response(&messages, "variables")
+            .get("body")
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            variables,
+            concat!(
+                r#"{"variables":[{"name":"a","value":"1","variablesReference":0},"#,
+                r#"{"name":"b","value":"2","variablesReference":0},"#,
+                r#"{"name":"sum","value":"3","variablesReference":0}]}"#
+            )
+        );
+
+        let result = response(&messages, "evaluate").get("body").unwrap();
+        assert_eq!(result.get("result").and_then(Json::as_str), Some("30"));
+
+        let output: Vec<&str> = events(&messages, "output")
+            .iter()
+            .filter_map(|o| o.get("output").and_then(Json::as_str))
+            .collect();
+        assert_eq!(output, vec!["3\n", "1\n"]);
+
+        let exited = events(&messages, "exited");
+        assert_eq!(exited[0].get("exitCode").and_then(Json::as_usize), Some(0));
+        assert_eq!(events(&messages, "terminated").len(), 1);
+        assert!(response(&messages, "disconnect").get("success") == Some(&Json::Bool(true)));
+    }
+
+    #[test]
+    fn t_disconnect_while_paused() {
+        let messages = replay(&[
+            SESSION[0],
+            r#"{"seq":2,"type":"request","command":"launch","arguments":{"program":"PROGRAM","stopOnEntry":true}}"#,
+            SESSION[3],
+            r#"{"seq":5,"type":"request","command":"disconnect"}"#,
+        ]);
+
+        let stopped = events(&messages, "stopped");
+        assert_eq!(stopped.len(), 1);
+        assert_eq!(
+            stopped[0].get("reason").and_then(Json::as_str),
+            Some("entry")
+        );
+        assert!(events(&messages, "output").is_empty());
+        assert_eq!(events(&messages, "terminated").len(), 1);
+    }
+}
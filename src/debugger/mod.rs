@@ -0,0 +1,419 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeSet,
+    io::{BufRead, Write},
+};
+
+use crate::{
+    dialect::Dialect, error::TeciResult, interpreter::Interpreter, parser::Parser,
+    scanner::Scanner, stmt::Stmt,
+};
+
+pub trait DebugHook {
+    fn before_statement(
+        &self,
+        interpreter: &Interpreter,
+        statement: &Stmt,
+    ) -> Result<(), TeciResult>;
+}
+
+pub fn statement_line(statement: &Stmt) -> usize {
+    match statement {
+        Stmt::Break(stmt) => stmt.token.line,
+        Stmt::Block(stmt) => stmt.brace.line,
+        Stmt::Destructure(stmt) => stmt.keyword.line,
+        Stmt::ForIn(stmt) => stmt.keyword.line,
+        Stmt::If(stmt) => stmt.keyword.line,
+        Stmt::Expression(stmt) => stmt.token.line,
+        Stmt::Function(stmt) => stmt.name.line,
+        Stmt::Print(stmt) => stmt.keyword.line,
+        Stmt::Return(stmt) => stmt.keyword.line,
+        Stmt::Let(stmt) => stmt.name.line,
+        Stmt::While(stmt) => stmt.keyword.line,
+        Stmt::Yield(stmt) => stmt.keyword.line,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepMode {
+    Continue,
+    Into,
+    // Pause once the call depth is back to (at most) the given one
+    Over(usize),
+    // Pause once the call depth drops below the given one
+    Out(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PauseReason {
+    Step,
+    Breakpoint,
+}
+
+// Breakpoints and stepping state shared by the debugger front ends
+pub struct Stepper {
+    breakpoints: RefCell<BTreeSet<usize>>,
+    mode: Cell<StepMode>,
+    // Line and call depth of the last statement executed, so a line holding
+    // several (nested) statements only pauses once
+    last_location: Cell<Option<(usize, usize)>>,
+    // The statements executed at that location since it was reached. One of
+    // them running again means a loop on the line went round, which a
+    // breakpoint pauses for, though stepping does not.
+    executed: RefCell<Vec<*const Stmt>>,
+}
+
+impl Stepper {
+    pub fn new(mode: StepMode) -> Self {
+        Self {
+            breakpoints: RefCell::new(BTreeSet::new()),
+            mode: Cell::new(mode),
+            last_location: Cell::new(None),
+            executed: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn set_mode(&self, mode: StepMode) {
+        self.mode.set(mode);
+    }
+
+    pub fn add_breakpoint(&self, line: usize) {
+        self.breakpoints.borrow_mut().insert(line);
+    }
+
+    pub fn remove_breakpoint(&self, line: usize) -> bool {
+        self.breakpoints.borrow_mut().remove(&line)
+    }
+
+    pub fn clear_breakpoints(&self) {
+        self.breakpoints.borrow_mut().clear();
+    }
+
+    pub fn has_breakpoint(&self, line: usize) -> bool {
+        self.breakpoints.borrow().contains(&line)
+    }
+
+    pub fn should_pause(&self, statement: &Stmt, depth: usize) -> Option<PauseReason> {
+        let line = statement_line(statement);
+        let location = Some((line, depth));
+        let moved = self.last_location.replace(location) != location;
+        let mut executed = self.executed.borrow_mut();
+        let repeated = executed.iter().any(|&s| std::ptr::eq(s, statement));
+        if moved || repeated {
+            executed.clear();
+        }
+        executed.push(statement);
+
+        let stepping = match self.mode.get() {
+            StepMode::Continue => false,
+            StepMode::Into => true,
+            StepMode::Over(d) => depth <= d,
+            StepMode::Out(d) => depth < d,
+        };
+        if stepping && moved {
+            Some(PauseReason::Step)
+        } else if self.has_breakpoint(line) && (moved || repeated) {
+            Some(PauseReason::Breakpoint)
+        } else {
+            None
+        }
+    }
+}
+
+const HELP: &str = "Commands:
+    break <line>      (b)   set a breakpoint
+    delete <line>     (d)   remove a breakpoint
+    step              (s)   step into the next statement
+    next              (n)   step over function calls
+    finish            (f)   step out of the current function
+    continue          (c)   run until the next breakpoint
+    print <expr>      (p)   evaluate an expression in the current scope
+    backtrace         (bt)  show the call stack
+    list              (l)   show the source around the current line
+    quit              (q)   stop the program";
+
+pub struct Debugger<R: BufRead, W: Write> {
+    source: Vec<String>,
+    // Expressions to print are written in the program's dialect
+    dialect: Dialect,
+    stepper: Stepper,
+    evaluating: Cell<bool>,
+    input: RefCell<R>,
+    output: RefCell<W>,
+}
+
+impl<R: BufRead, W: Write> Debugger<R, W> {
+    pub fn new(source: &str, input: R, output: W) -> Self {
+        Self {
+            source: source.lines().map(|l| l.to_string()).collect(),
+            dialect: Dialect::classic(),
+            // Pause on the first statement so breakpoints can be set
+            stepper: Stepper::new(StepMode::Into),
+            evaluating: Cell::new(false),
+            input: RefCell::new(input),
+            output: RefCell::new(output),
+        }
+    }
+
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    fn say(&self, text: &str) {
+        let mut output = self.output.borrow_mut();
+        let _ = writeln!(output, "{}", text);
+        let _ = output.flush();
+    }
+
+    fn source_line(&self, line: usize) -> &str {
+        self.source
+            .get(line.wrapping_sub(1))
+            .map(|l| l.trim())
+            .unwrap_or("")
+    }
+
+    fn read_command(&self) -> Option<String> {
+        {
+            let mut output = self.output.borrow_mut();
+            let _ = write!(output, "(teci-db) ");
+            let _ = output.flush();
+        }
+        let mut command = String::new();
+        match self.input.borrow_mut().read_line(&mut command) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(command.trim().to_string()),
+        }
+    }
+
+    fn prompt(&self, interpreter: &Interpreter, line: usize) -> Result<(), TeciResult> {
+        self.say(&format!("[line {}] {}", line, self.source_line(line)));
+        let depth = interpreter.call_depth();
+
+        loop {
+            let Some(command) = self.read_command() else {
+                // No more commands, so let the program run to completion
+                self.stepper.clear_breakpoints();
+                self.stepper.set_mode(StepMode::Continue);
+                return Ok(());
+            };
+            let (name, argument) = command
+                .split_once(' ')
+                .map(|(n, a)| (n, a.trim()))
+                .unwrap_or((command.as_str(), ""));
+
+            match name {
+                "" => {}
+                "b" | "break" => match argument.parse::<usize>() {
+                    Ok(line) => {
+                        self.stepper.add_breakpoint(line);
+                        self.say(&format!("Breakpoint set at line {}", line));
+                    }
+                    Err(_) => self.say("Usage: break <line>"),
+                },
+                "d" | "delete" => match argument.parse::<usize>() {
+                    Ok(line) if self.stepper.remove_breakpoint(line) => {
+                        self.say(&format!("Breakpoint at line {} removed", line))
+                    }
+                    Ok(line) => self.say(&format!("No breakpoint at line {}", line)),
+                    Err(_) => self.say("Usage: delete <line>"),
+                },
+                "s" | "step" => {
+                    self.stepper.set_mode(StepMode::Into);
+                    return Ok(());
+                }
+                "n" | "next" => {
+                    self.stepper.set_mode(StepMode::Over(depth));
+                    return Ok(());
+                }
+                "f" | "finish" => {
+                    self.stepper.set_mode(StepMode::Out(depth));
+                    return Ok(());
+                }
+                "c" | "continue" => {
+                    self.stepper.set_mode(StepMode::Continue);
+                    return Ok(());
+                }
+                "p" | "print" => self.print(interpreter, argument),
+                "bt" | "backtrace" => self.backtrace(interpreter, line),
+                "l" | "list" => self.list(line),
+                "q" | "quit" => return Err(TeciResult::Terminate),
+                "h" | "help" => self.say(HELP),
+                _ => self.say(&format!("Unknown command '{}', try 'help'", name)),
+            }
+        }
+    }
+
+    fn print(&self, interpreter: &Interpreter, source: &str) {
+        let Ok(tokens) = Scanner::new(source.to_string())
+            .with_dialect(self.dialect.clone())
+            .scan_tokens()
+        else {
+            return;
+        };
+        let Ok(expr) = Parser::new(tokens).parse_expression() else {
+            return;
+        };
+
+        // Calls made while evaluating must not pause the debugger
+        self.evaluating.set(true);
+        let value = interpreter.evaluate_detached(&expr);
+        self.evaluating.set(false);
+
+        if let Ok(value) = value {
+            self.say(&Interpreter::stringify(value));
+        }
+    }
+
+    fn backtrace(&self, interpreter: &Interpreter, line: usize) {
+        let mut line = line;
+        for (i, frame) in interpreter.call_frames().iter().rev().enumerate() {
+            self.say(&format!("#{} {} at line {}", i, frame.signature(), line));
+            line = frame.call_site.line;
+        }
+        self.say(&format!(
+            "#{} <script> at line {}",
+            interpreter.call_depth(),
+            line
+        ));
+    }
+
+    fn list(&self, line: usize) {
+        let first = line.saturating_sub(3).max(1);
+        let last = (line + 3).min(self.source.len());
+        for n in first..=last {
+            let marker = if n == line { "->" } else { "  " };
+            let breakpoint = if self.stepper.has_breakpoint(n) {
+                "*"
+            } else {
+                " "
+            };
+            self.say(&format!(
+                "{}{}{:>4} {}",
+                marker,
+                breakpoint,
+                n,
+                self.source[n - 1]
+            ));
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> DebugHook for Debugger<R, W> {
+    fn before_statement(
+        &self,
+        interpreter: &Interpreter,
+        statement: &Stmt,
+    ) -> Result<(), TeciResult> {
+        if self.evaluating.get() {
+            return Ok(());
+        }
+
+        let line = statement_line(statement);
+        match self
+            .stepper
+            .should_pause(statement, interpreter.call_depth())
+        {
+            Some(_) => self.prompt(interpreter, line),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, rc::Rc};
+
+    use super::*;
+
+    const SOURCE: &str = "fun add(a, b) {
+    let sum = a + b;
+    return sum;
+}
+let x = 1;
+let y = add(x, 2);
+print y;";
+
+    // Runs SOURCE under a debugger fed with the given commands and returns
+    // everything the debugger wrote
+    fn debug(commands: &str) -> String {
+        debug_source(SOURCE, commands)
+    }
+
+    fn debug_source(source: &str, commands: &str) -> String {
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let debugger = Debugger::new(
+            source,
+            Cursor::new(commands.to_string()),
+            SharedBuffer(Rc::clone(&output)),
+        );
+        let interpreter = Interpreter::new();
+        interpreter.set_debug_hook(Rc::new(debugger));
+
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        assert!(interpreter.interpret(&statements).is_ok());
+
+        String::from_utf8(output.borrow().clone()).unwrap()
+    }
+
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn paused_lines(output: &str) -> Vec<usize> {
+        output
+            .lines()
+            .filter_map(|l| l.split("[line ").nth(1))
+            .filter_map(|l| l.split(']').next()?.parse().ok())
+            .collect()
+    }
+
+    #[test]
+    fn t_step_into() {
+        let output = debug("s\ns\ns\ns\ns\ns\n");
+        assert_eq!(paused_lines(&output), vec![1, 5, 6, 2, 3, 7]);
+    }
+
+    #[test]
+    fn t_step_over() {
+        let output = debug("n\nn\nn\nn\n");
+        assert_eq!(paused_lines(&output), vec![1, 5, 6, 7]);
+    }
+
+    #[test]
+    fn t_step_out() {
+        let output = debug("b 2\nc\nf\nc\n");
+        assert_eq!(paused_lines(&output), vec![1, 2, 7]);
+    }
+
+    #[test]
+    fn t_print_and_backtrace() {
+        let output = debug("b 3\nc\np sum * 10\nbt\nc\n");
+        assert!(output.contains("30\n"));
+        assert!(output.contains("#0 add(1, 2) at line 3"));
+        assert!(output.contains("#1 <script> at line 6"));
+    }
+
+    #[test]
+    fn t_breakpoint_in_one_line_loop() {
+        // A breakpoint pauses on every time round a loop written on one
+        // line, while stepping goes over the whole line at once
+        let source = "let i = 0;\nwhile (i < 3) i++;\nprint i;";
+        let output = debug_source(source, "b 2\nc\np i\nc\np i\nc\np i\nc\n");
+        assert_eq!(paused_lines(&output), vec![1, 2, 2, 2]);
+        for i in 0..3 {
+            assert!(output.contains(&format!("(teci-db) {}\n", i)));
+        }
+        let output = debug_source(source, "n\nn\nn\n");
+        assert_eq!(paused_lines(&output), vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,231 @@
+use std::rc::Rc;
+
+use crate::token_type::TokenType;
+
+const CLASSIC: &[(&str, TokenType)] = &[
+    ("and", TokenType::And),
+    ("break", TokenType::Break),
+    ("class", TokenType::Class),
+    ("const", TokenType::Const),
+    ("else", TokenType::Else),
+    ("false", TokenType::False),
+    ("for", TokenType::For),
+    ("fun", TokenType::Fun),
+    ("if", TokenType::If),
+    ("in", TokenType::In),
+    ("let", TokenType::Let),
+    ("match", TokenType::Match),
+    ("nil", TokenType::Nil),
+    ("or", TokenType::Or),
+    ("print", TokenType::Print),
+    ("escuchadmebien", TokenType::Print),
+    ("return", TokenType::Return),
+    ("super", TokenType::Super),
+    ("this", TokenType::This),
+    ("true", TokenType::True),
+    ("while", TokenType::While),
+    ("yield", TokenType::Yield),
+];
+
+// Spanish aliases the TECI dialect adds on top of the classic keywords
+const TECI: &[(&str, &str)] = &[
+    ("and", "y"),
+    ("break", "romper"),
+    ("class", "clase"),
+    ("const", "constante"),
+    ("else", "sino"),
+    ("false", "falso"),
+    ("for", "para"),
+    ("fun", "funcion"),
+    ("if", "si"),
+    ("in", "en"),
+    ("let", "sea"),
+    ("match", "segun"),
+    ("nil", "nada"),
+    ("or", "o"),
+    ("return", "devolver"),
+    ("this", "esto"),
+    ("true", "verdadero"),
+    ("while", "mientras"),
+    ("yield", "ceder"),
+];
+
+// Selects the dialect of a file when it is its very first line
+pub const PRAGMA: &str = "#dialect";
+
+// The set of words the scanner reads as keywords
+#[derive(Debug, Clone)]
+pub struct Dialect {
+    name: Rc<str>,
+    keywords: Rc<[(String, TokenType)]>,
+}
+
+impl Dialect {
+    pub fn classic() -> Self {
+        Self::new("classic", Vec::new())
+    }
+
+    pub fn teci() -> Self {
+        let aliases = TECI
+            .iter()
+            .map(|(keyword, alias)| (alias.to_string(), Self::classic().keyword(keyword).unwrap()))
+            .collect();
+        Self::new("teci", aliases)
+    }
+
+    fn new(name: &str, aliases: Vec<(String, TokenType)>) -> Self {
+        let mut keywords: Vec<(String, TokenType)> = CLASSIC
+            .iter()
+            .map(|(keyword, ttype)| (keyword.to_string(), *ttype))
+            .collect();
+        keywords.extend(aliases);
+        Self {
+            name: name.into(),
+            keywords: keywords.into(),
+        }
+    }
+
+    // Reads a mapping of `keyword => alias` lines, like the ones in
+    // keywords.md, adding the aliases to the classic keywords. Lines without a
+    // `=>` are ignored, and so is a leading `-` so the file can be a list.
+    pub fn parse(name: &str, mapping: &str) -> Result<Self, String> {
+        let classic = Self::classic();
+        let mut aliases = Vec::new();
+        for (i, line) in mapping.lines().enumerate() {
+            let Some((keyword, alias)) = line.split_once("=>") else {
+                continue;
+            };
+            let keyword = keyword.trim().trim_start_matches('-').trim();
+            let alias = alias.trim();
+            let ttype = classic
+                .keyword(keyword)
+                .ok_or_else(|| format!("Unknown keyword '{}' in line {}", keyword, i + 1))?;
+            let mut chars = alias.chars();
+            let is_word = chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !is_word {
+                return Err(format!("Invalid alias '{}' in line {}", alias, i + 1));
+            }
+            aliases.push((alias.to_string(), ttype));
+        }
+
+        Ok(Self::new(name, aliases))
+    }
+
+    // A built-in dialect by name
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "classic" => Some(Self::classic()),
+            "teci" => Some(Self::teci()),
+            _ => None,
+        }
+    }
+
+    // A built-in dialect by name, or else a mapping file at that path
+    pub fn named(name: &str) -> Result<Self, String> {
+        if let Some(dialect) = Self::builtin(name) {
+            return Ok(dialect);
+        }
+        match std::fs::read_to_string(name) {
+            Ok(mapping) => Self::parse(name, &mapping),
+            Err(e) => Err(format!("Unknown dialect '{}' ({})", name, e)),
+        }
+    }
+
+    // The dialect `source` asks for in its pragma, if any. Mapping files are
+    // only read with `files`, so that sandboxed scripts can't read them.
+    pub fn of_source(source: &str, files: bool) -> Option<Result<Self, String>> {
+        let name = source.lines().next()?.strip_prefix(PRAGMA)?;
+        if !name.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let name = name.trim();
+        if files {
+            Some(Self::named(name))
+        } else {
+            Some(Self::builtin(name).ok_or_else(|| {
+                format!(
+                    "Unknown dialect '{}' (mapping files need the 'fs' capability)",
+                    name
+                )
+            }))
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn keyword(&self, word: &str) -> Option<TokenType> {
+        self.keywords
+            .iter()
+            .find(|(keyword, _)| keyword == word)
+            .map(|(_, ttype)| *ttype)
+    }
+
+    pub fn keywords(&self) -> impl Iterator<Item = &str> {
+        self.keywords.iter().map(|(keyword, _)| keyword.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_builtin_dialects() {
+        let classic = Dialect::classic();
+        assert_eq!(classic.keyword("print"), Some(TokenType::Print));
+        assert_eq!(classic.keyword("escuchadmebien"), Some(TokenType::Print));
+        assert_eq!(classic.keyword("mientras"), None);
+
+        let teci = Dialect::teci();
+        assert_eq!(teci.keyword("mientras"), Some(TokenType::While));
+        assert_eq!(teci.keyword("while"), Some(TokenType::While));
+        assert_eq!(teci.keyword("funcion"), Some(TokenType::Fun));
+    }
+
+    #[test]
+    fn t_mapping_file() {
+        let dialect = Dialect::parse("mine", "# Mine\n\n- fun => def\nwhile => loop\n").unwrap();
+        assert_eq!(dialect.name(), "mine");
+        assert_eq!(dialect.keyword("def"), Some(TokenType::Fun));
+        assert_eq!(dialect.keyword("loop"), Some(TokenType::While));
+        assert_eq!(dialect.keyword("fun"), Some(TokenType::Fun));
+
+        assert_eq!(
+            Dialect::parse("bad", "list => vectorcillo").unwrap_err(),
+            "Unknown keyword 'list' in line 1"
+        );
+        assert_eq!(
+            Dialect::parse("bad", "fun => función").unwrap_err(),
+            "Invalid alias 'función' in line 1"
+        );
+    }
+
+    #[test]
+    fn t_pragma() {
+        let dialect = Dialect::of_source("#dialect teci\nmientras (verdadero) romper;", false);
+        assert_eq!(dialect.unwrap().unwrap().name(), "teci");
+        assert!(Dialect::of_source("print 1;\n#dialect teci", true).is_none());
+        assert!(Dialect::of_source("#dialectteci", true).is_none());
+        assert!(
+            Dialect::of_source("#dialect nope.md", true)
+                .unwrap()
+                .is_err()
+        );
+        assert!(
+            Dialect::of_source("#dialect keywords.md", true)
+                .unwrap()
+                .is_ok()
+        );
+        assert_eq!(
+            Dialect::of_source("#dialect keywords.md", false)
+                .unwrap()
+                .unwrap_err(),
+            "Unknown dialect 'keywords.md' (mapping files need the 'fs' capability)"
+        );
+    }
+}
@@ -4,11 +4,13 @@ use std::{
     rc::Rc,
 };
 
-use crate::{error::TeciResult, object::Object, token::Token};
+use crate::{error::TeciResult, gc::Traced, object::Object, symbol::Symbol, token::Token};
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Environment {
-    values: HashMap<String, Object>,
+    values: HashMap<Symbol, Object>,
+    // The names that cannot be reassigned, with what they are for errors
+    immutable: HashMap<Symbol, &'static str>,
     enclosing: Option<Rc<RefCell<Environment>>>,
 }
 
@@ -16,6 +18,7 @@ impl Environment {
     pub fn new() -> Self {
         Self {
             values: HashMap::new(),
+            immutable: HashMap::new(),
             enclosing: None,
         }
     }
@@ -23,12 +26,58 @@ impl Environment {
     pub fn with_enclosing(environment: Rc<RefCell<Environment>>) -> Self {
         Self {
             values: HashMap::new(),
+            immutable: HashMap::new(),
             enclosing: Some(environment),
         }
     }
 
-    pub fn define(&mut self, name: &str, value: Object) {
-        self.values.insert(name.to_string(), value);
+    pub fn define(&mut self, name: impl Into<Symbol>, value: Object) {
+        let name = name.into();
+        self.immutable.remove(&name);
+        self.values.insert(name, value);
+    }
+
+    // Defines a name that `assign` refuses to change, a "constant" or a
+    // "function"
+    pub fn define_immutable(&mut self, name: impl Into<Symbol>, value: Object, kind: &'static str) {
+        let name = name.into();
+        self.values.insert(name.clone(), value);
+        self.immutable.insert(name, kind);
+    }
+
+    // Fails if `name` is a constant of this very scope, which cannot be
+    // declared again
+    pub fn check_redeclare(&self, name: &Token) -> Result<(), TeciResult> {
+        match self.immutable.get(&name.lexeme) {
+            Some(&"constant") => Err(TeciResult::runtime_error(
+                name.clone(),
+                &format!("Cannot redeclare constant '{}'", name.lexeme),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn enclosing(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.enclosing.clone()
+    }
+
+    pub fn trace(&self, visit: &mut dyn FnMut(Traced)) {
+        if let Some(enclosing) = &self.enclosing {
+            visit(Traced::Environment(enclosing));
+        }
+        self.values
+            .values()
+            .for_each(|value| visit(Traced::Value(value)));
+    }
+
+    pub fn bindings(&self) -> Vec<(String, Object)> {
+        let mut bindings: Vec<(String, Object)> = self
+            .values
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        bindings
     }
 
     pub fn get(&self, name: &Token) -> Result<Object, TeciResult> {
@@ -45,7 +94,12 @@ impl Environment {
     }
 
     pub fn assign(&mut self, name: &Token, value: Object) -> Result<(), TeciResult> {
-        if let Entry::Occupied(mut object) = self.values.entry(name.lexeme.clone()) {
+        if let Some(kind) = self.immutable.get(&name.lexeme) {
+            Err(TeciResult::runtime_error(
+                name.clone(),
+                &format!("Cannot assign to {} '{}'", kind, name.lexeme),
+            ))
+        } else if let Entry::Occupied(mut object) = self.values.entry(name.lexeme.clone()) {
             object.insert(value);
             Ok(())
         } else if let Some(enc) = &self.enclosing {
@@ -83,10 +137,10 @@ mod tests {
     #[test]
     fn t_lookup_variable() {
         let mut e = Environment::new();
-        e.define("a", Object::Str("foo".to_string()));
-        let tok = Token::new(TokenType::Identifier, "a".to_string(), None, 0);
-        let tok_err = Token::new(TokenType::Identifier, "b".to_string(), None, 0);
-        assert_eq!(e.get(&tok).unwrap(), Object::Str("foo".to_string()));
+        e.define("a", Object::Str("foo".into()));
+        let tok = Token::new(TokenType::Identifier, "a", None, 0);
+        let tok_err = Token::new(TokenType::Identifier, "b", None, 0);
+        assert_eq!(e.get(&tok).unwrap(), Object::Str("foo".into()));
         assert!(e.get(&tok_err).is_err())
     }
 
@@ -102,7 +156,7 @@ mod tests {
         let e = Rc::new(RefCell::new(Environment::new()));
         e.borrow_mut().define("a", Object::Num(1.0));
         let f = Environment::with_enclosing(Rc::clone(&e));
-        let a_token = Token::new(TokenType::Identifier, "a".to_string(), None, 0);
+        let a_token = Token::new(TokenType::Identifier, "a", None, 0);
         assert_eq!(f.get(&a_token).unwrap(), Object::Num(1.0))
     }
 
@@ -111,8 +165,34 @@ mod tests {
         let e = Rc::new(RefCell::new(Environment::new()));
         e.borrow_mut().define("a", Object::Num(1.0));
         let mut f = Environment::with_enclosing(Rc::clone(&e));
-        let a_token = Token::new(TokenType::Identifier, "a".to_string(), None, 0);
+        let a_token = Token::new(TokenType::Identifier, "a", None, 0);
         assert!(f.assign(&a_token, Object::Num(2.0)).is_ok());
         assert_eq!(f.get(&a_token).unwrap(), Object::Num(2.0))
     }
+
+    #[test]
+    fn t_assign_to_immutable() {
+        let e = Rc::new(RefCell::new(Environment::new()));
+        e.borrow_mut()
+            .define_immutable("a", Object::Int(1), "constant");
+        let mut f = Environment::with_enclosing(Rc::clone(&e));
+        let a_token = Token::new(TokenType::Identifier, "a", None, 0);
+        match f.assign(&a_token, Object::Int(2)) {
+            Err(TeciResult::RuntimeError { message, .. }) => {
+                assert_eq!(message, "Cannot assign to constant 'a'")
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+        // Shadowing makes a new, mutable, binding
+        assert!(f.check_redeclare(&a_token).is_ok());
+        f.define("a", Object::Int(3));
+        assert!(f.assign(&a_token, Object::Int(4)).is_ok());
+        // But the constant can't be declared again in its own scope
+        match e.borrow().check_redeclare(&a_token) {
+            Err(TeciResult::RuntimeError { message, .. }) => {
+                assert_eq!(message, "Cannot redeclare constant 'a'")
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
 }
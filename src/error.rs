@@ -1,16 +1,60 @@
-use crate::{object::Object, token::Token, token_type::TokenType};
+use crate::{
+    call_stack::CallFrame, callable::Callable, object::Object, sandbox::Limit, token::Token,
+    token_type::TokenType,
+};
 
 #[derive(Debug)]
 pub enum TeciResult {
     // Errors
-    ParseError { token: Token, message: String },
-    RuntimeError { token: Token, message: String },
-    TeciError { line: usize, message: String },
-    SystemError { message: String },
+    ParseError {
+        token: Token,
+        message: String,
+    },
+    ResolveError {
+        token: Token,
+        message: String,
+    },
+    TypeError {
+        token: Token,
+        message: String,
+    },
+    RuntimeError {
+        token: Token,
+        message: String,
+    },
+    TeciError {
+        line: usize,
+        message: String,
+    },
+    SystemError {
+        message: String,
+    },
+    // A script went over a limit of the sandbox it runs in
+    LimitExceeded {
+        line: usize,
+        limit: Limit,
+    },
+    // A runtime error that escaped the interpreter, with the calls that led to it
+    Backtrace {
+        error: Box<TeciResult>,
+        frames: Vec<CallFrame>,
+    },
 
     // Statement tricks
     Break,
-    Return { _value: Object },
+    Terminate,
+    Return {
+        _value: Object,
+    },
+    // A call in tail position, handed back for the trampoline in
+    // `TeciFunction::call` to make in place of the returning call.
+    // `discard` is set when the call was a statement, not a `return`.
+    TailCall {
+        function: Callable,
+        call_site: Token,
+        arguments: Vec<Object>,
+        discard: bool,
+    },
 }
 
 impl TeciResult {
@@ -23,6 +67,24 @@ impl TeciResult {
         error
     }
 
+    pub fn resolve_error(token: Token, message: &str) -> TeciResult {
+        let error = TeciResult::ResolveError {
+            token,
+            message: message.to_string(),
+        };
+        error.report("");
+        error
+    }
+
+    pub fn type_error(token: Token, message: &str) -> TeciResult {
+        let error = TeciResult::TypeError {
+            token,
+            message: message.to_string(),
+        };
+        error.report("");
+        error
+    }
+
     pub fn runtime_error(token: Token, message: &str) -> TeciResult {
         let error = TeciResult::RuntimeError {
             token,
@@ -49,6 +111,34 @@ impl TeciResult {
         error
     }
 
+    pub fn limit_exceeded(line: usize, limit: Limit) -> TeciResult {
+        let error = TeciResult::LimitExceeded { line, limit };
+        error.report("");
+        error
+    }
+
+    pub fn backtrace(error: TeciResult, frames: Vec<CallFrame>) -> TeciResult {
+        let error = TeciResult::Backtrace {
+            error: Box::new(error),
+            frames,
+        };
+        error.report("");
+        error
+    }
+
+    fn line(&self) -> Option<usize> {
+        match self {
+            TeciResult::ParseError { token, .. }
+            | TeciResult::ResolveError { token, .. }
+            | TeciResult::TypeError { token, .. }
+            | TeciResult::RuntimeError { token, .. } => Some(token.line),
+            TeciResult::TeciError { line, .. } | TeciResult::LimitExceeded { line, .. } => {
+                Some(*line)
+            }
+            _ => None,
+        }
+    }
+
     pub fn report(&self, loc: &str) {
         match self {
             TeciResult::ParseError { token, message } => {
@@ -61,6 +151,18 @@ impl TeciResult {
                     token.line, token_display, message
                 )
             }
+            TeciResult::ResolveError { token, message } => {
+                eprintln!(
+                    "[Resolve Error] In line {} at '{}': {}",
+                    token.line, token.lexeme, message
+                )
+            }
+            TeciResult::TypeError { token, message } => {
+                eprintln!(
+                    "[Type Error] In line {} at '{}': {}",
+                    token.line, token.lexeme, message
+                )
+            }
             TeciResult::RuntimeError { token, message } => {
                 let token_display = match &token.ttype {
                     TokenType::Eof => "EOF",
@@ -77,8 +179,46 @@ impl TeciResult {
             TeciResult::SystemError { message } => {
                 eprintln!("[System Error] {}", message)
             }
+            TeciResult::LimitExceeded { line, limit } => {
+                eprintln!("[Limit Exceeded] In line {}: {}", line, limit)
+            }
+            TeciResult::Backtrace { error, frames } => {
+                // The error itself was reported when it was raised, so only the
+                // chain of calls is printed here, most recent call first
+                eprintln!("Stack trace (most recent call first):");
+                let mut line = error.line();
+                let mut frames = frames.iter().rev().peekable();
+                while let Some(frame) = frames.next() {
+                    match line {
+                        Some(line) => eprintln!("    in {} at line {}", frame.signature(), line),
+                        None => eprintln!("    in {}", frame.signature()),
+                    }
+                    line = Some(frame.call_site.line);
+
+                    // Deep recursion repeats the same frame, so collapse the run
+                    let mut repeated = 0;
+                    while let Some(next) = frames.peek() {
+                        if next.callee != frame.callee
+                            || next.call_site.line != frame.call_site.line
+                        {
+                            break;
+                        }
+                        repeated += 1;
+                        frames.next();
+                    }
+                    if repeated > 0 {
+                        eprintln!("    ... {} more calls to {}", repeated, frame.callee);
+                    }
+                }
+                match line {
+                    Some(line) => eprintln!("    in <script> at line {}", line),
+                    None => eprintln!("    in <script>"),
+                }
+            }
             TeciResult::Break => {}
+            TeciResult::Terminate => {}
             TeciResult::Return { _value } => {}
+            TeciResult::TailCall { .. } => {}
         }
     }
 }
@@ -2,16 +2,23 @@
 use crate::token::*;
 use crate::object::*;
 use crate::error::*;
+use crate::pattern::*;
 
 #[derive(Clone)]
 pub enum Expr {
     Assign(AssignExpr),
     Binary(BinaryExpr),
     Call(CallExpr),
+    Conditional(ConditionalExpr),
+    Get(GetExpr),
     Grouping(GroupingExpr),
     Logical(LogicalExpr),
     Literal(LiteralExpr),
+    Match(MatchExpr),
     Unary(UnaryExpr),
+    Tuple(TupleExpr),
+    TupleAssign(TupleAssignExpr),
+    Update(UpdateExpr),
     Variable(VariableExpr),
 }
 
@@ -21,10 +28,16 @@ impl Expr {
             Expr::Assign(exp) => exp.accept(visitor),
             Expr::Binary(exp) => exp.accept(visitor),
             Expr::Call(exp) => exp.accept(visitor),
+            Expr::Conditional(exp) => exp.accept(visitor),
+            Expr::Get(exp) => exp.accept(visitor),
             Expr::Grouping(exp) => exp.accept(visitor),
             Expr::Logical(exp) => exp.accept(visitor),
             Expr::Literal(exp) => exp.accept(visitor),
+            Expr::Match(exp) => exp.accept(visitor),
             Expr::Unary(exp) => exp.accept(visitor),
+            Expr::Tuple(exp) => exp.accept(visitor),
+            Expr::TupleAssign(exp) => exp.accept(visitor),
+            Expr::Update(exp) => exp.accept(visitor),
             Expr::Variable(exp) => exp.accept(visitor),
         }
     }
@@ -50,8 +63,22 @@ pub struct CallExpr {
     pub arguments: Vec<Expr>,
 }
 
+#[derive(Clone)]
+pub struct ConditionalExpr {
+    pub condition: Box<Expr>,
+    pub then_branch: Box<Expr>,
+    pub else_branch: Box<Expr>,
+}
+
+#[derive(Clone)]
+pub struct GetExpr {
+    pub object: Box<Expr>,
+    pub name: Token,
+}
+
 #[derive(Clone)]
 pub struct GroupingExpr {
+    pub paren: Token,
     pub expression: Box<Expr>,
 }
 
@@ -64,15 +91,44 @@ pub struct LogicalExpr {
 
 #[derive(Clone)]
 pub struct LiteralExpr {
+    pub token: Token,
     pub value: Option<Object>,
 }
 
+#[derive(Clone)]
+pub struct MatchExpr {
+    pub keyword: Token,
+    pub value: Box<Expr>,
+    pub arms: Vec<MatchArm>,
+    pub brace: Token,
+}
+
 #[derive(Clone)]
 pub struct UnaryExpr {
     pub operator: Token,
     pub right: Box<Expr>,
 }
 
+#[derive(Clone)]
+pub struct TupleExpr {
+    pub paren: Token,
+    pub elements: Vec<Expr>,
+}
+
+#[derive(Clone)]
+pub struct TupleAssignExpr {
+    pub paren: Token,
+    pub target: Pattern,
+    pub value: Box<Expr>,
+}
+
+#[derive(Clone)]
+pub struct UpdateExpr {
+    pub name: Token,
+    pub operator: Token,
+    pub prefix: bool,
+}
+
 #[derive(Clone)]
 pub struct VariableExpr {
     pub name: Token,
@@ -82,10 +138,16 @@ pub trait ExprVisitor<T> {
     fn visit_assign_expr(&self, expr: &AssignExpr) -> Result<T, TeciResult>;
     fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<T, TeciResult>;
     fn visit_call_expr(&self, expr: &CallExpr) -> Result<T, TeciResult>;
+    fn visit_conditional_expr(&self, expr: &ConditionalExpr) -> Result<T, TeciResult>;
+    fn visit_get_expr(&self, expr: &GetExpr) -> Result<T, TeciResult>;
     fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<T, TeciResult>;
     fn visit_logical_expr(&self, expr: &LogicalExpr) -> Result<T, TeciResult>;
     fn visit_literal_expr(&self, expr: &LiteralExpr) -> Result<T, TeciResult>;
+    fn visit_match_expr(&self, expr: &MatchExpr) -> Result<T, TeciResult>;
     fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<T, TeciResult>;
+    fn visit_tuple_expr(&self, expr: &TupleExpr) -> Result<T, TeciResult>;
+    fn visit_tupleassign_expr(&self, expr: &TupleAssignExpr) -> Result<T, TeciResult>;
+    fn visit_update_expr(&self, expr: &UpdateExpr) -> Result<T, TeciResult>;
     fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<T, TeciResult>;
 }
 
@@ -107,6 +169,18 @@ impl CallExpr {
     }
 }
 
+impl ConditionalExpr {
+    pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, TeciResult> {
+        visitor.visit_conditional_expr(self)
+    }
+}
+
+impl GetExpr {
+    pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, TeciResult> {
+        visitor.visit_get_expr(self)
+    }
+}
+
 impl GroupingExpr {
     pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, TeciResult> {
         visitor.visit_grouping_expr(self)
@@ -125,12 +199,36 @@ impl LiteralExpr {
     }
 }
 
+impl MatchExpr {
+    pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, TeciResult> {
+        visitor.visit_match_expr(self)
+    }
+}
+
 impl UnaryExpr {
     pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, TeciResult> {
         visitor.visit_unary_expr(self)
     }
 }
 
+impl TupleExpr {
+    pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, TeciResult> {
+        visitor.visit_tuple_expr(self)
+    }
+}
+
+impl TupleAssignExpr {
+    pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, TeciResult> {
+        visitor.visit_tupleassign_expr(self)
+    }
+}
+
+impl UpdateExpr {
+    pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, TeciResult> {
+        visitor.visit_update_expr(self)
+    }
+}
+
 impl VariableExpr {
     pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, TeciResult> {
         visitor.visit_variable_expr(self)
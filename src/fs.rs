@@ -0,0 +1,328 @@
+use std::{
+    cell::RefCell,
+    fs::{self, File, OpenOptions, ReadDir},
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+    rc::Rc,
+};
+
+use crate::{
+    callable::{Callable, TeciCallable},
+    error::TeciResult,
+    interpreter::Interpreter,
+    native_object::{NativeObject, TeciObject},
+    object::Object,
+    sandbox::Capability,
+};
+
+// Reads a line without its line break, or None at the end of `reader`
+pub fn read_line(reader: &mut dyn BufRead) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(Some(line))
+}
+
+// The `fs` module, for reading and writing files. Failures are runtime
+// errors with the message of the OS.
+pub struct FsModule;
+
+impl TeciObject for FsModule {
+    fn type_name(&self) -> &'static str {
+        "module"
+    }
+
+    fn to_string(&self) -> String {
+        "<module fs>".to_string()
+    }
+
+    fn get(&self, name: &str) -> Option<Object> {
+        let fs_function = match name {
+            "read_file" => FsFunction::ReadFile,
+            "write_file" => FsFunction::WriteFile,
+            "append_file" => FsFunction::AppendFile,
+            "exists" => FsFunction::Exists,
+            "remove" => FsFunction::Remove,
+            "open" => FsFunction::Open,
+            "dir" => FsFunction::Dir,
+            _ => return None,
+        };
+        Some(Object::Func(Callable {
+            func: Rc::new(fs_function),
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FsFunction {
+    // `fs.read_file(path)`, the whole file as a string
+    ReadFile,
+    // `fs.write_file(path, text)`, replacing what the file had
+    WriteFile,
+    // `fs.append_file(path, text)`, creating the file if needed
+    AppendFile,
+    // `fs.exists(path)`
+    Exists,
+    // `fs.remove(path)`, a file or an empty directory
+    Remove,
+    // `fs.open(path)`, a file handle to read line by line
+    Open,
+    // `fs.dir(path)`, a directory handle to iterate over the entries
+    Dir,
+}
+
+impl FsFunction {
+    fn short_name(self) -> &'static str {
+        match self {
+            FsFunction::ReadFile => "read_file",
+            FsFunction::WriteFile => "write_file",
+            FsFunction::AppendFile => "append_file",
+            FsFunction::Exists => "exists",
+            FsFunction::Remove => "remove",
+            FsFunction::Open => "open",
+            FsFunction::Dir => "dir",
+        }
+    }
+
+    fn run(self, path: &str, args: &[Object]) -> io::Result<Object> {
+        match self {
+            FsFunction::ReadFile => fs::read_to_string(path).map(|text| Object::Str(text.into())),
+            FsFunction::WriteFile => {
+                fs::write(path, Interpreter::stringify(args[1].clone())).map(|_| Object::Nil)
+            }
+            FsFunction::AppendFile => OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(path)?
+                .write_all(Interpreter::stringify(args[1].clone()).as_bytes())
+                .map(|_| Object::Nil),
+            FsFunction::Exists => Path::new(path).try_exists().map(Object::Bool),
+            FsFunction::Remove if Path::new(path).is_dir() => {
+                fs::remove_dir(path).map(|_| Object::Nil)
+            }
+            FsFunction::Remove => fs::remove_file(path).map(|_| Object::Nil),
+            FsFunction::Open => File::open(path).map(|file| {
+                Object::Native(NativeObject::new(FileHandle {
+                    path: path.to_string(),
+                    reader: Rc::new(RefCell::new(Some(BufReader::new(file)))),
+                }))
+            }),
+            FsFunction::Dir => fs::read_dir(path).map(|entries| {
+                Object::Native(NativeObject::new(DirHandle {
+                    path: path.to_string(),
+                    entries: Rc::new(RefCell::new(entries)),
+                }))
+            }),
+        }
+    }
+}
+
+impl TeciCallable for FsFunction {
+    fn arity(&self) -> usize {
+        match self {
+            FsFunction::WriteFile | FsFunction::AppendFile => 2,
+            _ => 1,
+        }
+    }
+
+    fn call(&self, interpreter: &Interpreter, args: Vec<Object>) -> Result<Object, TeciResult> {
+        let Object::Str(path) = &args[0] else {
+            return Err(interpreter.call_error(&format!(
+                "{} expects a string path, found a {}",
+                self.short_name(),
+                args[0].type_name()
+            )));
+        };
+        self.run(path, &args).map_err(|error| {
+            interpreter.call_error(&format!(
+                "{} failed on '{}': {}",
+                self.short_name(),
+                path,
+                error
+            ))
+        })
+    }
+
+    fn name(&self) -> String {
+        format!("fs.{}", self.short_name())
+    }
+
+    fn to_string(&self) -> String {
+        format!("<fun native::fs.{}>", self.short_name())
+    }
+
+    fn capability(&self) -> Option<Capability> {
+        Some(Capability::FileSystem)
+    }
+}
+
+// A file opened with `fs.open`. It gives its lines with `read_line()`, or
+// when iterated over, until `close()` is called.
+struct FileHandle {
+    path: String,
+    // None once closed
+    reader: Rc<RefCell<Option<BufReader<File>>>>,
+}
+
+impl FileHandle {
+    fn method(&self, method: FileMethod) -> Callable {
+        Callable {
+            func: Rc::new(FileHandleMethod {
+                path: self.path.clone(),
+                reader: Rc::clone(&self.reader),
+                method,
+            }),
+        }
+    }
+}
+
+impl TeciObject for FileHandle {
+    fn type_name(&self) -> &'static str {
+        "file"
+    }
+
+    fn to_string(&self) -> String {
+        format!("<file {}>", self.path)
+    }
+
+    fn get(&self, name: &str) -> Option<Object> {
+        match name {
+            "read_line" => Some(Object::Func(self.method(FileMethod::ReadLine))),
+            "close" => Some(Object::Func(self.method(FileMethod::Close))),
+            _ => None,
+        }
+    }
+
+    fn iterator(&self) -> Option<Callable> {
+        Some(self.method(FileMethod::ReadLine))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FileMethod {
+    // The next line, without the line break, or nil at the end of the file
+    ReadLine,
+    Close,
+}
+
+struct FileHandleMethod {
+    path: String,
+    reader: Rc<RefCell<Option<BufReader<File>>>>,
+    method: FileMethod,
+}
+
+impl TeciCallable for FileHandleMethod {
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn call(&self, interpreter: &Interpreter, _args: Vec<Object>) -> Result<Object, TeciResult> {
+        let mut reader = self.reader.borrow_mut();
+        match self.method {
+            FileMethod::ReadLine => {
+                let Some(reader) = reader.as_mut() else {
+                    return Err(interpreter.call_error(&format!("'{}' is closed", self.path)));
+                };
+                match read_line(reader) {
+                    Ok(Some(line)) => Ok(Object::Str(line.into())),
+                    Ok(None) => Ok(Object::Nil),
+                    Err(error) => Err(interpreter
+                        .call_error(&format!("read_line failed on '{}': {}", self.path, error))),
+                }
+            }
+            FileMethod::Close => {
+                reader.take();
+                Ok(Object::Nil)
+            }
+        }
+    }
+
+    fn name(&self) -> String {
+        match self.method {
+            FileMethod::ReadLine => "read_line".to_string(),
+            FileMethod::Close => "close".to_string(),
+        }
+    }
+
+    fn to_string(&self) -> String {
+        format!("<fun native::file.{}>", self.name())
+    }
+}
+
+// A directory opened with `fs.dir`. It gives the names of its entries, in
+// no particular order, with `next()` or when iterated over.
+struct DirHandle {
+    path: String,
+    entries: Rc<RefCell<ReadDir>>,
+}
+
+impl DirHandle {
+    fn next(&self) -> Callable {
+        Callable {
+            func: Rc::new(DirNext {
+                path: self.path.clone(),
+                entries: Rc::clone(&self.entries),
+            }),
+        }
+    }
+}
+
+impl TeciObject for DirHandle {
+    fn type_name(&self) -> &'static str {
+        "directory"
+    }
+
+    fn to_string(&self) -> String {
+        format!("<directory {}>", self.path)
+    }
+
+    fn get(&self, name: &str) -> Option<Object> {
+        match name {
+            "next" => Some(Object::Func(self.next())),
+            _ => None,
+        }
+    }
+
+    fn iterator(&self) -> Option<Callable> {
+        Some(self.next())
+    }
+}
+
+// The name of the next entry of a directory, or nil after the last one
+struct DirNext {
+    path: String,
+    entries: Rc<RefCell<ReadDir>>,
+}
+
+impl TeciCallable for DirNext {
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn call(&self, interpreter: &Interpreter, _args: Vec<Object>) -> Result<Object, TeciResult> {
+        match self.entries.borrow_mut().next() {
+            Some(Ok(entry)) => Ok(Object::Str(
+                entry.file_name().to_string_lossy().as_ref().into(),
+            )),
+            Some(Err(error)) => {
+                Err(interpreter.call_error(&format!("next failed on '{}': {}", self.path, error)))
+            }
+            None => Ok(Object::Nil),
+        }
+    }
+
+    fn name(&self) -> String {
+        "next".to_string()
+    }
+
+    fn to_string(&self) -> String {
+        "<fun native::directory.next>".to_string()
+    }
+}
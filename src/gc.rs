@@ -0,0 +1,260 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet, hash_map::Entry},
+    rc::{Rc, Weak},
+};
+
+use crate::{
+    callable::{Callable, TeciCallable},
+    envirnoment::Environment,
+    object::Object,
+};
+
+// Below this many tracked environments collections are not worth it
+const MIN_THRESHOLD: usize = 1000;
+
+// A strong reference held by an environment or a function, for the collector
+// to follow
+pub enum Traced<'a> {
+    Environment(&'a Rc<RefCell<Environment>>),
+    Value(&'a Object),
+    Callable(&'a Callable),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct HeapStats {
+    pub collections: usize,
+    // Environments freed by breaking their cycles
+    pub freed: usize,
+    // Environments still tracked after the last collection
+    pub tracked: usize,
+}
+
+// Values are reference counted, which frees everything but cycles. Every cycle
+// goes through an environment a function closes over (a closure holding a
+// function that holds the closure back), so those are the environments the
+// heap tracks. Collecting counts the references the tracked part of the heap
+// holds to itself, like CPython does: whatever has more references than that
+// is also referenced from outside, by the interpreter, and is alive, and so is
+// everything it reaches. The environments left are only kept alive by cycles,
+// and clearing them frees the lot.
+pub struct Heap {
+    tracked: RefCell<HashMap<usize, Weak<RefCell<Environment>>>>,
+    // Collect once this many environments are tracked
+    threshold: Cell<usize>,
+    stats: Cell<HeapStats>,
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Self {
+            tracked: RefCell::new(HashMap::new()),
+            threshold: Cell::new(MIN_THRESHOLD),
+            stats: Cell::new(HeapStats::default()),
+        }
+    }
+
+    // Tracks an environment that is the closure of a function, collecting
+    // when enough of them have piled up
+    pub fn track(&self, environment: &Rc<RefCell<Environment>>) {
+        let len = {
+            let mut tracked = self.tracked.borrow_mut();
+            tracked.insert(address(environment), Rc::downgrade(environment));
+            tracked.len()
+        };
+        if len >= self.threshold.get() {
+            self.collect();
+        }
+    }
+
+    // Frees the environments only kept alive by cycles, returning how many
+    pub fn collect(&self) -> usize {
+        let roots: Vec<_> = self.tracked.borrow().values().cloned().collect();
+        let mut graph = Graph::default();
+        for root in roots {
+            if let Some(environment) = root.upgrade() {
+                // Not counting the reference just upgraded
+                graph.environment(&environment, 1);
+                graph.trace();
+            }
+        }
+
+        let garbage = graph.garbage();
+        for environment in &garbage {
+            if let Some(environment) = environment.upgrade() {
+                // Dropped once the borrow is over, as dropping the values may
+                // free other environments
+                let contents = match environment.try_borrow_mut() {
+                    Ok(mut environment) => std::mem::replace(&mut *environment, Environment::new()),
+                    Err(_) => continue,
+                };
+                drop(contents);
+            }
+        }
+
+        let mut tracked = self.tracked.borrow_mut();
+        tracked.retain(|_, environment| environment.strong_count() > 0);
+        self.threshold.set(MIN_THRESHOLD.max(2 * tracked.len()));
+        let stats = self.stats.get();
+        self.stats.set(HeapStats {
+            collections: stats.collections + 1,
+            freed: stats.freed + garbage.len(),
+            tracked: tracked.len(),
+        });
+        garbage.len()
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        self.stats.get()
+    }
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Something on the heap holding references to other things on it
+enum Container {
+    Environment(Rc<RefCell<Environment>>),
+    Function(Rc<dyn TeciCallable>),
+    Tuple(Rc<[Object]>),
+}
+
+struct Node {
+    // References to it from anywhere, minus the ones from other nodes once
+    // the graph is complete
+    references: usize,
+    edges: Vec<usize>,
+    environment: Option<Weak<RefCell<Environment>>>,
+}
+
+// The part of the heap reachable from the tracked environments, keyed by
+// address
+#[derive(Default)]
+struct Graph {
+    nodes: HashMap<usize, Node>,
+    // Containers found but not traced yet. The references held here are not
+    // counted, as each node counts its references when it is found.
+    pending: Vec<(usize, Container)>,
+}
+
+impl Graph {
+    // Adds `environment`, not counting the `held` references to it from the
+    // collector itself
+    fn environment(&mut self, environment: &Rc<RefCell<Environment>>, held: usize) -> usize {
+        self.container(
+            address(environment),
+            Rc::strong_count(environment) - held,
+            || Container::Environment(Rc::clone(environment)),
+        )
+    }
+
+    // Adds the container `value` is, if any
+    fn value(&mut self, value: &Object) -> Option<usize> {
+        match value {
+            Object::Func(callable) => Some(self.callable(callable)),
+            Object::Tuple(elements) => Some(self.container(
+                address(elements),
+                Rc::strong_count(elements),
+                || Container::Tuple(Rc::clone(elements)),
+            )),
+            _ => None,
+        }
+    }
+
+    fn callable(&mut self, callable: &Callable) -> usize {
+        self.container(
+            address(&callable.func),
+            Rc::strong_count(&callable.func),
+            || Container::Function(Rc::clone(&callable.func)),
+        )
+    }
+
+    fn container(
+        &mut self,
+        id: usize,
+        references: usize,
+        container: impl FnOnce() -> Container,
+    ) -> usize {
+        if let Entry::Vacant(entry) = self.nodes.entry(id) {
+            let container = container();
+            let environment = match &container {
+                Container::Environment(environment) => Some(Rc::downgrade(environment)),
+                _ => None,
+            };
+            entry.insert(Node {
+                references,
+                edges: Vec::new(),
+                environment,
+            });
+            self.pending.push((id, container));
+        }
+        id
+    }
+
+    // Adds everything reachable from the pending containers. The ones in use
+    // cannot be looked into, which only makes what they reference look alive.
+    fn trace(&mut self) {
+        while let Some((id, container)) = self.pending.pop() {
+            let mut edges = Vec::new();
+            let mut visit = |traced: Traced| {
+                let edge = match traced {
+                    Traced::Environment(environment) => Some(self.environment(environment, 0)),
+                    Traced::Value(value) => self.value(value),
+                    Traced::Callable(callable) => Some(self.callable(callable)),
+                };
+                edges.extend(edge);
+            };
+            match &container {
+                Container::Environment(environment) => {
+                    if let Ok(environment) = environment.try_borrow() {
+                        environment.trace(&mut visit);
+                    }
+                }
+                Container::Function(function) => function.trace(&mut visit),
+                Container::Tuple(elements) => elements.iter().for_each(|e| visit(Traced::Value(e))),
+            }
+            if let Some(node) = self.nodes.get_mut(&id) {
+                node.edges = edges;
+            }
+        }
+    }
+
+    // The environments only referenced from inside the graph, and not
+    // reachable from anything referenced from outside
+    fn garbage(mut self) -> Vec<Weak<RefCell<Environment>>> {
+        let edges: Vec<usize> = self.nodes.values().flat_map(|n| n.edges.clone()).collect();
+        for edge in edges {
+            if let Some(node) = self.nodes.get_mut(&edge) {
+                node.references = node.references.saturating_sub(1);
+            }
+        }
+
+        let mut alive: Vec<usize> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.references > 0)
+            .map(|(id, _)| *id)
+            .collect();
+        let mut reached: HashSet<usize> = alive.iter().copied().collect();
+        while let Some(id) = alive.pop() {
+            for edge in &self.nodes[&id].edges {
+                if reached.insert(*edge) {
+                    alive.push(*edge);
+                }
+            }
+        }
+
+        self.nodes
+            .into_iter()
+            .filter(|(id, _)| !reached.contains(id))
+            .filter_map(|(_, node)| node.environment)
+            .collect()
+    }
+}
+
+fn address<T: ?Sized>(rc: &Rc<T>) -> usize {
+    Rc::as_ptr(rc) as *const () as usize
+}
@@ -0,0 +1,264 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    callable::{Callable, TeciCallable},
+    envirnoment::Environment,
+    error::TeciResult,
+    expr::Expr,
+    gc::Traced,
+    interpreter::Interpreter,
+    object::Object,
+    stmt::Stmt,
+    token::Token,
+};
+
+// Whether running `statement` may yield. Functions declared inside it are
+// generators of their own, so their bodies do not count.
+pub fn contains_yield(statement: &Stmt) -> bool {
+    match statement {
+        Stmt::Yield(_) => true,
+        Stmt::Block(s) => s.statements.iter().any(contains_yield),
+        Stmt::If(s) => {
+            contains_yield(&s.then_branch) || s.else_branch.as_deref().is_some_and(contains_yield)
+        }
+        Stmt::While(s) => contains_yield(&s.body),
+        Stmt::ForIn(s) => contains_yield(&s.body),
+        _ => false,
+    }
+}
+
+// Where a suspended generator is in each of the statements it is running.
+// The tree walking interpreter keeps this on the Rust stack, which is gone
+// once a value is yielded, so generators keep it here instead.
+enum Frame {
+    // The statements of a block still to run
+    Block {
+        statements: Rc<Vec<Stmt>>,
+        next: usize,
+        environment: Rc<RefCell<Environment>>,
+    },
+    While {
+        condition: Expr,
+        body: Rc<Vec<Stmt>>,
+        environment: Rc<RefCell<Environment>>,
+    },
+    ForIn {
+        keyword: Token,
+        name: Token,
+        next: Callable,
+        body: Rc<Vec<Stmt>>,
+        environment: Rc<RefCell<Environment>>,
+    },
+}
+
+impl Frame {
+    fn is_loop(&self) -> bool {
+        !matches!(self, Frame::Block { .. })
+    }
+}
+
+// The iterator a call to a function with `yield` statements returns. Each call
+// runs the function until its next `yield`, and gives nil once it returns.
+pub struct Generator {
+    name: String,
+    frames: RefCell<Vec<Frame>>,
+}
+
+impl Generator {
+    pub fn new(name: String, body: Rc<Vec<Stmt>>, environment: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            name,
+            frames: RefCell::new(vec![Frame::Block {
+                statements: body,
+                next: 0,
+                environment,
+            }]),
+        }
+    }
+
+    // Runs until the next `yield`, giving its value, or until the generator
+    // is done, giving None
+    fn resume(
+        frames: &mut Vec<Frame>,
+        interpreter: &Interpreter,
+    ) -> Result<Option<Object>, TeciResult> {
+        while let Some(frame) = frames.last_mut() {
+            match frame {
+                Frame::Block {
+                    statements,
+                    next,
+                    environment,
+                } => {
+                    if *next == statements.len() {
+                        frames.pop();
+                        continue;
+                    }
+                    *next += 1;
+                    let (statements, i) = (Rc::clone(statements), *next - 1);
+                    let environment = Rc::clone(environment);
+                    match Generator::start(frames, interpreter, &statements[i], environment) {
+                        Ok(Some(value)) => return Ok(Some(value)),
+                        Ok(None) => {}
+                        Err(TeciResult::Break) => {
+                            // Leave the innermost loop, or the function if
+                            // there is none, like a function call would
+                            let Some(i) = frames.iter().rposition(Frame::is_loop) else {
+                                return Ok(None);
+                            };
+                            frames.truncate(i);
+                        }
+                        Err(TeciResult::Return { .. }) => return Ok(None),
+                        Err(e) => return Err(e),
+                    }
+                }
+                Frame::While {
+                    condition,
+                    body,
+                    environment,
+                } => {
+                    let condition = interpreter.evaluate_in(condition, Rc::clone(environment))?;
+                    if Interpreter::is_truthy(&condition) {
+                        let frame = Frame::Block {
+                            statements: Rc::clone(body),
+                            next: 0,
+                            environment: Rc::clone(environment),
+                        };
+                        frames.push(frame);
+                    } else {
+                        frames.pop();
+                    }
+                }
+                Frame::ForIn {
+                    keyword,
+                    name,
+                    next,
+                    body,
+                    environment,
+                } => match interpreter.call(next, keyword.clone(), Vec::new())? {
+                    Object::Nil => {
+                        frames.pop();
+                    }
+                    value => {
+                        let mut environment = Environment::with_enclosing(Rc::clone(environment));
+                        environment.define(&name.lexeme, value);
+                        let frame = Frame::Block {
+                            statements: Rc::clone(body),
+                            next: 0,
+                            environment: Rc::new(RefCell::new(environment)),
+                        };
+                        frames.push(frame);
+                    }
+                },
+            }
+        }
+
+        Ok(None)
+    }
+
+    // Starts running `statement`. Statements that cannot yield run to
+    // completion, the others push the frames to run them step by step.
+    fn start(
+        frames: &mut Vec<Frame>,
+        interpreter: &Interpreter,
+        statement: &Stmt,
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<Option<Object>, TeciResult> {
+        if !contains_yield(statement) {
+            let in_loop = frames.iter().any(Frame::is_loop);
+            interpreter.execute_in(statement, environment, in_loop)?;
+            return Ok(None);
+        }
+
+        interpreter.before_statement(statement)?;
+        match statement {
+            Stmt::Yield(s) => return Ok(Some(interpreter.evaluate_in(&s.value, environment)?)),
+            Stmt::Block(s) => frames.push(Frame::Block {
+                statements: Rc::new(s.statements.clone()),
+                next: 0,
+                environment: Rc::new(RefCell::new(Environment::with_enclosing(environment))),
+            }),
+            Stmt::If(s) => {
+                let condition = interpreter.evaluate_in(&s.condition, Rc::clone(&environment))?;
+                let branch = if Interpreter::is_truthy(&condition) {
+                    Some(&s.then_branch)
+                } else {
+                    s.else_branch.as_ref()
+                };
+                if let Some(branch) = branch {
+                    frames.push(Frame::Block {
+                        statements: Rc::new(vec![(**branch).clone()]),
+                        next: 0,
+                        environment,
+                    });
+                }
+            }
+            Stmt::While(s) => frames.push(Frame::While {
+                condition: s.condition.clone(),
+                body: Rc::new(vec![(*s.body).clone()]),
+                environment,
+            }),
+            Stmt::ForIn(s) => {
+                let iterable = interpreter.evaluate_in(&s.iterable, Rc::clone(&environment))?;
+                frames.push(Frame::ForIn {
+                    keyword: s.keyword.clone(),
+                    name: s.name.clone(),
+                    next: Interpreter::iterator(&s.keyword, &iterable)?,
+                    body: Rc::new(vec![(*s.body).clone()]),
+                    environment,
+                });
+            }
+            _ => unreachable!("only the statements above can contain a yield"),
+        }
+
+        Ok(None)
+    }
+}
+
+impl TeciCallable for Generator {
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn call(&self, interpreter: &Interpreter, _args: Vec<Object>) -> Result<Object, TeciResult> {
+        let Ok(mut frames) = self.frames.try_borrow_mut() else {
+            return Err(TeciResult::system_error(&format!(
+                "Generator {} is already running",
+                self.name
+            )));
+        };
+        let result = Generator::resume(&mut frames, interpreter);
+        // Done for good once it returns or fails
+        if !matches!(result, Ok(Some(_))) {
+            frames.clear();
+        }
+        result.map(|value| value.unwrap_or(Object::Nil))
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn to_string(&self) -> String {
+        format!("<generator {}>", self.name)
+    }
+
+    // Running generators cannot be looked into, as their frames are in use
+    fn trace(&self, visit: &mut dyn FnMut(Traced)) {
+        let Ok(frames) = self.frames.try_borrow() else {
+            return;
+        };
+        for frame in frames.iter() {
+            match frame {
+                Frame::Block { environment, .. } | Frame::While { environment, .. } => {
+                    visit(Traced::Environment(environment))
+                }
+                Frame::ForIn {
+                    next, environment, ..
+                } => {
+                    visit(Traced::Callable(next));
+                    visit(Traced::Environment(environment));
+                }
+            }
+        }
+    }
+}
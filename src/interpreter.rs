@@ -1,13 +1,23 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::io::{self, BufRead, Write};
 use std::rc::Rc;
+use std::slice;
 
 use crate::{
+    call_stack::{CallFrame, CallStack, DEFAULT_MAX_DEPTH},
     callable::{Callable, TeciCallable},
+    debugger::{DebugHook, statement_line},
     envirnoment::Environment,
     error::TeciResult,
     expr::*,
+    fs::{self, FsModule},
+    gc::{Heap, HeapStats},
+    iterator,
     native_functions::*,
-    object::Object,
+    native_object::NativeObject,
+    object::{Object, OperatorError, OperatorResult},
+    profiler::{FunctionId, Profiler},
+    sandbox::{Capability, Limit, Limiter, Sandbox},
     stmt::*,
     teci_function::TeciFunction,
     token::Token,
@@ -18,6 +28,17 @@ pub struct Interpreter {
     pub _globals: Rc<RefCell<Environment>>,
     environment: RefCell<Rc<RefCell<Environment>>>,
     nesting_level: RefCell<usize>,
+    call_stack: RefCell<CallStack>,
+    debug_hook: RefCell<Option<Rc<dyn DebugHook>>>,
+    output: RefCell<Box<dyn Write>>,
+    // What `input` reads, stdin when not set
+    input: RefCell<Option<Box<dyn BufRead>>>,
+    heap: Heap,
+    profiler: RefCell<Option<Profiler>>,
+    // Whether a function body is running, where calls in tail position are
+    // handed back to `TeciFunction::call` rather than made
+    tail_calls: Cell<bool>,
+    limiter: RefCell<Option<Limiter>>,
 }
 
 impl Interpreter {
@@ -31,29 +52,225 @@ impl Interpreter {
             }),
         );
 
+        globals.borrow_mut().define(
+            "next",
+            Object::Func(Callable {
+                func: Rc::new(NativeNext),
+            }),
+        );
+        globals.borrow_mut().define(
+            "range",
+            Object::Func(Callable {
+                func: Rc::new(NativeRange),
+            }),
+        );
+        globals.borrow_mut().define(
+            "gc",
+            Object::Func(Callable {
+                func: Rc::new(NativeGc),
+            }),
+        );
+        globals.borrow_mut().define(
+            "input",
+            Object::Func(Callable {
+                func: Rc::new(NativeInput),
+            }),
+        );
+        globals
+            .borrow_mut()
+            .define("fs", Object::Native(NativeObject::new(FsModule)));
+
         Self {
             _globals: Rc::clone(&globals),
             environment: RefCell::new(Rc::clone(&globals)),
             nesting_level: RefCell::new(0),
+            call_stack: RefCell::new(CallStack::new(DEFAULT_MAX_DEPTH)),
+            debug_hook: RefCell::new(None),
+            output: RefCell::new(Box::new(io::stdout())),
+            input: RefCell::new(None),
+            heap: Heap::new(),
+            profiler: RefCell::new(None),
+            tail_calls: Cell::new(false),
+            limiter: RefCell::new(None),
         }
     }
 
+    pub fn set_max_depth(&self, max_depth: usize) {
+        self.call_stack.borrow_mut().set_max_depth(max_depth);
+    }
+
+    // Runs scripts within the limits of `sandbox` from now on
+    pub fn set_sandbox(&self, sandbox: Sandbox) {
+        self.limiter.replace(Some(Limiter::new(sandbox)));
+    }
+
+    // Whether the sandbox, if any, grants `capability`
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.limiter
+            .borrow()
+            .as_ref()
+            .is_none_or(|limiter| limiter.sandbox().allows(capability))
+    }
+
+    // Fails with `TeciResult::LimitExceeded` if the sandbox, if any, does
+    // not allow what `check` looks at
+    fn check_limit(
+        &self,
+        line: usize,
+        check: impl FnOnce(&mut Limiter) -> Result<(), Limit>,
+    ) -> Result<(), TeciResult> {
+        match self.limiter.borrow_mut().as_mut().map(check) {
+            Some(Err(limit)) => Err(TeciResult::limit_exceeded(line, limit)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn set_debug_hook(&self, hook: Rc<dyn DebugHook>) {
+        self.debug_hook.replace(Some(hook));
+    }
+
+    pub fn set_output(&self, output: Box<dyn Write>) {
+        self.output.replace(output);
+    }
+
+    pub fn set_input(&self, input: Box<dyn BufRead>) {
+        self.input.replace(Some(input));
+    }
+
+    // Writes `text` where `print` does, right away
+    pub fn write(&self, text: &str) -> Result<(), TeciResult> {
+        let mut output = self.output.borrow_mut();
+        output
+            .write_all(text.as_bytes())
+            .and_then(|_| output.flush())
+            .map_err(|e| TeciResult::system_error(&format!("Could not print: {}", e)))
+    }
+
+    // Reads a line for `input`, without the line break, or None at the end
+    // of the input
+    pub fn read_line(&self) -> io::Result<Option<String>> {
+        match self.input.borrow_mut().as_mut() {
+            Some(input) => fs::read_line(input),
+            None => fs::read_line(&mut io::stdin().lock()),
+        }
+    }
+
+    // A runtime error at the call being made, for natives, which have no
+    // token of their own to report it at
+    pub fn call_error(&self, message: &str) -> TeciResult {
+        let call_site = self
+            .call_stack
+            .borrow()
+            .frames()
+            .last()
+            .map(|frame| frame.call_site.clone());
+        match call_site {
+            Some(call_site) => TeciResult::runtime_error(call_site, message),
+            None => TeciResult::system_error(message),
+        }
+    }
+
+    pub fn environment(&self) -> Rc<RefCell<Environment>> {
+        Rc::clone(&self.environment.borrow())
+    }
+
+    pub fn call_depth(&self) -> usize {
+        self.call_stack.borrow().depth()
+    }
+
+    pub fn call_frames(&self) -> Vec<CallFrame> {
+        self.call_stack.borrow().frames().to_vec()
+    }
+
+    // Lets the garbage collector know a function closes over `environment`
+    pub fn track(&self, environment: &Rc<RefCell<Environment>>) {
+        self.heap.track(environment);
+    }
+
+    pub fn collect_garbage(&self) -> usize {
+        self.heap.collect()
+    }
+
+    pub fn heap_stats(&self) -> HeapStats {
+        self.heap.stats()
+    }
+
+    // Counts and times the calls made from now on
+    pub fn enable_profiler(&self) {
+        self.profiler.replace(Some(Profiler::new()));
+    }
+
+    pub fn profiler_report(&self) -> Option<String> {
+        self.profiler.borrow().as_ref().map(Profiler::report)
+    }
+
     pub fn dbg_environment(&self) {
         println!("{:?}", &self.environment);
     }
 
-    pub fn interpret(&self, statements: &[Stmt]) -> bool {
+    pub fn interpret(&self, statements: &[Stmt]) -> Result<(), TeciResult> {
         *self.nesting_level.borrow_mut() = 0;
-        match statements.iter().try_for_each(|s| self.execute(s)) {
-            Ok(_) => true,
-            Err(r) => matches!(r, TeciResult::Break),
+        if let Some(limiter) = self.limiter.borrow_mut().as_mut() {
+            limiter.restart();
+        }
+        let result = statements.iter().try_for_each(|s| self.execute(s));
+        let frames = self.call_stack.borrow_mut().take();
+        match result {
+            Ok(_)
+            | Err(TeciResult::Break)
+            | Err(TeciResult::Return { .. })
+            | Err(TeciResult::Terminate) => Ok(()),
+            Err(e) => Err(TeciResult::backtrace(e, frames)),
         }
     }
 
     fn execute(&self, statement: &Stmt) -> Result<(), TeciResult> {
+        self.before_statement(statement)?;
         statement.accept(self)
     }
 
+    // Counts the statement about to run against the sandbox, if any, and
+    // lets the debug hook, if any, see it
+    pub fn before_statement(&self, statement: &Stmt) -> Result<(), TeciResult> {
+        self.check_limit(statement_line(statement), Limiter::count_statement)?;
+        let hook = self.debug_hook.borrow().clone();
+        match hook {
+            Some(hook) => hook.before_statement(self, statement),
+            None => Ok(()),
+        }
+    }
+
+    // Executes a statement of a generator in `environment`. `in_loop` tells
+    // whether the generator is in a loop, where a `break` is allowed.
+    pub fn execute_in(
+        &self,
+        statement: &Stmt,
+        environment: Rc<RefCell<Environment>>,
+        in_loop: bool,
+    ) -> Result<(), TeciResult> {
+        let nesting_level = self.nesting_level.replace(in_loop as usize);
+        let tail_calls = self.tail_calls.replace(false);
+        let previous = self.environment.replace(environment);
+        let result = self.execute(statement);
+        self.environment.replace(previous);
+        self.tail_calls.set(tail_calls);
+        self.nesting_level.replace(nesting_level);
+        result
+    }
+
+    pub fn evaluate_in(
+        &self,
+        expr: &Expr,
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<Object, TeciResult> {
+        let tail_calls = self.tail_calls.replace(false);
+        let previous = self.environment.replace(environment);
+        let result = self.evaluate(expr);
+        self.environment.replace(previous);
+        self.tail_calls.set(tail_calls);
+        result
+    }
+
     pub fn execute_block(
         &self,
         statements: &[Stmt],
@@ -65,55 +282,381 @@ impl Interpreter {
         result
     }
 
+    // Executes the body of a function, handing back the call it ends with,
+    // or returns the value of, as a `TeciResult::TailCall`
+    pub fn execute_body(
+        &self,
+        statements: &[Stmt],
+        environment: Environment,
+    ) -> Result<(), TeciResult> {
+        let tail_calls = self.tail_calls.replace(true);
+        let previous = self.environment.replace(Rc::new(RefCell::new(environment)));
+        let result = self.execute_tail(statements);
+        self.environment.replace(previous);
+        self.tail_calls.set(tail_calls);
+        result
+    }
+
+    // Executes statements whose last one is in tail position: a call there,
+    // or at the end of the branch of an `if` or of a block there, is the
+    // last thing the function does
+    fn execute_tail(&self, statements: &[Stmt]) -> Result<(), TeciResult> {
+        let Some((last, statements)) = statements.split_last() else {
+            return Ok(());
+        };
+        statements.iter().try_for_each(|s| self.execute(s))?;
+        self.before_statement(last)?;
+        match last {
+            Stmt::Expression(stmt) => self.evaluate_tail(&stmt.expression, true).map(|_| ()),
+            Stmt::If(stmt) => {
+                if Interpreter::is_truthy(&self.evaluate(&stmt.condition)?) {
+                    self.execute_tail(slice::from_ref(&stmt.then_branch))
+                } else if let Some(else_branch) = &stmt.else_branch {
+                    self.execute_tail(slice::from_ref(else_branch))
+                } else {
+                    Ok(())
+                }
+            }
+            Stmt::Block(stmt) => {
+                let e = Environment::with_enclosing(self.environment.borrow().clone());
+                let previous = self.environment.replace(Rc::new(RefCell::new(e)));
+                let result = self.execute_tail(&stmt.statements);
+                self.environment.replace(previous);
+                result
+            }
+            _ => last.accept(self),
+        }
+    }
+
+    // Evaluates an expression in tail position: a call there, or in the
+    // branch of a `?:`, a grouping or the arm of a `match` there, is handed
+    // back as a `TeciResult::TailCall`
+    fn evaluate_tail(&self, expr: &Expr, discard: bool) -> Result<Object, TeciResult> {
+        match expr {
+            Expr::Call(call) => Err(self.tail_call_of(call, discard)?),
+            Expr::Grouping(grouping) => self.evaluate_tail(&grouping.expression, discard),
+            Expr::Conditional(conditional) => {
+                if Interpreter::is_truthy(&self.evaluate(&conditional.condition)?) {
+                    self.evaluate_tail(&conditional.then_branch, discard)
+                } else {
+                    self.evaluate_tail(&conditional.else_branch, discard)
+                }
+            }
+            Expr::Match(expr) => {
+                let (body, environment) = self.match_arm(expr)?;
+                let previous = self.environment.replace(environment);
+                let result = self.evaluate_tail(body, discard);
+                self.environment.replace(previous);
+                result
+            }
+            _ => self.evaluate(expr),
+        }
+    }
+
+    // The call in tail position, with its callee and arguments evaluated
+    fn tail_call_of(&self, expr: &CallExpr, discard: bool) -> Result<TeciResult, TeciResult> {
+        let (function, arguments) = self.evaluate_call(expr)?;
+        Ok(TeciResult::TailCall {
+            function,
+            call_site: expr.paren.clone(),
+            arguments,
+            discard,
+        })
+    }
+
+    // The body of the first arm that matches the value, with the
+    // environment that binds the names in its pattern
+    fn match_arm<'a>(
+        &self,
+        expr: &'a MatchExpr,
+    ) -> Result<(&'a Expr, Rc<RefCell<Environment>>), TeciResult> {
+        let value = self.evaluate(&expr.value)?;
+        for arm in &expr.arms {
+            let mut bindings = Vec::new();
+            if !arm.pattern.matches(&value, &mut bindings) {
+                continue;
+            }
+            let mut environment = Environment::with_enclosing(self.environment.borrow().clone());
+            for (name, value) in bindings {
+                environment.define(&name.lexeme, value);
+            }
+            let environment = Rc::new(RefCell::new(environment));
+            if let Some(guard) = &arm.guard
+                && !Interpreter::is_truthy(&self.evaluate_in(guard, Rc::clone(&environment))?)
+            {
+                continue;
+            }
+            return Ok((&arm.body, environment));
+        }
+
+        Err(TeciResult::runtime_error(
+            expr.keyword.clone(),
+            &format!("No pattern matches the value {}", value),
+        ))
+    }
+
+    fn evaluate_call(&self, expr: &CallExpr) -> Result<(Callable, Vec<Object>), TeciResult> {
+        let callee = self.evaluate(&expr.callee)?;
+
+        let mut arguments = Vec::new();
+        for arg in &expr.arguments {
+            arguments.push(self.evaluate(arg)?);
+        }
+
+        if let Object::Func(function) = callee {
+            Ok((function, arguments))
+        } else {
+            Err(TeciResult::runtime_error(
+                expr.paren.clone(),
+                "Only callable objects are functions and classes",
+            ))
+        }
+    }
+
     fn evaluate(&self, expr: &Expr) -> Result<Object, TeciResult> {
         expr.accept(self)
     }
 
-    fn is_truthy(obj: &Object) -> bool {
+    // Evaluates an expression on behalf of a tool (e.g. a debugger prompt) and
+    // leaves the call stack as it found it, even if the evaluation fails
+    pub fn evaluate_detached(&self, expr: &Expr) -> Result<Object, TeciResult> {
+        let depth = self.call_depth();
+        let result = self.evaluate(expr);
+        self.call_stack.borrow_mut().truncate(depth);
+        result
+    }
+
+    fn check_arity(
+        function: &Callable,
+        call_site: &Token,
+        arguments: &[Object],
+    ) -> Result<(), TeciResult> {
+        if function.arity() != arguments.len() {
+            return Err(TeciResult::runtime_error(
+                call_site.clone(),
+                &format!(
+                    "Expected {} arguments but found {} instead",
+                    function.arity(),
+                    arguments.len()
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    // Whether the sandbox, if any, lets `function` be called at `depth`
+    fn check_call(
+        &self,
+        function: &Callable,
+        call_site: &Token,
+        depth: usize,
+    ) -> Result<(), TeciResult> {
+        self.check_limit(call_site.line, |limiter| {
+            limiter.check_depth(depth)?;
+            match function.capability() {
+                Some(capability) => limiter.check_capability(capability),
+                None => Ok(()),
+            }
+        })
+    }
+
+    // Calls `function` on behalf of the code at `call_site`, keeping track of
+    // the call in the call stack
+    pub fn call(
+        &self,
+        function: &Callable,
+        call_site: Token,
+        arguments: Vec<Object>,
+    ) -> Result<Object, TeciResult> {
+        Interpreter::check_arity(function, &call_site, &arguments)?;
+        self.check_call(function, &call_site, self.call_depth())?;
+        self.call_stack.borrow_mut().push(CallFrame {
+            callee: function.name(),
+            call_site,
+            arguments: arguments.clone(),
+        })?;
+        let result = self.profiled(function, || function.call(self, arguments));
+        // On failure the frame is left in place so `interpret` can report
+        // the chain of calls that was active when the error was raised
+        if result.is_ok() {
+            self.call_stack.borrow_mut().pop();
+        }
+        result
+    }
+
+    // Makes a call handed back from tail position. It runs in place of the
+    // call that ended with it, so the Rust stack does not grow, but it still
+    // gets a frame in the call stack, so it counts towards the maximum depth
+    // and shows in backtraces. `end_tail_calls` pops the frames once the
+    // chain of calls returns.
+    pub fn tail_call(
+        &self,
+        function: &Callable,
+        call_site: Token,
+        arguments: Vec<Object>,
+    ) -> Result<Object, TeciResult> {
+        Interpreter::check_arity(function, &call_site, &arguments)?;
+        self.check_call(function, &call_site, self.call_depth())?;
+        self.call_stack.borrow_mut().push(CallFrame {
+            callee: function.name(),
+            call_site,
+            arguments: arguments.clone(),
+        })?;
+        self.profiled(function, || function.call_once(self, arguments))
+    }
+
+    pub fn end_tail_calls(&self, count: usize) {
+        let depth = self.call_depth().saturating_sub(count);
+        self.call_stack.borrow_mut().truncate(depth);
+    }
+
+    fn profiled(
+        &self,
+        function: &Callable,
+        call: impl FnOnce() -> Result<Object, TeciResult>,
+    ) -> Result<Object, TeciResult> {
+        let profiled = self.profiler.borrow_mut().as_mut().map(|profiler| {
+            let declaration = function.declaration();
+            let id = match declaration {
+                Some(name) => FunctionId::Declared(name.line, name.column),
+                None => FunctionId::Named(TeciCallable::to_string(function)),
+            };
+            let started = profiler.enter(&id, || match declaration {
+                Some(name) => format!("{} (line {})", TeciCallable::to_string(function), name.line),
+                None => TeciCallable::to_string(function),
+            });
+            (id, started)
+        });
+        let result = call();
+        if let Some((id, started)) = profiled
+            && let Some(profiler) = self.profiler.borrow_mut().as_mut()
+        {
+            profiler.exit(&id, started);
+        }
+        result
+    }
+
+    // The iterator a `for-in` loop at `keyword` takes from `iterable`
+    pub fn iterator(keyword: &Token, iterable: &Object) -> Result<Callable, TeciResult> {
+        iterator::iterator(iterable).ok_or_else(|| {
+            TeciResult::runtime_error(
+                keyword.clone(),
+                &format!("Cannot iterate over a {}", iterable.type_name()),
+            )
+        })
+    }
+
+    pub fn is_truthy(obj: &Object) -> bool {
         match obj {
+            Object::Int(x) => *x != 0,
+            // Never zero
+            Object::BigInt(_) | Object::Rational(_) => true,
             Object::Num(x) => *x != 0.0,
             Object::Str(s) => !s.is_empty(),
             Object::Bool(b) => *b,
             Object::Nil => false,
-            Object::ArithmeticError => false,
-            Object::DivisionByZeroError => false,
-            Object::Func(_) => false,
+            Object::Func(_) | Object::Native(_) => false,
+            Object::Tuple(elements) => !elements.is_empty(),
         }
     }
 
-    fn check_numeric_operands(
+    // Compares two numbers with `compare`
+    fn compare(
         left: &Object,
         right: &Object,
-        operator: Token,
-    ) -> Result<(), TeciResult> {
-        match (left, right) {
-            (Object::Num(_), Object::Num(_)) => Ok(()),
-            _ => Err(TeciResult::runtime_error(
-                operator,
-                "Invalid operator for non numeric operands",
-            )),
+        compare: impl Fn(&Object, &Object) -> bool,
+    ) -> OperatorResult {
+        if left.is_number() && right.is_number() {
+            Ok(Object::Bool(compare(left, right)))
+        } else {
+            Err(OperatorError::UnsupportedOperands(
+                left.type_name(),
+                right.type_name(),
+            ))
+        }
+    }
+
+    pub fn unary(operator: TokenType, right: Object) -> OperatorResult {
+        match operator {
+            TokenType::Minus => -right,
+            TokenType::Tilde => !right,
+            TokenType::Bang => Ok(Object::Bool(!Interpreter::is_truthy(&right))),
+            _ => unreachable!("not a unary operator"),
+        }
+    }
+
+    pub fn binary(operator: TokenType, left: Object, right: Object) -> OperatorResult {
+        match operator {
+            TokenType::Minus => left - right,
+            TokenType::Star => left * right,
+            TokenType::StarStar => left.pow(right),
+            TokenType::Slash => left / right,
+            TokenType::TildeSlash => left.floor_div(right),
+            TokenType::Percent => left % right,
+            TokenType::Plus => left + right,
+            TokenType::Ampersand => left & right,
+            TokenType::Pipe => left | right,
+            TokenType::Caret => left ^ right,
+            TokenType::LessLess => left << right,
+            TokenType::GreaterGreater => left >> right,
+            TokenType::GreaterEqual => Interpreter::compare(&left, &right, |l, r| l >= r),
+            TokenType::Greater => Interpreter::compare(&left, &right, |l, r| l > r),
+            TokenType::LessEqual => Interpreter::compare(&left, &right, |l, r| l <= r),
+            TokenType::Less => Interpreter::compare(&left, &right, |l, r| l < r),
+            TokenType::Equals => Ok(Object::Bool(left == right)),
+            TokenType::BangEqual => Ok(Object::Bool(left != right)),
+            _ => unreachable!("not a binary operator"),
         }
     }
 
+    // At most how large, as `Object::size`, the result of the operators that
+    // make big numbers out of small ones would be
+    fn size_bound(operator: TokenType, left: &Object, right: &Object) -> Option<u64> {
+        let bits = left.exact_bits()?;
+        let bits = match (operator, right) {
+            (TokenType::Star, right) => bits + right.exact_bits()?,
+            // 0, 1 and -1 stay as they are
+            (TokenType::StarStar, Object::Int(_)) if bits <= 1 => 1,
+            (TokenType::StarStar, Object::Int(exponent)) => {
+                bits.saturating_mul(exponent.unsigned_abs())
+            }
+            (TokenType::LessLess, Object::Int(count)) if *count > 0 => {
+                bits.saturating_add(count.unsigned_abs())
+            }
+            (TokenType::GreaterGreater, Object::Int(count)) if *count < 0 => {
+                bits.saturating_add(count.unsigned_abs())
+            }
+            // Shifting by a big number, which fails unless the value is zero
+            (TokenType::LessLess | TokenType::GreaterGreater, Object::BigInt(_)) => 0,
+            _ => return None,
+        };
+        Some(bits.div_ceil(8))
+    }
+
+    fn operator_error(operator: &Token, error: OperatorError) -> TeciResult {
+        TeciResult::runtime_error(operator.clone(), &error.message(&operator.lexeme))
+    }
+
     pub fn stringify(value: Object) -> String {
         match value {
-            Object::Num(x) => {
-                let mut text = x.to_string();
-                if text.ends_with(".0") {
-                    text = text.get(0..text.len() - 2).unwrap().to_string();
-                }
-                text
+            Object::Int(_) | Object::BigInt(_) | Object::Rational(_) | Object::Num(_) => {
+                value.to_string()
             }
-            Object::Str(s) => s,
+            Object::Str(s) => s.to_string(),
             Object::Bool(b) => b.to_string(),
             Object::Nil => "nil".to_string(),
-            Object::ArithmeticError => "arithmetic_error!!!".to_string(),
-            Object::DivisionByZeroError => "division_by_zero_error!!!".to_string(),
             Object::Func(callable) => TeciCallable::to_string(&callable),
+            Object::Tuple(_) | Object::Native(_) => value.to_string(),
         }
     }
 }
 
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ExprVisitor<Object> for Interpreter {
     fn visit_assign_expr(&self, expr: &AssignExpr) -> Result<Object, TeciResult> {
         let value = self.evaluate(&expr.value)?;
@@ -126,61 +669,73 @@ impl ExprVisitor<Object> for Interpreter {
 
     fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<Object, TeciResult> {
         let right = self.evaluate(&expr.right)?;
-        let result = match expr.operator.ttype {
-            TokenType::Minus => -right,
-            TokenType::Bang => Object::Bool(!Interpreter::is_truthy(&right)),
-            _ => Object::ArithmeticError,
-        };
+        Interpreter::unary(expr.operator.ttype, right)
+            .map_err(|error| Interpreter::operator_error(&expr.operator, error))
+    }
 
-        if result == Object::ArithmeticError {
-            Err(TeciResult::teci_error(
-                expr.operator.line,
-                "Invalid operator",
-            ))
+    fn visit_update_expr(&self, expr: &UpdateExpr) -> Result<Object, TeciResult> {
+        let old = self.environment.borrow().borrow().get(&expr.name)?;
+        let new = if !old.is_number() {
+            Err(OperatorError::UnsupportedOperand(old.type_name()))
+        } else if expr.operator.ttype == TokenType::PlusPlus {
+            old.clone() + Object::Int(1)
         } else {
-            Ok(result)
-        }
+            old.clone() - Object::Int(1)
+        };
+        let new = new.map_err(|error| Interpreter::operator_error(&expr.operator, error))?;
+        self.environment
+            .borrow()
+            .borrow_mut()
+            .assign(&expr.name, new.clone())?;
+        Ok(if expr.prefix { new } else { old })
     }
 
     fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<Object, TeciResult> {
         let left = self.evaluate(&expr.left)?;
         let right = self.evaluate(&expr.right)?;
-        let result = match expr.operator.ttype {
-            TokenType::Minus => left - right,
-            TokenType::Star => left * right,
-            TokenType::Slash => left / right,
-            TokenType::Plus => left + right,
-            TokenType::GreaterEqual => {
-                Interpreter::check_numeric_operands(&left, &right, expr.operator.clone())?;
-                Object::Bool(left >= right)
-            }
-            TokenType::Greater => {
-                Interpreter::check_numeric_operands(&left, &right, expr.operator.clone())?;
-                Object::Bool(left > right)
-            }
-            TokenType::LessEqual => {
-                Interpreter::check_numeric_operands(&left, &right, expr.operator.clone())?;
-                Object::Bool(left <= right)
-            }
-            TokenType::Less => {
-                Interpreter::check_numeric_operands(&left, &right, expr.operator.clone())?;
-                Object::Bool(left < right)
+        let line = expr.operator.line;
+        // Refused before computing, as that alone can take too long
+        self.check_limit(line, |limiter| {
+            match Interpreter::size_bound(expr.operator.ttype, &left, &right) {
+                Some(bound) => limiter.check_size(bound),
+                None => Ok(()),
             }
-            TokenType::Equals => Object::Bool(left == right),
-            TokenType::BangEqual => Object::Bool(left != right),
-            _ => Object::ArithmeticError,
-        };
+        })?;
+        let value = Interpreter::binary(expr.operator.ttype, left, right)
+            .map_err(|error| Interpreter::operator_error(&expr.operator, error))?;
+        self.check_limit(line, |limiter| limiter.check_size(value.size()))?;
+        Ok(value)
+    }
 
-        match result {
-            Object::ArithmeticError => Err(TeciResult::runtime_error(
-                expr.operator.clone(),
-                "Invalid operator",
-            )),
-            Object::DivisionByZeroError => Err(TeciResult::runtime_error(
-                expr.operator.clone(),
-                "Division by zero",
+    fn visit_match_expr(&self, expr: &MatchExpr) -> Result<Object, TeciResult> {
+        let (body, environment) = self.match_arm(expr)?;
+        self.evaluate_in(body, environment)
+    }
+
+    fn visit_conditional_expr(&self, expr: &ConditionalExpr) -> Result<Object, TeciResult> {
+        if Interpreter::is_truthy(&self.evaluate(&expr.condition)?) {
+            self.evaluate(&expr.then_branch)
+        } else {
+            self.evaluate(&expr.else_branch)
+        }
+    }
+
+    fn visit_get_expr(&self, expr: &GetExpr) -> Result<Object, TeciResult> {
+        match self.evaluate(&expr.object)? {
+            Object::Native(native) => native.object.get(&expr.name.lexeme).ok_or_else(|| {
+                TeciResult::runtime_error(
+                    expr.name.clone(),
+                    &format!(
+                        "A {} has no property '{}'",
+                        native.object.type_name(),
+                        expr.name.lexeme
+                    ),
+                )
+            }),
+            object => Err(TeciResult::runtime_error(
+                expr.name.clone(),
+                &format!("A {} has no properties", object.type_name()),
             )),
-            _ => Ok(result),
         }
     }
 
@@ -192,6 +747,28 @@ impl ExprVisitor<Object> for Interpreter {
         Ok(expr.value.clone().unwrap())
     }
 
+    fn visit_tuple_expr(&self, expr: &TupleExpr) -> Result<Object, TeciResult> {
+        let elements = expr
+            .elements
+            .iter()
+            .map(|element| self.evaluate(element))
+            .collect::<Result<Vec<_>, _>>()?;
+        let tuple = Object::Tuple(elements.into());
+        self.check_limit(expr.paren.line, |limiter| limiter.check_size(tuple.size()))?;
+        Ok(tuple)
+    }
+
+    fn visit_tupleassign_expr(&self, expr: &TupleAssignExpr) -> Result<Object, TeciResult> {
+        let value = self.evaluate(&expr.value)?;
+        let mut bindings = Vec::new();
+        expr.target.destructure(value.clone(), &mut bindings)?;
+        let environment = self.environment.borrow();
+        for (name, value) in bindings {
+            environment.borrow_mut().assign(&name, value)?;
+        }
+        Ok(value)
+    }
+
     fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<Object, TeciResult> {
         self.environment.borrow().borrow().get(&expr.name)
     }
@@ -209,40 +786,20 @@ impl ExprVisitor<Object> for Interpreter {
     }
 
     fn visit_call_expr(&self, expr: &CallExpr) -> Result<Object, TeciResult> {
-        let callee = self.evaluate(&expr.callee)?;
-
-        let mut arguments = Vec::new();
-        for arg in &expr.arguments {
-            arguments.push(self.evaluate(arg)?);
-        }
-
-        if let Object::Func(function) = callee {
-            if function.arity() != arguments.len() {
-                Err(TeciResult::runtime_error(
-                    expr.paren.clone(),
-                    &format!(
-                        "Expected {} arguments but found {} instead",
-                        function.arity(),
-                        arguments.len()
-                    ),
-                ))
-            } else {
-                function.call(self, arguments)
-            }
-        } else {
-            Err(TeciResult::runtime_error(
-                expr.paren.clone(),
-                "Only callable objects are functions and classes",
-            ))
-        }
+        let (function, arguments) = self.evaluate_call(expr)?;
+        self.call(&function, expr.paren.clone(), arguments)
     }
 }
 
 impl StmtVisitor<()> for Interpreter {
     fn visit_print_stmt(&self, stmt: &PrintStmt) -> Result<(), TeciResult> {
         let value = self.evaluate(&stmt.expression)?;
-        println!("{}", Interpreter::stringify(value));
-        Ok(())
+        writeln!(
+            self.output.borrow_mut(),
+            "{}",
+            Interpreter::stringify(value)
+        )
+        .map_err(|e| TeciResult::system_error(&format!("Could not print: {}", e)))
     }
 
     fn visit_expression_stmt(&self, stmt: &ExpressionStmt) -> Result<(), TeciResult> {
@@ -257,10 +814,35 @@ impl StmtVisitor<()> for Interpreter {
         } else {
             Object::Nil
         };
-        self.environment
-            .borrow()
-            .borrow_mut()
-            .define(stmt.name.lexeme.as_str(), value);
+        let environment = self.environment.borrow();
+        environment.borrow().check_redeclare(&stmt.name)?;
+        if stmt.constant {
+            environment
+                .borrow_mut()
+                .define_immutable(&stmt.name.lexeme, value, "constant");
+        } else {
+            environment.borrow_mut().define(&stmt.name.lexeme, value);
+        }
+        Ok(())
+    }
+
+    fn visit_destructure_stmt(&self, stmt: &DestructureStmt) -> Result<(), TeciResult> {
+        let value = self.evaluate(&stmt.initializer)?;
+        let mut bindings = Vec::new();
+        stmt.pattern.destructure(value, &mut bindings)?;
+        let environment = self.environment.borrow();
+        for (name, _) in &bindings {
+            environment.borrow().check_redeclare(name)?;
+        }
+        for (name, value) in bindings {
+            if stmt.constant {
+                environment
+                    .borrow_mut()
+                    .define_immutable(&name.lexeme, value, "constant");
+            } else {
+                environment.borrow_mut().define(&name.lexeme, value);
+            }
+        }
         Ok(())
     }
 
@@ -298,6 +880,40 @@ impl StmtVisitor<()> for Interpreter {
         Ok(())
     }
 
+    fn visit_forin_stmt(&self, stmt: &ForInStmt) -> Result<(), TeciResult> {
+        let iterable = self.evaluate(&stmt.iterable)?;
+        let next = Interpreter::iterator(&stmt.keyword, &iterable)?;
+
+        *self.nesting_level.borrow_mut() += 1;
+        let result = loop {
+            let value = match self.call(&next, stmt.keyword.clone(), Vec::new()) {
+                Ok(Object::Nil) => break Ok(()),
+                Ok(value) => value,
+                Err(e) => break Err(e),
+            };
+            // A new variable for every iteration, so closures capture its value
+            let mut environment = Environment::with_enclosing(self.environment.borrow().clone());
+            environment.define(&stmt.name.lexeme, value);
+            match self.execute_block(std::slice::from_ref(&stmt.body), environment) {
+                Ok(_) => {}
+                Err(TeciResult::Break) => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+        *self.nesting_level.borrow_mut() -= 1;
+
+        result
+    }
+
+    // Generators run their `yield` statements themselves, so one reaching
+    // here is not in a generator
+    fn visit_yield_stmt(&self, stmt: &YieldStmt) -> Result<(), TeciResult> {
+        Err(TeciResult::runtime_error(
+            stmt.keyword.clone(),
+            "Found a 'yield' statement outside a generator",
+        ))
+    }
+
     fn visit_break_stmt(&self, stmt: &BreakStmt) -> Result<(), TeciResult> {
         if *self.nesting_level.borrow() == 0 {
             Err(TeciResult::runtime_error(
@@ -310,21 +926,25 @@ impl StmtVisitor<()> for Interpreter {
     }
 
     fn visit_function_stmt(&self, stmt: &FunctionStmt) -> Result<(), TeciResult> {
-        let function = TeciFunction::new(stmt, &self.environment.borrow());
-        self.environment.borrow().borrow_mut().define(
+        let environment = self.environment();
+        environment.borrow().check_redeclare(&stmt.name)?;
+        let function = TeciFunction::new(stmt, &environment);
+        environment.borrow_mut().define_immutable(
             &stmt.name.lexeme,
             Object::Func(Callable {
                 func: Rc::new(function),
             }),
+            "function",
         );
+        self.track(&environment);
         Ok(())
     }
 
     fn visit_return_stmt(&self, stmt: &ReturnStmt) -> Result<(), TeciResult> {
-        let value = if let Some(obj) = &stmt.value {
-            self.evaluate(obj)?
-        } else {
-            Object::Nil
+        let value = match &stmt.value {
+            Some(value) if self.tail_calls.get() => self.evaluate_tail(value, false)?,
+            Some(value) => self.evaluate(value)?,
+            None => Object::Nil,
         };
 
         Err(TeciResult::Return { _value: value })
@@ -334,16 +954,37 @@ impl StmtVisitor<()> for Interpreter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::token::*;
+    use crate::{
+        parser::Parser, sandbox::DEFAULT_MAX_SIZE, scanner::Scanner, token::*,
+        type_checker::TypeChecker,
+    };
+    use std::time::Duration;
+
+    fn literal(value: Object) -> Box<Expr> {
+        Box::new(Expr::Literal(LiteralExpr {
+            token: Token::new(TokenType::Number, value.to_string(), None, 0),
+            value: Some(value),
+        }))
+    }
+
+    fn run_source(interpreter: &Interpreter, source: &str) -> Result<(), TeciResult> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens()?;
+        let statements = Parser::new(tokens).parse()?;
+        interpreter.interpret(&statements)
+    }
+
+    // The value of the variable `name` where the interpreter is at
+    fn get(interpreter: &Interpreter, name: &str) -> Object {
+        let token = Token::new(TokenType::Identifier, name, None, 0);
+        interpreter.environment().borrow().get(&token).unwrap()
+    }
 
     #[test]
     fn t_unary_minus() {
         let interpreter = Interpreter::new();
         let unary = UnaryExpr {
-            operator: Token::new(TokenType::Minus, "-".to_string(), None, 0),
-            right: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Num(69f64)),
-            })),
+            operator: Token::new(TokenType::Minus, "-", None, 0),
+            right: literal(Object::Num(69f64)),
         };
         assert_eq!(
             Object::Num(-69f64),
@@ -355,10 +996,8 @@ mod tests {
     fn t_unary_bang() {
         let interpreter = Interpreter::new();
         let boolean = UnaryExpr {
-            operator: Token::new(TokenType::Bang, "!".to_string(), None, 0),
-            right: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Bool(true)),
-            })),
+            operator: Token::new(TokenType::Bang, "!", None, 0),
+            right: literal(Object::Bool(true)),
         };
         assert_eq!(
             Object::Bool(false),
@@ -370,15 +1009,11 @@ mod tests {
     fn t_substraction() {
         let interpreter = Interpreter::new();
         let expr = BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Num(70.0)),
-            })),
-            operator: Token::new(TokenType::Minus, "-".to_string(), None, 0),
+            left: literal(Object::Num(70.0)),
+            operator: Token::new(TokenType::Minus, "-", None, 0),
             right: Box::new(Expr::Unary(UnaryExpr {
-                operator: Token::new(TokenType::Minus, "-".to_string(), None, 0),
-                right: Box::new(Expr::Literal(LiteralExpr {
-                    value: Some(Object::Num(1.0)),
-                })),
+                operator: Token::new(TokenType::Minus, "-", None, 0),
+                right: literal(Object::Num(1.0)),
             })),
         };
         assert_eq!(
@@ -391,15 +1026,11 @@ mod tests {
     fn t_mulitplication() {
         let interpreter = Interpreter::new();
         let expr = BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Num(70.0)),
-            })),
-            operator: Token::new(TokenType::Star, "*".to_string(), None, 0),
+            left: literal(Object::Num(70.0)),
+            operator: Token::new(TokenType::Star, "*", None, 0),
             right: Box::new(Expr::Unary(UnaryExpr {
-                operator: Token::new(TokenType::Minus, "-".to_string(), None, 0),
-                right: Box::new(Expr::Literal(LiteralExpr {
-                    value: Some(Object::Num(2.0)),
-                })),
+                operator: Token::new(TokenType::Minus, "-", None, 0),
+                right: literal(Object::Num(2.0)),
             })),
         };
         assert_eq!(
@@ -412,15 +1043,11 @@ mod tests {
     fn t_division() {
         let interpreter = Interpreter::new();
         let expr = BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Num(70.0)),
-            })),
-            operator: Token::new(TokenType::Slash, "/".to_string(), None, 0),
+            left: literal(Object::Num(70.0)),
+            operator: Token::new(TokenType::Slash, "/", None, 0),
             right: Box::new(Expr::Unary(UnaryExpr {
-                operator: Token::new(TokenType::Minus, "-".to_string(), None, 0),
-                right: Box::new(Expr::Literal(LiteralExpr {
-                    value: Some(Object::Num(2.0)),
-                })),
+                operator: Token::new(TokenType::Minus, "-", None, 0),
+                right: literal(Object::Num(2.0)),
             })),
         };
         assert_eq!(
@@ -433,15 +1060,11 @@ mod tests {
     fn t_addition_numbers() {
         let interpreter = Interpreter::new();
         let expr = BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Num(70.0)),
-            })),
-            operator: Token::new(TokenType::Plus, "+".to_string(), None, 0),
+            left: literal(Object::Num(70.0)),
+            operator: Token::new(TokenType::Plus, "+", None, 0),
             right: Box::new(Expr::Unary(UnaryExpr {
-                operator: Token::new(TokenType::Minus, "-".to_string(), None, 0),
-                right: Box::new(Expr::Literal(LiteralExpr {
-                    value: Some(Object::Num(2.0)),
-                })),
+                operator: Token::new(TokenType::Minus, "-", None, 0),
+                right: literal(Object::Num(2.0)),
             })),
         };
         assert_eq!(
@@ -454,16 +1077,12 @@ mod tests {
     fn t_concatenation_strings() {
         let interpreter = Interpreter::new();
         let expr = BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Str("Hello ".to_string())),
-            })),
-            operator: Token::new(TokenType::Plus, "+".to_string(), None, 0),
-            right: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Str("World!".to_string())),
-            })),
+            left: literal(Object::Str("Hello ".into())),
+            operator: Token::new(TokenType::Plus, "+", None, 0),
+            right: literal(Object::Str("World!".into())),
         };
         assert_eq!(
-            Object::Str("Hello World!".to_string()),
+            Object::Str("Hello World!".into()),
             interpreter.visit_binary_expr(&expr).unwrap()
         );
     }
@@ -472,28 +1091,48 @@ mod tests {
     fn t_arithmetic_error() {
         let interpreter = Interpreter::new();
         let expr = BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Str("Hello ".to_string())),
-            })),
-            operator: Token::new(TokenType::Minus, "-".to_string(), None, 0),
-            right: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Str("World!".to_string())),
-            })),
+            left: literal(Object::Str("Hello ".into())),
+            operator: Token::new(TokenType::Minus, "-", None, 0),
+            right: literal(Object::Str("World!".into())),
         };
         assert!(interpreter.visit_binary_expr(&expr).is_err())
     }
 
+    #[test]
+    fn t_operator_errors() {
+        // An operator error points at the operator, and names the types of the
+        // operands the way the type checker does
+        let source = "let n = 1;\nprint n -\n  \"s\";";
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        let Err(TeciResult::Backtrace { error, .. }) = Interpreter::new().interpret(&statements)
+        else {
+            panic!("expected a runtime error");
+        };
+        let TeciResult::RuntimeError { token, message } = *error else {
+            panic!("expected a runtime error");
+        };
+        assert_eq!((token.line, token.lexeme.as_ref()), (2, "-"));
+        assert_eq!(message, "Unsupported operand types for '-': num and str");
+
+        let typed = "let n: num = 1;\nprint n -\n  \"s\";";
+        let tokens = Scanner::new(typed.to_string()).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        match TypeChecker::new().check(&statements).as_slice() {
+            [TeciResult::TypeError { token, message: m }] => {
+                assert_eq!((token.line, m.as_str()), (2, message.as_str()))
+            }
+            errors => panic!("unexpected errors {:?}", errors),
+        }
+    }
+
     #[test]
     fn t_greaterequal() {
         let interpreter = Interpreter::new();
         let expr = BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Num(16.0)),
-            })),
-            operator: Token::new(TokenType::GreaterEqual, ">=".to_string(), None, 0),
-            right: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Num(6.0)),
-            })),
+            left: literal(Object::Num(16.0)),
+            operator: Token::new(TokenType::GreaterEqual, ">=", None, 0),
+            right: literal(Object::Num(6.0)),
         };
         assert_eq!(
             Object::Bool(true),
@@ -505,13 +1144,9 @@ mod tests {
     fn t_greater() {
         let interpreter = Interpreter::new();
         let expr = BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Num(16.0)),
-            })),
-            operator: Token::new(TokenType::Greater, ">".to_string(), None, 0),
-            right: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Num(6.0)),
-            })),
+            left: literal(Object::Num(16.0)),
+            operator: Token::new(TokenType::Greater, ">", None, 0),
+            right: literal(Object::Num(6.0)),
         };
         assert_eq!(
             Object::Bool(true),
@@ -523,13 +1158,9 @@ mod tests {
     fn t_lessequal() {
         let interpreter = Interpreter::new();
         let expr = BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Num(6.0)),
-            })),
-            operator: Token::new(TokenType::LessEqual, "<=".to_string(), None, 0),
-            right: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Num(6.0)),
-            })),
+            left: literal(Object::Num(6.0)),
+            operator: Token::new(TokenType::LessEqual, "<=", None, 0),
+            right: literal(Object::Num(6.0)),
         };
         assert_eq!(
             Object::Bool(true),
@@ -541,13 +1172,9 @@ mod tests {
     fn t_less() {
         let interpreter = Interpreter::new();
         let expr = BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Num(16.0)),
-            })),
-            operator: Token::new(TokenType::Less, "<".to_string(), None, 0),
-            right: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Bool(true)),
-            })),
+            left: literal(Object::Num(16.0)),
+            operator: Token::new(TokenType::Less, "<", None, 0),
+            right: literal(Object::Bool(true)),
         };
         assert!(interpreter.visit_binary_expr(&expr).is_err())
     }
@@ -556,13 +1183,9 @@ mod tests {
     fn t_equals() {
         let interpreter = Interpreter::new();
         let expr = BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Str("hello".to_string())),
-            })),
-            operator: Token::new(TokenType::Equals, "==".to_string(), None, 0),
-            right: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Str("hello".to_string())),
-            })),
+            left: literal(Object::Str("hello".into())),
+            operator: Token::new(TokenType::Equals, "==", None, 0),
+            right: literal(Object::Str("hello".into())),
         };
         assert_eq!(
             Object::Bool(true),
@@ -574,17 +1197,662 @@ mod tests {
     fn t_bangequals() {
         let interpreter = Interpreter::new();
         let expr = BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Nil),
-            })),
-            operator: Token::new(TokenType::BangEqual, "!=".to_string(), None, 0),
-            right: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Str("sixteen".to_string())),
-            })),
+            left: literal(Object::Nil),
+            operator: Token::new(TokenType::BangEqual, "!=", None, 0),
+            right: literal(Object::Str("sixteen".into())),
         };
         assert_eq!(
             Object::Bool(true),
             interpreter.visit_binary_expr(&expr).unwrap()
         )
     }
+
+    #[test]
+    fn t_runtime_error_backtrace() {
+        let interpreter = Interpreter::new();
+        let source =
+            "fun inner() {\n  return 1 - \"a\";\n}\nfun outer() {\n  inner();\n}\nouter();";
+        match run_source(&interpreter, source) {
+            Err(TeciResult::Backtrace { error, frames }) => {
+                assert!(matches!(*error, TeciResult::RuntimeError { .. }));
+                let calls: Vec<(String, usize)> = frames
+                    .into_iter()
+                    .map(|f| (f.callee, f.call_site.line))
+                    .collect();
+                assert_eq!(
+                    calls,
+                    vec![("outer".to_string(), 7), ("inner".to_string(), 5)]
+                );
+            }
+            _ => panic!("expected a runtime failure"),
+        }
+        assert!(run_source(&interpreter, "print 1;").is_ok());
+    }
+
+    #[test]
+    fn t_stack_overflow() {
+        let interpreter = Interpreter::new();
+        interpreter.set_max_depth(50);
+        let source = "fun down(n) {\n  return down(n + 1);\n}\ndown(0);";
+        match run_source(&interpreter, source) {
+            Err(TeciResult::Backtrace { error, frames }) => {
+                assert!(matches!(
+                    *error,
+                    TeciResult::RuntimeError { ref message, .. } if message.starts_with("Stack overflow")
+                ));
+                assert_eq!(frames.len(), 50);
+            }
+            _ => panic!("expected a stack overflow"),
+        }
+        assert!(run_source(&interpreter, "down;").is_ok());
+    }
+
+    #[test]
+    fn t_tail_calls() {
+        let interpreter = Interpreter::new();
+        // Deeper than the Rust stack of a test thread could go without them
+        interpreter.set_max_depth(200000);
+        interpreter.set_output(Box::new(io::sink()));
+        let source = "fun count_rec(n) {\n  if (n == 0) {\n    print \"Done!\";\n  } else {\n    count_rec(n - 1);\n  }\n}\nfun even(n) {\n  if (n == 0) return true;\n  return odd(n - 1);\n}\nfun odd(n) {\n  if (n == 0) return false;\n  return even(n - 1);\n}\nfun first(n) {\n  second(n);\n}\nfun second(n) {\n  return n * 1;\n}\ncount_rec(100000);\nlet parity = even(10001);\nlet result = first(1);";
+        assert!(run_source(&interpreter, source).is_ok());
+        assert!(matches!(get(&interpreter, "parity"), Object::Bool(false)));
+        // The value of a call made as a statement is not returned
+        assert!(matches!(get(&interpreter, "result"), Object::Nil));
+
+        // Tail calls keep their frames, for backtraces and the maximum depth
+        match run_source(&interpreter, "first(\"a\");") {
+            Err(TeciResult::Backtrace { frames, .. }) => {
+                let calls: Vec<(String, usize)> = frames
+                    .into_iter()
+                    .map(|f| (f.callee, f.call_site.line))
+                    .collect();
+                assert_eq!(
+                    calls,
+                    vec![("first".to_string(), 1), ("second".to_string(), 17)]
+                );
+            }
+            _ => panic!("expected a runtime failure"),
+        }
+        interpreter.set_max_depth(50);
+        match run_source(&interpreter, "count_rec(100);") {
+            Err(TeciResult::Backtrace { error, frames }) => {
+                assert!(matches!(
+                    *error,
+                    TeciResult::RuntimeError { ref message, .. } if message.starts_with("Stack overflow")
+                ));
+                assert_eq!(frames.len(), 50);
+            }
+            _ => panic!("expected a stack overflow"),
+        }
+        assert_eq!(interpreter.call_depth(), 0);
+        interpreter.set_max_depth(200000);
+
+        // Calls in the branches of `?:`, in groupings and in `match` arms
+        // are in tail position too
+        let source = r#"
+fun sum(n, acc) { return n == 0 ? acc : sum(n - 1, acc + n); }
+fun grouped(n) { return n == 0 ? "done" : (grouped(n - 1)); }
+fun matched(n) {
+    return match n {
+        0 => "done",
+        m => matched(m - 1),
+    };
+}
+fun stated(n) { n == 0 ? nil : stated(n - 1); }
+let total = sum(100000, 0);
+let group = grouped(1000);
+let arm = matched(1000);
+let statement = stated(1000);
+"#;
+        assert!(run_source(&interpreter, source).is_ok());
+        assert_eq!(get(&interpreter, "total"), Object::Int(5000050000));
+        assert_eq!(get(&interpreter, "group"), Object::Str("done".into()));
+        assert_eq!(get(&interpreter, "arm"), Object::Str("done".into()));
+        assert_eq!(get(&interpreter, "statement"), Object::Nil);
+    }
+
+    #[test]
+    fn t_sandbox() {
+        let limit = |sandbox: Sandbox, source: &str| {
+            let interpreter = Interpreter::new();
+            interpreter.set_sandbox(sandbox);
+            match run_source(&interpreter, source) {
+                Err(TeciResult::Backtrace { error, .. }) => match *error {
+                    TeciResult::LimitExceeded { line, limit } => Some((line, limit)),
+                    _ => None,
+                },
+                _ => None,
+            }
+        };
+        let forever = "let i = 0;\nwhile (true) {}";
+        assert_eq!(
+            limit(Sandbox::new().with_max_statements(100), forever),
+            Some((2, Limit::Statements(100)))
+        );
+        let timeout = Duration::from_millis(10);
+        assert_eq!(
+            limit(Sandbox::new().with_timeout(timeout), forever),
+            Some((2, Limit::Time(timeout)))
+        );
+        assert_eq!(
+            limit(
+                Sandbox::new().with_max_size(8),
+                "let s = \"\";\nwhile (true) s = s + \"a\";"
+            ),
+            Some((2, Limit::Size(8)))
+        );
+        // Big numbers are limited too, before the time goes into computing them
+        let sandbox = Sandbox::new().with_timeout(Duration::from_millis(500));
+        assert!(matches!(
+            limit(sandbox.clone(), "let x = 3;\nwhile (true) x = x * x;"),
+            Some((2, Limit::Size(_) | Limit::Time(_)))
+        ));
+        assert_eq!(
+            limit(sandbox.clone(), "let x = 3 ** 300000000;"),
+            Some((1, Limit::Size(DEFAULT_MAX_SIZE)))
+        );
+        assert_eq!(
+            limit(sandbox, "let x = 1 << 4000000000;"),
+            Some((1, Limit::Size(DEFAULT_MAX_SIZE)))
+        );
+        assert_eq!(
+            limit(
+                Sandbox::new().with_max_depth(10),
+                "fun down(n) {\n  return 1 + down(n + 1);\n}\ndown(0);"
+            ),
+            Some((2, Limit::Depth(10)))
+        );
+        assert_eq!(
+            limit(
+                Sandbox::new().with_max_depth(10),
+                "fun forever() {\n  return forever();\n}\nforever();"
+            ),
+            Some((2, Limit::Depth(10)))
+        );
+        assert_eq!(
+            limit(Sandbox::new(), "let t = clock();"),
+            Some((1, Limit::Capability(Capability::Clock)))
+        );
+        let clock = Sandbox::new().with_capability(Capability::Clock);
+        assert_eq!(limit(clock, "let t = clock();"), None);
+    }
+
+    #[test]
+    fn t_fs_and_input() {
+        let interpreter = Interpreter::new();
+        interpreter.set_output(Box::new(io::sink()));
+        interpreter.set_input(Box::new(io::Cursor::new("Ada\n")));
+        let dir = std::env::temp_dir().join(format!("teci-fs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let define = |name: &str, path: &std::path::Path| {
+            let path = path.to_string_lossy();
+            interpreter
+                .environment()
+                .borrow_mut()
+                .define(name, Object::Str(path.as_ref().into()));
+        };
+        define("dir", &dir);
+        define("path", &dir.join("notes.txt"));
+        // Strings have no escapes, so the line breaks are written as they are
+        let source = r#"fs.write_file(path, "one
+");
+fs.append_file(path, "two
+");
+let text = fs.read_file(path);
+let file = fs.open(path);
+let first = file.read_line();
+let rest = "";
+for (line in file) rest = rest + line;
+file.close();
+let entries = "";
+for (entry in fs.dir(dir)) entries = entries + entry;
+let name = input("Name? ");
+let done = input("");"#;
+        assert!(run_source(&interpreter, source).is_ok());
+        assert_eq!(get(&interpreter, "text"), Object::Str("one\ntwo\n".into()));
+        assert_eq!(get(&interpreter, "first"), Object::Str("one".into()));
+        assert_eq!(get(&interpreter, "rest"), Object::Str("two".into()));
+        assert_eq!(
+            get(&interpreter, "entries"),
+            Object::Str("notes.txt".into())
+        );
+        assert_eq!(get(&interpreter, "name"), Object::Str("Ada".into()));
+        assert_eq!(get(&interpreter, "done"), Object::Nil);
+
+        assert!(
+            run_source(
+                &interpreter,
+                "fs.remove(path);\nlet gone = !fs.exists(path);"
+            )
+            .is_ok()
+        );
+        assert_eq!(get(&interpreter, "gone"), Object::Bool(true));
+        // Failures are runtime errors at the call, with the message of the OS
+        match run_source(&interpreter, "file.read_line();\nfs.read_file(path);") {
+            Err(TeciResult::Backtrace { error, .. }) => assert!(matches!(
+                *error,
+                TeciResult::RuntimeError { ref message, .. } if message.ends_with("is closed")
+            )),
+            _ => panic!("expected a closed file"),
+        }
+        match run_source(&interpreter, "fs.read_file(path);") {
+            Err(TeciResult::Backtrace { error, .. }) => assert!(matches!(
+                *error,
+                TeciResult::RuntimeError { ref message, ref token }
+                    if message.contains("No such file or directory") && token.line == 1
+            )),
+            _ => panic!("expected a missing file"),
+        }
+        std::fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn t_integer_operators() {
+        let interpreter = Interpreter::new();
+        let source = "let q = 17 ~/ 5;\nlet r = 17 % 5;\nlet f = 17 / 5 - 3;\nlet x = 17.0 / 5 - 3;\nlet big = 9007199254740993;";
+        assert!(run_source(&interpreter, source).is_ok());
+        assert!(matches!(get(&interpreter, "q"), Object::Int(3)));
+        assert!(matches!(get(&interpreter, "r"), Object::Int(2)));
+        assert_eq!(get(&interpreter, "f").to_string(), "2/5");
+        assert!(matches!(get(&interpreter, "x"), Object::Num(x) if (x - 0.4).abs() < 1e-9));
+        assert!(matches!(
+            get(&interpreter, "big"),
+            Object::Int(9007199254740993)
+        ));
+
+        for source in ["print 1 % 0;", "print 1 ~/ 0;", "print 1 / 0;"] {
+            assert!(matches!(
+                run_source(&interpreter, source),
+                Err(TeciResult::Backtrace { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn t_bignum_literals() {
+        let interpreter = Interpreter::new();
+        let source = "let a = 9223372036854775807 + 1;\nlet b = 100000000000000000000 - 99999999999999999999;\nlet c = 1 / 3 + 2 / 3;";
+        assert!(run_source(&interpreter, source).is_ok());
+        assert_eq!(get(&interpreter, "a").to_string(), "9223372036854775808");
+        assert!(matches!(get(&interpreter, "b"), Object::Int(1)));
+        assert!(matches!(get(&interpreter, "c"), Object::Int(1)));
+    }
+
+    #[test]
+    fn t_operators() {
+        let interpreter = Interpreter::new();
+        let source = "let a = 2 ** 3 ** 2;\nlet b = -2 ** 2;\nlet c = 1 | 6 & 3 ^ 4;\nlet d = 1 << 2 + 1;\nlet e = ~5 >> 1;\nlet i = 10;\ni += 5;\ni -= 1;\ni *= 3;\ni %= 5;\nlet j = i++;\nlet k = --i;\nlet s = \"n\";\ns += 1;";
+        assert!(run_source(&interpreter, source).is_ok());
+        assert!(matches!(get(&interpreter, "a"), Object::Int(512)));
+        assert!(matches!(get(&interpreter, "b"), Object::Int(-4)));
+        assert!(matches!(get(&interpreter, "c"), Object::Int(7)));
+        assert!(matches!(get(&interpreter, "d"), Object::Int(8)));
+        assert!(matches!(get(&interpreter, "e"), Object::Int(-3)));
+        assert!(matches!(get(&interpreter, "j"), Object::Int(2)));
+        assert!(matches!(get(&interpreter, "k"), Object::Int(2)));
+        assert!(matches!(get(&interpreter, "i"), Object::Int(2)));
+        assert_eq!(get(&interpreter, "s"), Object::Str("n1".into()));
+
+        for (source, message) in [
+            (
+                "print \"a\" - 1;",
+                "Unsupported operand types for '-': str and num",
+            ),
+            (
+                "print 1.5 & 1;",
+                "Unsupported operand types for '&': num and num",
+            ),
+            (
+                "print nil < 1;",
+                "Unsupported operand types for '<': nil and num",
+            ),
+            ("print ~true;", "Unsupported operand type for '~': bool"),
+            (
+                "let t = \"a\";\nt++;",
+                "Unsupported operand type for '++': str",
+            ),
+            (
+                "let u = nil;\nu *= 2;",
+                "Unsupported operand types for '*': nil and num",
+            ),
+            ("print 1 << 2 ** 64;", "The result of '<<' is too big"),
+            ("print 1 ~/ 0;", "Division by zero"),
+        ] {
+            match run_source(&interpreter, source) {
+                Err(TeciResult::Backtrace { error, .. }) => match *error {
+                    TeciResult::RuntimeError { message: m, .. } => assert_eq!(m, message),
+                    error => panic!("unexpected error {:?}", error),
+                },
+                result => panic!("unexpected result {:?}", result),
+            }
+        }
+    }
+
+    #[test]
+    fn t_conditional() {
+        let interpreter = Interpreter::new();
+        let source = "fun max(a, b) { return a > b ? a : b; }\nlet a = max(3, 7);\nlet b = 0 ? 1 : nil ? 2 : 3;\nlet c = true ? 1, 2 : 3;\nlet e;\nlet f = false ? e = 1 : 4;";
+        assert!(run_source(&interpreter, source).is_ok());
+        assert!(matches!(get(&interpreter, "a"), Object::Int(7)));
+        assert!(matches!(get(&interpreter, "b"), Object::Int(3)));
+        assert_eq!(
+            get(&interpreter, "c"),
+            Object::Tuple(vec![Object::Int(1), Object::Int(2)].into())
+        );
+        assert!(matches!(get(&interpreter, "e"), Object::Nil));
+        assert!(matches!(get(&interpreter, "f"), Object::Int(4)));
+    }
+
+    #[test]
+    fn t_commas() {
+        let interpreter = Interpreter::new();
+        let source = r#"
+let x = 0;
+let a = 7;
+let grouped = (x = 5, x + 1);
+let bare;
+bare = x = 7, x + 1;
+fun pair() { return x, x + 1; }
+let returned = pair();
+let a = 1, b, c = a;
+let steps = 0;
+for (let i = 0, j = 10; i < j; i++, j--) steps++;
+"#;
+        assert!(run_source(&interpreter, source).is_ok());
+        let pair = |a, b| Object::Tuple(vec![Object::Int(a), Object::Int(b)].into());
+        assert_eq!(get(&interpreter, "grouped"), pair(5, 6));
+        assert_eq!(get(&interpreter, "bare"), Object::Int(7));
+        assert_eq!(get(&interpreter, "returned"), pair(7, 8));
+        assert_eq!(get(&interpreter, "a"), Object::Int(1));
+        assert_eq!(get(&interpreter, "b"), Object::Nil);
+        // Declared together, so `c` gets the `a` from before
+        assert_eq!(get(&interpreter, "c"), Object::Int(7));
+        assert_eq!(get(&interpreter, "steps"), Object::Int(5));
+
+        // Commas in `let` separate names, so a tuple needs parentheses
+        assert!(run_source(&interpreter, "let t = 1, 2;").is_err());
+        assert!(run_source(&interpreter, "let t = (1, 2);").is_ok());
+        assert_eq!(get(&interpreter, "t"), pair(1, 2));
+    }
+
+    #[test]
+    fn t_for_in() {
+        let interpreter = Interpreter::new();
+        let source = r#"
+let word = "";
+for (c in "abc") word = c + word;
+let sum = 0;
+for (i in range(0, 10, 1)) {
+    if (i == 5) break;
+    sum += i;
+}
+fun countdown(n) {
+    fun next() {
+        if (n == 0) return nil;
+        n--;
+        return n + 1;
+    }
+    return next;
+}
+let counted = "";
+for (n in countdown(3)) counted += n;
+let after = 0;
+while (true) {
+    for (i in range(0, 3, 1)) break;
+    after = 1;
+    break;
+}
+"#;
+        assert!(run_source(&interpreter, source).is_ok());
+        assert_eq!(get(&interpreter, "word"), Object::Str("cba".into()));
+        assert!(matches!(get(&interpreter, "sum"), Object::Int(10)));
+        assert_eq!(get(&interpreter, "counted"), Object::Str("321".into()));
+        assert!(matches!(get(&interpreter, "after"), Object::Int(1)));
+
+        for source in [
+            "for (x in 3) print x;",
+            "for (x in range(0, 1, 0)) print x;",
+        ] {
+            assert!(matches!(
+                run_source(&interpreter, source),
+                Err(TeciResult::Backtrace { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn t_generators() {
+        let interpreter = Interpreter::new();
+        let source = r#"
+fun count() {
+    let i = 0;
+    while (true) {
+        yield i;
+        i = i + 1;
+    }
+}
+let gen = count();
+let first = next(gen);
+let second = next(gen);
+let other = next(count());
+
+fun evens(limit) {
+    for (i in range(0, limit, 1)) {
+        if (i % 2 == 1) continue_ = i; else yield i;
+        if (i >= 6) break;
+    }
+    yield "done";
+    return;
+    yield "never";
+}
+let continue_;
+let seen = "";
+for (x in evens(100)) seen += x + " ";
+let e = evens(1);
+next(e);
+next(e);
+let finished = next(e);
+
+fun nested() {
+    fun inner() { yield 1; }
+    yield inner;
+}
+let inner = next(next(nested()));
+"#;
+        assert!(run_source(&interpreter, source).is_ok());
+        assert!(matches!(get(&interpreter, "first"), Object::Int(0)));
+        assert!(matches!(get(&interpreter, "second"), Object::Int(1)));
+        assert!(matches!(get(&interpreter, "other"), Object::Int(0)));
+        assert_eq!(
+            get(&interpreter, "seen"),
+            Object::Str("0 2 4 6 done ".into())
+        );
+        assert!(matches!(get(&interpreter, "finished"), Object::Nil));
+        assert!(matches!(get(&interpreter, "continue_"), Object::Int(5)));
+        assert_eq!(get(&interpreter, "inner").to_string(), "<generator inner>");
+
+        for source in [
+            "fun g() { yield 1 / 0; }\nnext(g());",
+            "fun h() { yield next(s); }\nlet s = h();\nnext(s);",
+            "next(1);",
+        ] {
+            assert!(matches!(
+                run_source(&interpreter, source),
+                Err(TeciResult::Backtrace { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn t_match() {
+        let interpreter = Interpreter::new();
+        let source = r#"
+fun describe(x) {
+    return match x {
+        0 => "zero",
+        1 | 2 => "small",
+        "str" => "a string",
+        nil => "nothing",
+        -5..0 => "negative",
+        n if n > 10 => "big " + n,
+        _ => "other",
+    };
+}
+let results = "";
+for (x in range(-1, 3, 1)) results += describe(x) + ",";
+let a = describe("str");
+let b = describe(nil);
+let c = describe(11);
+let d = describe(7);
+let e = describe(-10);
+let n = "outer";
+let f = match 3 { n => n * 2 } + 1;
+"#;
+        assert!(run_source(&interpreter, source).is_ok());
+        let string = |s: &str| Object::Str(s.into());
+        assert_eq!(
+            get(&interpreter, "results"),
+            string("negative,zero,small,small,")
+        );
+        assert_eq!(get(&interpreter, "a"), string("a string"));
+        assert_eq!(get(&interpreter, "b"), string("nothing"));
+        assert_eq!(get(&interpreter, "c"), string("big 11"));
+        assert_eq!(get(&interpreter, "d"), string("other"));
+        assert_eq!(get(&interpreter, "e"), string("other"));
+        assert_eq!(get(&interpreter, "n"), string("outer"));
+        assert!(matches!(get(&interpreter, "f"), Object::Int(7)));
+
+        match run_source(&interpreter, "print match 3 { 1 => 1, 2 => 2 };") {
+            Err(TeciResult::Backtrace { error, .. }) => match *error {
+                TeciResult::RuntimeError { token, message } => {
+                    assert_eq!(token.ttype, TokenType::Match);
+                    assert_eq!(message, "No pattern matches the value 3");
+                }
+                error => panic!("unexpected error {:?}", error),
+            },
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(run_source(&interpreter, "print match 1 { 1 | n => n };").is_err());
+    }
+
+    #[test]
+    fn t_tuples() {
+        let interpreter = Interpreter::new();
+        let source = r#"
+fun divmod(a, b) {
+    return a ~/ b, a % b;
+}
+let (q, r) = divmod(7, 2);
+let a = 1;
+let b = 2;
+(a, b) = (b, a);
+let (x, (_, y)) = ("x", (nil, (1,)));
+let same = (1, "a") == (1.0, "a");
+let different = (1, 2) == (1, 2, 3);
+let kind = match divmod(9, 3) { (n, 0) => n, _ => -1 };
+"#;
+        assert!(run_source(&interpreter, source).is_ok());
+        assert_eq!(get(&interpreter, "q"), Object::Int(3));
+        assert_eq!(get(&interpreter, "r"), Object::Int(1));
+        assert_eq!(
+            (get(&interpreter, "a"), get(&interpreter, "b")),
+            (Object::Int(2), Object::Int(1))
+        );
+        assert_eq!(get(&interpreter, "x"), Object::Str("x".into()));
+        assert_eq!(
+            get(&interpreter, "y"),
+            Object::Tuple(vec![Object::Int(1)].into())
+        );
+        assert_eq!(get(&interpreter, "same"), Object::Bool(true));
+        assert_eq!(get(&interpreter, "different"), Object::Bool(false));
+        assert_eq!(get(&interpreter, "kind"), Object::Int(3));
+
+        match run_source(&interpreter, "let (c, d) = (1, 2, 3);") {
+            Err(TeciResult::Backtrace { error, .. }) => match *error {
+                TeciResult::RuntimeError { token, message } => {
+                    assert_eq!(token.ttype, TokenType::LeftParen);
+                    assert_eq!(message, "Expected a tuple of 2 values, found 3 values");
+                }
+                error => panic!("unexpected error {:?}", error),
+            },
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(run_source(&interpreter, "(a, b) = 1;").is_err());
+    }
+
+    #[test]
+    fn t_constants() {
+        let interpreter = Interpreter::new();
+        // Assignments the resolver cannot see, like to globals declared after
+        // the function, fail when they run
+        let source = "fun set() { limit = 2; }\nconst limit = 1;\nconst (low, high) = (0, limit);";
+        assert!(run_source(&interpreter, source).is_ok());
+        for (source, message) in [
+            ("set();", "Cannot assign to constant 'limit'"),
+            ("high++;", "Cannot assign to constant 'high'"),
+            ("set = nil;", "Cannot assign to function 'set'"),
+            ("let limit = 3;", "Cannot redeclare constant 'limit'"),
+            (
+                "{ const k = 1;\nlet k = 2; }",
+                "Cannot redeclare constant 'k'",
+            ),
+        ] {
+            match run_source(&interpreter, source) {
+                Err(TeciResult::Backtrace { error, .. }) => match *error {
+                    TeciResult::RuntimeError { message: m, .. } => assert_eq!(m, message),
+                    error => panic!("unexpected error {:?}", error),
+                },
+                result => panic!("unexpected result {:?}", result),
+            }
+        }
+        // A declaration in an inner scope makes a new binding
+        assert!(run_source(&interpreter, "{ let limit = 3;\nlimit = 4; }").is_ok());
+    }
+
+    #[test]
+    fn t_garbage_collection() {
+        let interpreter = Interpreter::new();
+        let source = r#"
+fun make_counter() {
+    let i = 0;
+    fun count() {
+        i += 1;
+        return i;
+    }
+    return count;
+}
+for (let n = 0; n < 10; n++) {
+    make_counter()();
+}
+let kept = make_counter();
+kept();
+let freed = gc();
+let count = kept();
+"#;
+        assert!(run_source(&interpreter, source).is_ok());
+        // The counters thrown away are only kept alive by their cycles
+        assert_eq!(get(&interpreter, "freed"), Object::Int(10));
+        assert_eq!(get(&interpreter, "count"), Object::Int(2));
+        let stats = interpreter.heap_stats();
+        assert_eq!((stats.collections, stats.freed), (1, 10));
+        // The globals and the closure of `kept`
+        assert_eq!(stats.tracked, 2);
+    }
+
+    #[test]
+    fn t_profiler() {
+        let interpreter = Interpreter::new();
+        interpreter.set_output(Box::new(io::sink()));
+        interpreter.enable_profiler();
+        let source = "fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }\nprint fib(5);\nclock();\nfun a() {\n  fun helper() { return 1; }\n  return helper();\n}\nfun b() {\n  fun helper() { return 2; }\n  return helper() + helper();\n}\na();\nb();";
+        assert!(run_source(&interpreter, source).is_ok());
+        let profiler = interpreter.profiler.borrow();
+        let functions = profiler.as_ref().unwrap().functions();
+        let calls: Vec<_> = functions.iter().map(|(name, p)| (*name, p.calls)).collect();
+        assert!(calls.contains(&("<fun fib> (line 1)", 15)));
+        assert!(calls.contains(&("<fun native::clock>", 1)));
+        // Functions with the same name are counted apart
+        assert!(calls.contains(&("<fun helper> (line 5)", 1)));
+        assert!(calls.contains(&("<fun helper> (line 9)", 2)));
+    }
 }
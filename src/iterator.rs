@@ -0,0 +1,150 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use crate::{
+    callable::{Callable, TeciCallable},
+    error::TeciResult,
+    interpreter::Interpreter,
+    object::Object,
+};
+
+// An iterator is a function without parameters that returns the next value
+// each time it is called, and nil once there are no more. `for-in` loops take
+// one, or a value that can give one.
+pub fn iterator(value: &Object) -> Option<Callable> {
+    match value {
+        Object::Func(function) if function.arity() == 0 => Some(function.clone()),
+        Object::Native(native) => native.object.iterator(),
+        Object::Str(s) => Some(Callable {
+            func: Rc::new(StringIterator {
+                chars: s.chars().collect(),
+                next: Cell::new(0),
+            }),
+        }),
+        _ => None,
+    }
+}
+
+// The characters of a string, as strings
+pub struct StringIterator {
+    chars: Vec<char>,
+    next: Cell<usize>,
+}
+
+impl TeciCallable for StringIterator {
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn call(&self, _interpreter: &Interpreter, _args: Vec<Object>) -> Result<Object, TeciResult> {
+        let i = self.next.get();
+        match self.chars.get(i) {
+            Some(c) => {
+                self.next.set(i + 1);
+                Ok(Object::Str(c.to_string().into()))
+            }
+            None => Ok(Object::Nil),
+        }
+    }
+
+    fn name(&self) -> String {
+        "string iterator".to_string()
+    }
+
+    fn to_string(&self) -> String {
+        "<fun native::string iterator>".to_string()
+    }
+}
+
+// The numbers from `start` up to (or down to, for a negative step) `end`,
+// not included
+pub struct RangeIterator {
+    next: RefCell<Object>,
+    end: Object,
+    step: Object,
+}
+
+impl RangeIterator {
+    pub fn new(start: Object, end: Object, step: Object) -> Self {
+        Self {
+            next: RefCell::new(start),
+            end,
+            step,
+        }
+    }
+}
+
+impl TeciCallable for RangeIterator {
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn call(&self, _interpreter: &Interpreter, _args: Vec<Object>) -> Result<Object, TeciResult> {
+        let current = self.next.borrow().clone();
+        let done = if self.step > Object::Int(0) {
+            current >= self.end
+        } else {
+            current <= self.end
+        };
+        if done {
+            return Ok(Object::Nil);
+        }
+        let next = (current.clone() + self.step.clone())
+            .map_err(|error| TeciResult::system_error(&error.message("+")))?;
+        self.next.replace(next);
+        Ok(current)
+    }
+
+    fn name(&self) -> String {
+        "range iterator".to_string()
+    }
+
+    fn to_string(&self) -> String {
+        "<fun native::range iterator>".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(iterable: &Object) -> Vec<Object> {
+        let interpreter = Interpreter::new();
+        let next = iterator(iterable).unwrap();
+        std::iter::from_fn(|| match next.call(&interpreter, Vec::new()).unwrap() {
+            Object::Nil => None,
+            value => Some(value),
+        })
+        .collect()
+    }
+
+    #[test]
+    fn t_iterators() {
+        let s = |s: &str| Object::Str(s.into());
+        assert_eq!(collect(&s("año")), vec![s("a"), s("ñ"), s("o")]);
+        assert!(collect(&s("")).is_empty());
+
+        let range = |start, end, step| {
+            Object::Func(Callable {
+                func: Rc::new(RangeIterator::new(start, end, step)),
+            })
+        };
+        let ints = |values: &[i64]| values.iter().map(|i| Object::Int(*i)).collect::<Vec<_>>();
+        assert_eq!(
+            collect(&range(Object::Int(0), Object::Int(5), Object::Int(2))),
+            ints(&[0, 2, 4])
+        );
+        assert_eq!(
+            collect(&range(Object::Int(3), Object::Int(0), Object::Int(-1))),
+            ints(&[3, 2, 1])
+        );
+        assert!(collect(&range(Object::Int(3), Object::Int(0), Object::Int(1))).is_empty());
+        assert_eq!(
+            collect(&range(Object::Num(0.0), Object::Int(1), Object::Num(0.25))).len(),
+            4
+        );
+        assert!(iterator(&Object::Int(1)).is_none());
+    }
+}
@@ -0,0 +1,363 @@
+use std::{
+    fmt,
+    io::{BufRead, Write},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = JsonParser {
+            chars: text.chars().collect(),
+            current: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.current < parser.chars.len() {
+            return Err(format!("Unexpected trailing input at {}", parser.current));
+        }
+
+        Ok(value)
+    }
+
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+
+    pub fn str(s: &str) -> Json {
+        Json::String(s.to_string())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Json::Number(x) if *x >= 0.0 && x.fract() == 0.0 => Some(*x as usize),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+// Reads one message framed by a `Content-Length` header, as used by both the
+// Debug Adapter and the Language Server protocols
+pub fn read_framed<R: BufRead>(input: &mut R) -> Option<Json> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.trim().eq_ignore_ascii_case("Content-Length")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let mut body = vec![0; length?];
+    input.read_exact(&mut body).ok()?;
+    Json::parse(&String::from_utf8_lossy(&body)).ok()
+}
+
+pub fn write_framed<W: Write>(output: &mut W, message: &Json) {
+    let body = message.to_string();
+    let _ = write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body);
+    let _ = output.flush();
+}
+
+fn write_escaped(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(x) if x.is_finite() => write!(f, "{}", x),
+            Json::Number(_) => write!(f, "null"),
+            Json::String(s) => write_escaped(f, s),
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_escaped(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+struct JsonParser {
+    chars: Vec<char>,
+    current: usize,
+}
+
+impl JsonParser {
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('n') => self.keyword("null", Json::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) => Err(format!("Unexpected character '{}' at {}", c, self.current)),
+            None => Err("Unexpected end of input".to_string()),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.current += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.advance() {
+                Some(',') => {}
+                Some('}') => return Ok(Json::Object(fields)),
+                _ => return Err(format!("Expected ',' or '}}' at {}", self.current)),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.current += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.advance() {
+                Some(',') => {}
+                Some(']') => return Ok(Json::Array(items)),
+                _ => return Err(format!("Expected ',' or ']' at {}", self.current)),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.advance() {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.advance() {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('/') => s.push('/'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('u') => {
+                        let mut code = self.hex4()?;
+                        // Characters outside the BMP come as a surrogate pair
+                        if (0xD800..0xDC00).contains(&code) {
+                            self.expect('\\')?;
+                            self.expect('u')?;
+                            let low = self.hex4()?;
+                            if !(0xDC00..0xE000).contains(&low) {
+                                return Err(format!("Invalid surrogate pair at {}", self.current));
+                            }
+                            code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                        }
+                        s.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+                    }
+                    _ => return Err(format!("Invalid escape at {}", self.current)),
+                },
+                Some(c) => s.push(c),
+                None => return Err("Unterminated string".to_string()),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .advance()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| format!("Invalid unicode escape at {}", self.current))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.current;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E') {
+                self.current += 1;
+            } else {
+                break;
+            }
+        }
+        let text: String = self.chars[start..self.current].iter().collect();
+        text.parse()
+            .map(Json::Number)
+            .map_err(|_| format!("Invalid number '{}'", text))
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        for expected in word.chars() {
+            if self.advance() != Some(expected) {
+                return Err(format!("Expected '{}' at {}", word, self.current));
+            }
+        }
+        Ok(value)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        if self.advance() == Some(expected) {
+            Ok(())
+        } else {
+            Err(format!("Expected '{}' at {}", expected, self.current))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.current += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.current).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.current += 1;
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_parse_message() {
+        let json = Json::parse(
+            r#"{"seq": 1, "type": "request", "arguments": {"lines": [1, 2.5], "ok": true, "x": null}}"#,
+        )
+        .unwrap();
+        assert_eq!(json.get("seq").and_then(Json::as_usize), Some(1));
+        assert_eq!(json.get("type").and_then(Json::as_str), Some("request"));
+        let arguments = json.get("arguments").unwrap();
+        assert_eq!(
+            arguments.get("lines").and_then(Json::as_array).unwrap(),
+            &[Json::Number(1.0), Json::Number(2.5)]
+        );
+        assert_eq!(arguments.get("ok").and_then(Json::as_bool), Some(true));
+        assert_eq!(arguments.get("x"), Some(&Json::Null));
+        assert!(Json::parse("{\"a\": 1,}").is_err());
+    }
+
+    #[test]
+    fn t_string_escapes_round_trip() {
+        let text = "say \"hi\"\n\ttab \\ \u{1} ñ 😀";
+        let json = Json::object(vec![("text", Json::str(text))]);
+        let parsed = Json::parse(&json.to_string()).unwrap();
+        assert_eq!(parsed.get("text").and_then(Json::as_str), Some(text));
+        assert_eq!(Json::parse(r#""\ud83d\ude00""#).unwrap(), Json::str("😀"));
+    }
+}
@@ -0,0 +1,36 @@
+pub mod bignum;
+pub mod call_stack;
+pub mod callable;
+pub mod dap;
+pub mod debugger;
+pub mod dialect;
+pub mod envirnoment;
+pub mod error;
+pub mod expr;
+pub mod fs;
+pub mod gc;
+pub mod generator;
+pub mod interpreter;
+pub mod iterator;
+pub mod json;
+pub mod lsp;
+pub mod native_functions;
+pub mod native_object;
+pub mod object;
+pub mod optimizer;
+pub mod parser;
+pub mod pattern;
+pub mod pretty_printer;
+pub mod profiler;
+pub mod resolver;
+pub mod sandbox;
+pub mod scanner;
+pub mod span;
+pub mod stmt;
+pub mod symbol;
+pub mod syntax;
+pub mod teci_function;
+pub mod token;
+pub mod token_type;
+pub mod type_checker;
+pub mod types;
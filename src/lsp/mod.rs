@@ -0,0 +1,743 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+
+use crate::{
+    error::TeciResult,
+    interpreter::Interpreter,
+    json::{self, Json},
+    resolver::{Definition, DefinitionKind, Resolution, Resolver},
+    span::{Position, Span},
+    syntax::SyntaxTree,
+    token::Token,
+};
+
+// JSON-RPC error code for requests the server does not implement
+const METHOD_NOT_FOUND: f64 = -32601.0;
+
+// LSP kinds used in the responses
+const SYMBOL_FUNCTION: usize = 12;
+const SYMBOL_VARIABLE: usize = 13;
+const SYMBOL_CONSTANT: usize = 14;
+const COMPLETION_FUNCTION: usize = 3;
+const COMPLETION_VARIABLE: usize = 6;
+const COMPLETION_KEYWORD: usize = 14;
+const COMPLETION_CONSTANT: usize = 21;
+const SEVERITY_ERROR: usize = 1;
+
+// How LSP positions count the characters in a line, agreed on in `initialize`.
+// Teci columns count `char`s, which is what UTF-32 does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    Utf16,
+    Utf32,
+}
+
+impl Encoding {
+    // The first encoding the client offers that the server knows, or else
+    // UTF-16, which every client supports
+    fn negotiate(message: &Json) -> Self {
+        param(message, &["capabilities", "general", "positionEncodings"])
+            .and_then(Json::as_array)
+            .into_iter()
+            .flatten()
+            .find_map(|encoding| match encoding.as_str()? {
+                "utf-16" => Some(Encoding::Utf16),
+                "utf-32" => Some(Encoding::Utf32),
+                _ => None,
+            })
+            .unwrap_or(Encoding::Utf16)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf16 => "utf-16",
+            Encoding::Utf32 => "utf-32",
+        }
+    }
+
+    // The LSP character for the column `column` of `line`
+    fn character(self, line: &str, column: usize) -> usize {
+        match self {
+            Encoding::Utf16 => line.chars().take(column).map(char::len_utf16).sum(),
+            Encoding::Utf32 => column,
+        }
+    }
+
+    // The column for the LSP character `character` of `line`
+    fn column(self, line: &str, character: usize) -> usize {
+        match self {
+            Encoding::Utf16 => {
+                let (mut units, mut column) = (0, 0);
+                for c in line.chars() {
+                    if units >= character {
+                        break;
+                    }
+                    units += c.len_utf16();
+                    column += 1;
+                }
+                column + character.saturating_sub(units)
+            }
+            Encoding::Utf32 => character,
+        }
+    }
+}
+
+// What the server knows about an open file, recomputed on every change
+struct Document {
+    tree: SyntaxTree,
+    resolution: Resolution,
+    errors: Vec<TeciResult>,
+    // The text of each line, to convert columns to LSP positions
+    lines: Vec<String>,
+    encoding: Encoding,
+}
+
+impl Document {
+    fn analyze(tree: SyntaxTree, natives: &[String], encoding: Encoding) -> Self {
+        let mut errors = tree.errors();
+        let (statements, parse_errors) = tree.statements();
+        errors.extend(parse_errors);
+        let resolution = Resolver::new(natives.to_vec()).resolve_unreported(&statements);
+        let lines = tree.text().split('\n').map(str::to_string).collect();
+
+        Self {
+            tree,
+            resolution,
+            errors,
+            lines,
+            encoding,
+        }
+    }
+
+    // LSP counts lines from 0, teci from 1
+    fn position(&self, position: Position) -> Json {
+        let character = match self.lines.get(position.line.saturating_sub(1)) {
+            Some(line) => self.encoding.character(line, position.column),
+            None => position.column,
+        };
+        Json::object(vec![
+            ("line", position.line.saturating_sub(1).into()),
+            ("character", character.into()),
+        ])
+    }
+
+    fn range(&self, span: Span) -> Json {
+        Json::object(vec![
+            ("start", self.position(span.start)),
+            ("end", self.position(span.end)),
+        ])
+    }
+
+    fn location(&self, uri: &str, token: &Token) -> Json {
+        Json::object(vec![
+            ("uri", Json::str(uri)),
+            ("range", self.range(Span::of(token))),
+        ])
+    }
+
+    // The position of the cursor in a request
+    fn cursor(&self, message: &Json) -> Option<Position> {
+        let position = param(message, &["position"])?;
+        let line = position.get("line")?.as_usize()?;
+        let character = position.get("character")?.as_usize()?;
+        let column = match self.lines.get(line) {
+            Some(text) => self.encoding.column(text, character),
+            None => character,
+        };
+        Some(Position {
+            line: line + 1,
+            column,
+        })
+    }
+
+    // The definition of the name under the cursor, whether the cursor is on a
+    // use of the name or on the definition itself
+    fn definition_at(&self, position: Position) -> Option<usize> {
+        let resolution = &self.resolution;
+        resolution
+            .references
+            .iter()
+            .find(|(token, _)| Span::of(token).contains(position))
+            .map(|(_, definition)| *definition)
+            .or_else(|| {
+                resolution
+                    .definitions
+                    .iter()
+                    .position(|d| Span::of(&d.name).contains(position))
+            })
+    }
+
+    fn diagnostics(&self) -> Vec<Json> {
+        let mut diagnostics = Vec::new();
+        for error in self.errors.iter().chain(&self.resolution.errors) {
+            let (range, message) = match error {
+                TeciResult::ParseError { token, message }
+                | TeciResult::ResolveError { token, message }
+                | TeciResult::TypeError { token, message } => {
+                    (self.range(Span::of(token)), message)
+                }
+                TeciResult::TeciError { line, message } => {
+                    let start = Position {
+                        line: *line,
+                        column: 0,
+                    };
+                    let end = Position {
+                        line: line + 1,
+                        column: 0,
+                    };
+                    (self.range(Span { start, end }), message)
+                }
+                _ => continue,
+            };
+            diagnostics.push(Json::object(vec![
+                ("range", range),
+                ("severity", SEVERITY_ERROR.into()),
+                ("source", Json::str("teci")),
+                ("message", Json::str(message)),
+            ]));
+        }
+        diagnostics
+    }
+
+    fn symbols(&self, parent: Option<usize>) -> Vec<Json> {
+        self.resolution
+            .definitions
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| d.parent == parent && d.kind != DefinitionKind::Parameter)
+            .map(|(i, d)| {
+                let kind = match d.kind {
+                    DefinitionKind::Function => SYMBOL_FUNCTION,
+                    DefinitionKind::Constant => SYMBOL_CONSTANT,
+                    _ => SYMBOL_VARIABLE,
+                };
+                Json::object(vec![
+                    ("name", Json::str(&d.name.lexeme)),
+                    ("kind", kind.into()),
+                    ("range", self.range(d.span)),
+                    ("selectionRange", self.range(Span::of(&d.name))),
+                    ("children", Json::Array(self.symbols(Some(i)))),
+                ])
+            })
+            .collect()
+    }
+
+    fn hover(&self, definition: &Definition) -> String {
+        match definition.kind {
+            DefinitionKind::Function => format!("fun {}({})", definition.name.lexeme, {
+                let params: Vec<&str> = definition
+                    .params
+                    .iter()
+                    .map(|p| p.lexeme.as_str())
+                    .collect();
+                params.join(", ")
+            }),
+            DefinitionKind::Variable => format!("let {}", definition.name.lexeme),
+            DefinitionKind::Constant => format!("const {}", definition.name.lexeme),
+            DefinitionKind::Parameter => match definition.parent {
+                Some(function) => format!(
+                    "{} (parameter of {})",
+                    definition.name.lexeme, self.resolution.definitions[function].name.lexeme
+                ),
+                None => definition.name.lexeme.to_string(),
+            },
+        }
+    }
+
+    // Names in scope at the cursor: globals, plus the definitions of every
+    // function the cursor is in
+    fn completions(&self, position: Position, natives: &[String]) -> Vec<Json> {
+        let definitions = &self.resolution.definitions;
+        let visible = |d: &Definition| match d.parent {
+            None => true,
+            Some(function) => definitions[function].span.contains(position),
+        };
+
+        let mut items = Vec::new();
+        let mut seen = Vec::new();
+        for definition in definitions.iter().rev().filter(|d| visible(d)) {
+            let name = &definition.name.lexeme;
+            if seen.contains(name) {
+                continue;
+            }
+            seen.push(name.clone());
+            let kind = match definition.kind {
+                DefinitionKind::Function => COMPLETION_FUNCTION,
+                DefinitionKind::Constant => COMPLETION_CONSTANT,
+                _ => COMPLETION_VARIABLE,
+            };
+            items.push(completion(name, kind, Some(self.hover(definition))));
+        }
+        for native in natives
+            .iter()
+            .filter(|n| !seen.iter().any(|s| s == n.as_str()))
+        {
+            items.push(completion(native, COMPLETION_FUNCTION, None));
+        }
+        for keyword in self.tree.dialect().keywords() {
+            items.push(completion(keyword, COMPLETION_KEYWORD, None));
+        }
+        items
+    }
+}
+
+fn completion(label: &str, kind: usize, detail: Option<String>) -> Json {
+    let mut fields = vec![("label", Json::str(label)), ("kind", kind.into())];
+    if let Some(detail) = detail {
+        fields.push(("detail", Json::String(detail)));
+    }
+    Json::object(fields)
+}
+
+fn param<'a>(message: &'a Json, path: &[&str]) -> Option<&'a Json> {
+    path.iter()
+        .try_fold(message.get("params")?, |json, key| json.get(key))
+}
+
+// The character offset of an LSP position in the source
+fn offset(source: &str, position: &Json, encoding: Encoding) -> Option<usize> {
+    let line = position.get("line")?.as_usize()?;
+    let character = position.get("character")?.as_usize()?;
+    let mut offset = 0;
+    for (i, text) in source.split('\n').enumerate() {
+        if i == line {
+            let column = encoding.column(text, character);
+            return Some(offset + column.min(text.chars().count()));
+        }
+        offset += text.chars().count() + 1;
+    }
+    None
+}
+
+fn capabilities(encoding: Encoding) -> Json {
+    Json::object(vec![
+        (
+            "capabilities",
+            Json::object(vec![
+                ("positionEncoding", Json::str(encoding.name())),
+                // Incremental document sync
+                ("textDocumentSync", 2.into()),
+                ("definitionProvider", true.into()),
+                ("referencesProvider", true.into()),
+                ("hoverProvider", true.into()),
+                ("documentSymbolProvider", true.into()),
+                ("completionProvider", Json::object(vec![])),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Json::object(vec![("name", Json::str("teci-lsp"))]),
+        ),
+    ])
+}
+
+struct Server<W: Write> {
+    output: W,
+    documents: HashMap<String, Document>,
+    natives: Vec<String>,
+    encoding: Encoding,
+}
+
+impl<W: Write> Server<W> {
+    fn send(&mut self, mut fields: Vec<(&str, Json)>) {
+        fields.insert(0, ("jsonrpc", Json::str("2.0")));
+        json::write_framed(&mut self.output, &Json::object(fields));
+    }
+
+    fn respond(&mut self, request: &Json, result: Json) {
+        let id = request.get("id").cloned().unwrap_or(Json::Null);
+        self.send(vec![("id", id), ("result", result)]);
+    }
+
+    fn notify(&mut self, method: &str, params: Json) {
+        self.send(vec![("method", Json::str(method)), ("params", params)]);
+    }
+
+    fn open(&mut self, uri: &str, tree: SyntaxTree) {
+        let document = Document::analyze(tree, &self.natives, self.encoding);
+        let diagnostics = document.diagnostics();
+        self.documents.insert(uri.to_string(), document);
+        self.notify(
+            "textDocument/publishDiagnostics",
+            Json::object(vec![
+                ("uri", Json::str(uri)),
+                ("diagnostics", Json::Array(diagnostics)),
+            ]),
+        );
+    }
+
+    fn change(&mut self, uri: &str, changes: &[Json]) {
+        let Some(document) = self.documents.get(uri) else {
+            return;
+        };
+        let mut tree = document.tree.clone();
+        for change in changes {
+            let Some(text) = change.get("text").and_then(Json::as_str) else {
+                continue;
+            };
+            tree = match change.get("range") {
+                Some(range) => {
+                    let source = tree.text();
+                    let to_offset = |p| offset(&source, p, self.encoding);
+                    let start = range.get("start").and_then(to_offset);
+                    let end = range.get("end").and_then(to_offset);
+                    match start.zip(end) {
+                        Some((start, end)) if start <= end => tree.edit(start, end, text),
+                        _ => continue,
+                    }
+                }
+                None => SyntaxTree::parse(text),
+            };
+        }
+        self.open(uri, tree);
+    }
+
+    fn handle_notification(&mut self, method: &str, message: &Json) {
+        let uri = param(message, &["textDocument", "uri"])
+            .and_then(Json::as_str)
+            .unwrap_or("")
+            .to_string();
+        match method {
+            "textDocument/didOpen" => {
+                if let Some(text) = param(message, &["textDocument", "text"]).and_then(Json::as_str)
+                {
+                    self.open(&uri, SyntaxTree::parse(text));
+                }
+            }
+            "textDocument/didChange" => {
+                if let Some(changes) = param(message, &["contentChanges"]).and_then(Json::as_array)
+                {
+                    self.change(&uri, changes);
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+            }
+            _ => {}
+        }
+    }
+
+    // Answers a request about an open document, or None if the method is not
+    // one of them
+    fn document_request(&self, method: &str, message: &Json) -> Option<Json> {
+        let uri = param(message, &["textDocument", "uri"])
+            .and_then(Json::as_str)
+            .unwrap_or("");
+        let document = self.documents.get(uri);
+        let definitions = document
+            .map(|d| &d.resolution.definitions[..])
+            .unwrap_or(&[]);
+        let cursor = document.and_then(|d| d.cursor(message));
+        let target = document.zip(cursor).and_then(|(d, p)| d.definition_at(p));
+
+        let result = match method {
+            "textDocument/definition" => match document.zip(target) {
+                Some((document, i)) => document.location(uri, &definitions[i].name),
+                None => Json::Null,
+            },
+            "textDocument/references" => {
+                let Some((document, target)) = document.zip(target) else {
+                    return Some(Json::Array(Vec::new()));
+                };
+                let include_declaration = param(message, &["context", "includeDeclaration"])
+                    .and_then(Json::as_bool)
+                    .unwrap_or(true);
+                let mut locations = Vec::new();
+                if include_declaration {
+                    locations.push(document.location(uri, &definitions[target].name));
+                }
+                for (token, definition) in &document.resolution.references {
+                    if *definition == target {
+                        locations.push(document.location(uri, token));
+                    }
+                }
+                Json::Array(locations)
+            }
+            "textDocument/hover" => match document.zip(target) {
+                Some((document, i)) => Json::object(vec![(
+                    "contents",
+                    Json::object(vec![
+                        ("kind", Json::str("markdown")),
+                        (
+                            "value",
+                            Json::String(format!(
+                                "```teci\n{}\n```",
+                                document.hover(&definitions[i])
+                            )),
+                        ),
+                    ]),
+                )]),
+                None => Json::Null,
+            },
+            "textDocument/documentSymbol" => {
+                Json::Array(document.map(|d| d.symbols(None)).unwrap_or_default())
+            }
+            "textDocument/completion" => {
+                let position = cursor.unwrap_or(Position { line: 1, column: 0 });
+                let items = match document {
+                    Some(document) => document.completions(position, &self.natives),
+                    None => Vec::new(),
+                };
+                Json::Array(items)
+            }
+            _ => return None,
+        };
+        Some(result)
+    }
+}
+
+pub fn serve<R: BufRead, W: Write>(mut input: R, output: W) {
+    let natives = Interpreter::new()
+        ._globals
+        .borrow()
+        .bindings()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    let mut server = Server {
+        output,
+        documents: HashMap::new(),
+        natives,
+        encoding: Encoding::Utf16,
+    };
+
+    while let Some(message) = json::read_framed(&mut input) {
+        let method = message
+            .get("method")
+            .and_then(Json::as_str)
+            .unwrap_or("")
+            .to_string();
+        if message.get("id").is_none() {
+            if method == "exit" {
+                return;
+            }
+            server.handle_notification(&method, &message);
+            continue;
+        }
+
+        match method.as_str() {
+            "initialize" => {
+                server.encoding = Encoding::negotiate(&message);
+                server.respond(&message, capabilities(server.encoding));
+            }
+            "shutdown" => server.respond(&message, Json::Null),
+            _ => match server.document_request(&method, &message) {
+                Some(result) => server.respond(&message, result),
+                None => {
+                    let id = message.get("id").cloned().unwrap_or(Json::Null);
+                    let error = Json::object(vec![
+                        ("code", Json::Number(METHOD_NOT_FOUND)),
+                        (
+                            "message",
+                            Json::String(format!("Unknown method '{}'", method)),
+                        ),
+                    ]);
+                    server.send(vec![("id", id), ("error", error)]);
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const PROGRAM: &str = r#"fun add(a, b) {\n  let sum = a + b;\n  return sum;\n}\nlet x = add(1, 2);\nprint x + y;\n"#;
+
+    // Requests recorded from an editor that opens a file with an undefined
+    // variable, navigates around it, fixes it and shuts the server down
+    const SESSION: &[&str] = &[
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"processId":null,"rootUri":null,"capabilities":{}}}"#,
+        r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///a.tc","languageId":"teci","version":1,"text":"PROGRAM"}}}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/definition","params":{"textDocument":{"uri":"file:///a.tc"},"position":{"line":4,"character":9}}}"#,
+        r#"{"jsonrpc":"2.0","id":3,"method":"textDocument/references","params":{"textDocument":{"uri":"file:///a.tc"},"position":{"line":1,"character":12},"context":{"includeDeclaration":true}}}"#,
+        r#"{"jsonrpc":"2.0","id":4,"method":"textDocument/hover","params":{"textDocument":{"uri":"file:///a.tc"},"position":{"line":4,"character":8}}}"#,
+        r#"{"jsonrpc":"2.0","id":5,"method":"textDocument/documentSymbol","params":{"textDocument":{"uri":"file:///a.tc"}}}"#,
+        r#"{"jsonrpc":"2.0","id":6,"method":"textDocument/completion","params":{"textDocument":{"uri":"file:///a.tc"},"position":{"line":2,"character":9}}}"#,
+        r#"{"jsonrpc":"2.0","id":7,"method":"textDocument/formatting","params":{"textDocument":{"uri":"file:///a.tc"}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///a.tc","version":2},"contentChanges":[{"range":{"start":{"line":5,"character":10},"end":{"line":5,"character":11}},"text":"x"},{"range":{"start":{"line":0,"character":0},"end":{"line":0,"character":0}},"text":"// Adds\n"}]}}"#,
+        r#"{"jsonrpc":"2.0","id":8,"method":"shutdown"}"#,
+        r#"{"jsonrpc":"2.0","method":"exit"}"#,
+        r#"{"jsonrpc":"2.0","id":9,"method":"shutdown"}"#,
+    ];
+
+    fn replay(session: &[&str]) -> Vec<Json> {
+        let input: String = session
+            .iter()
+            .map(|m| {
+                let m = m.replace("PROGRAM", PROGRAM);
+                format!("Content-Length: {}\r\n\r\n{}", m.len(), m)
+            })
+            .collect();
+        let mut output = Vec::new();
+        serve(Cursor::new(input.into_bytes()), &mut output);
+
+        let mut output = Cursor::new(output);
+        std::iter::from_fn(|| json::read_framed(&mut output)).collect()
+    }
+
+    fn result(messages: &[Json], id: usize) -> &Json {
+        messages
+            .iter()
+            .find(|m| m.get("id").and_then(Json::as_usize) == Some(id))
+            .unwrap_or_else(|| panic!("no response to request {}", id))
+    }
+
+    #[test]
+    fn t_editor_session() {
+        let messages = replay(SESSION);
+
+        let capabilities = result(&messages, 1).get("result").unwrap();
+        assert!(capabilities.get("capabilities").is_some());
+
+        let diagnostics: Vec<String> = messages
+            .iter()
+            .filter(|m| {
+                m.get("method").and_then(Json::as_str) == Some("textDocument/publishDiagnostics")
+            })
+            .map(|m| {
+                m.get("params")
+                    .unwrap()
+                    .get("diagnostics")
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                r#"[{"range":{"start":{"line":5,"character":10},"end":{"line":5,"character":11}},"severity":1,"source":"teci","message":"Undefined variable 'y'"}]"#,
+                "[]",
+            ]
+        );
+
+        assert_eq!(
+            result(&messages, 2).get("result").unwrap().to_string(),
+            r#"{"uri":"file:///a.tc","range":{"start":{"line":0,"character":4},"end":{"line":0,"character":7}}}"#
+        );
+
+        // `a` in `a + b`: the parameter and its single use
+        let references = result(&messages, 3).get("result").unwrap();
+        let lines: Vec<String> = references
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|l| l.get("range").unwrap().get("start").unwrap().to_string())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                r#"{"line":0,"character":8}"#,
+                r#"{"line":1,"character":12}"#
+            ]
+        );
+
+        let hover = result(&messages, 4).get("result").unwrap();
+        assert_eq!(
+            hover
+                .get("contents")
+                .unwrap()
+                .get("value")
+                .and_then(Json::as_str),
+            Some("```teci\nfun add(a, b)\n```")
+        );
+
+        let symbols = result(&messages, 5).get("result").unwrap();
+        assert_eq!(
+            symbols.to_string(),
+            concat!(
+                r#"[{"name":"add","kind":12,"#,
+                r#""range":{"start":{"line":0,"character":4},"end":{"line":2,"character":12}},"#,
+                r#""selectionRange":{"start":{"line":0,"character":4},"end":{"line":0,"character":7}},"#,
+                r#""children":[{"name":"sum","kind":13,"#,
+                r#""range":{"start":{"line":1,"character":6},"end":{"line":1,"character":17}},"#,
+                r#""selectionRange":{"start":{"line":1,"character":6},"end":{"line":1,"character":9}},"#,
+                r#""children":[]}]},"#,
+                r#"{"name":"x","kind":13,"#,
+                r#""range":{"start":{"line":4,"character":4},"end":{"line":4,"character":17}},"#,
+                r#""selectionRange":{"start":{"line":4,"character":4},"end":{"line":4,"character":5}},"#,
+                r#""children":[]}]"#
+            )
+        );
+
+        let labels: Vec<&str> = result(&messages, 6)
+            .get("result")
+            .and_then(Json::as_array)
+            .unwrap()
+            .iter()
+            .filter_map(|c| c.get("label").and_then(Json::as_str))
+            .collect();
+        for label in ["x", "sum", "b", "a", "add", "clock", "while"] {
+            assert!(labels.contains(&label), "missing completion {}", label);
+        }
+
+        let error = result(&messages, 7).get("error").unwrap();
+        assert_eq!(error.get("code"), Some(&Json::Number(METHOD_NOT_FOUND)));
+
+        assert_eq!(result(&messages, 8).get("result"), Some(&Json::Null));
+        // Nothing is answered after `exit`
+        assert!(
+            messages
+                .iter()
+                .all(|m| m.get("id").and_then(Json::as_usize) != Some(9))
+        );
+    }
+
+    #[test]
+    fn t_position_encodings() {
+        // The emoji takes two UTF-16 code units, and one UTF-32 one
+        let session = |capabilities: &str, character: usize| {
+            let initialize = format!(
+                r#"{{"jsonrpc":"2.0","id":1,"method":"initialize","params":{{"capabilities":{}}}}}"#,
+                capabilities
+            );
+            let definition = format!(
+                r#"{{"jsonrpc":"2.0","id":2,"method":"textDocument/definition","params":{{"textDocument":{{"uri":"file:///a.tc"}},"position":{{"line":0,"character":{}}}}}}}"#,
+                character
+            );
+            let messages = replay(&[
+                &initialize,
+                r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///a.tc","text":"let s = \"😀\"; print s + y;"}}}"#,
+                &definition,
+            ]);
+            let encoding = result(&messages, 1)
+                .get("result")
+                .and_then(|r| r.get("capabilities"))
+                .and_then(|c| c.get("positionEncoding"))
+                .and_then(Json::as_str)
+                .unwrap()
+                .to_string();
+            let diagnostic = messages
+                .iter()
+                .find_map(|m| m.get("params")?.get("diagnostics")?.as_array()?.first())
+                .and_then(|d| d.get("range"))
+                .unwrap()
+                .to_string();
+            let definition = result(&messages, 2).get("result").unwrap().clone();
+            (encoding, diagnostic, definition)
+        };
+
+        let (encoding, diagnostic, definition) = session("{}", 20);
+        assert_eq!(encoding, "utf-16");
+        assert_eq!(
+            diagnostic,
+            r#"{"start":{"line":0,"character":24},"end":{"line":0,"character":25}}"#
+        );
+        assert_ne!(definition, Json::Null);
+
+        let utf32 = r#"{"general":{"positionEncodings":["utf-8","utf-32","utf-16"]}}"#;
+        let (encoding, diagnostic, definition) = session(utf32, 19);
+        assert_eq!(encoding, "utf-32");
+        assert_eq!(
+            diagnostic,
+            r#"{"start":{"line":0,"character":23},"end":{"line":0,"character":24}}"#
+        );
+        assert_ne!(definition, Json::Null);
+    }
+}
@@ -1,59 +1,249 @@
-mod callable;
-mod envirnoment;
-mod error;
-mod expr;
-mod interpreter;
-mod native_functions;
-mod object;
-mod parser;
-mod scanner;
-mod stmt;
-mod teci_function;
-mod token;
-mod token_type;
-
-use crate::{error::TeciResult, interpreter::Interpreter, parser::Parser, scanner::Scanner};
+use teci_lang::{
+    call_stack::DEFAULT_MAX_DEPTH, dap, debugger::Debugger, dialect::Dialect, error::TeciResult,
+    interpreter::Interpreter, lsp, optimizer::Optimizer, parser::Parser,
+    pretty_printer::AstPrinter, resolver::Resolver, sandbox::Capability, sandbox::Sandbox,
+    scanner::Scanner, stmt::Stmt, type_checker::TypeChecker,
+};
 
 use std::{
+    cell::RefCell,
     env::args,
     io::{self, Write, stdout},
     path::Path,
+    rc::Rc,
+    thread,
+    time::Duration,
+};
+
+// Teci calls recurse on the Rust stack, so the interpreter gets a thread with
+// enough room for deep (but bounded by `--max-depth`) recursion
+const INTERPRETER_STACK_SIZE: usize = 256 * 1024 * 1024;
+// The stack a Teci call may take, with room for calls nested in expressions.
+// Measured with the call inside a block, a `let`, a `?:` and a grouping, it
+// takes about 20KiB in debug builds and 4KiB in release builds.
+const STACK_PER_CALL: usize = if cfg!(debug_assertions) {
+    32 * 1024
+} else {
+    16 * 1024
 };
 
+// A `--max-depth` too deep for the default stack gets a larger one
+fn stack_size(max_depth: usize) -> usize {
+    INTERPRETER_STACK_SIZE.max(max_depth.saturating_mul(STACK_PER_CALL))
+}
+
 fn main() {
-    let args: Vec<String> = args().collect();
-    let teci = Teci::new();
-    match args.len() {
-        1 => teci.run_prompt(),
-        2 => teci
-            .run_script(&args[1])
-            .unwrap_or_else(|_| panic!("Could not run script {}", &args[1])),
-        _ => {
-            println!("Usage: teci-lang [script]");
-            std::process::exit(64)
+    let max_depth = args()
+        .find_map(|arg| arg.strip_prefix("--max-depth=")?.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_MAX_DEPTH);
+    let interpreter = thread::Builder::new()
+        .stack_size(stack_size(max_depth))
+        .spawn(run_cli)
+        .unwrap_or_else(|error| {
+            eprintln!(
+                "Could not start the interpreter with a stack for --max-depth={}: {}",
+                max_depth, error
+            );
+            std::process::exit(70)
+        });
+    if interpreter.join().is_err() {
+        std::process::exit(101);
+    }
+}
+
+fn run_cli() {
+    let mut max_depth = DEFAULT_MAX_DEPTH;
+    let mut dialect = Dialect::classic();
+    let mut gc_stats = false;
+    let mut optimize = false;
+    let mut dump_ast = false;
+    // Any of the sandbox options runs scripts in a sandbox
+    let mut sandbox: Option<Sandbox> = None;
+    let mut args_left = Vec::new();
+    for arg in args().skip(1) {
+        if let Some(depth) = arg.strip_prefix("--max-depth=") {
+            max_depth = depth.parse().unwrap_or_else(|_| usage());
+        } else if arg == "--gc-stats" {
+            gc_stats = true;
+        } else if arg == "--optimize" {
+            optimize = true;
+        } else if arg == "--dump-ast" {
+            dump_ast = true;
+        } else if arg == "--sandbox" {
+            sandbox = Some(sandbox.unwrap_or_default());
+        } else if let Some(max) = arg.strip_prefix("--max-statements=") {
+            let max = max.parse().unwrap_or_else(|_| usage());
+            sandbox = Some(sandbox.unwrap_or_default().with_max_statements(max));
+        } else if let Some(timeout) = arg.strip_prefix("--timeout=") {
+            let timeout = Duration::from_millis(timeout.parse().unwrap_or_else(|_| usage()));
+            sandbox = Some(sandbox.unwrap_or_default().with_timeout(timeout));
+        } else if let Some(max) = arg.strip_prefix("--max-size=") {
+            let max = max.parse().unwrap_or_else(|_| usage());
+            sandbox = Some(sandbox.unwrap_or_default().with_max_size(max));
+        } else if let Some(capabilities) = arg.strip_prefix("--allow=") {
+            let mut allowed = sandbox.unwrap_or_default();
+            for name in capabilities.split(',') {
+                let capability = Capability::named(name).unwrap_or_else(|| usage());
+                allowed = allowed.with_capability(capability);
+            }
+            sandbox = Some(allowed);
+        } else if let Some(name) = arg.strip_prefix("--dialect=") {
+            dialect = Dialect::named(name).unwrap_or_else(|message| {
+                eprintln!("{}", message);
+                std::process::exit(64)
+            });
+        } else {
+            args_left.push(arg);
         }
     }
+
+    let teci = Teci::new(dialect)
+        .with_gc_stats(gc_stats)
+        .with_optimizer(optimize)
+        .with_ast_dump(dump_ast);
+    teci.interpreter.set_max_depth(max_depth);
+    if let Some(sandbox) = sandbox {
+        teci.interpreter.set_sandbox(sandbox);
+    }
+    match args_left.as_slice() {
+        [] => teci.run_prompt(),
+        [command] if command == "dap" => dap::serve(io::stdin().lock(), stdout()),
+        [command] if command == "lsp" => lsp::serve(io::stdin().lock(), stdout()),
+        [command, script] if command == "check" => teci
+            .check_script(script, false)
+            .unwrap_or_else(|_| panic!("Could not check script {}", script)),
+        [command, flag, script] if command == "check" && flag == "--types" => teci
+            .check_script(script, true)
+            .unwrap_or_else(|_| panic!("Could not check script {}", script)),
+        [command, script] if command == "run" => teci
+            .run_script(script)
+            .unwrap_or_else(|_| panic!("Could not run script {}", script)),
+        [command, flag, script] if command == "run" && flag == "--profile" => {
+            teci.interpreter.enable_profiler();
+            teci.run_script(script)
+                .unwrap_or_else(|_| panic!("Could not run script {}", script))
+        }
+        [command, script] if command == "debug" => teci
+            .debug_script(script)
+            .unwrap_or_else(|_| panic!("Could not debug script {}", script)),
+        [script] => teci
+            .run_script(script)
+            .unwrap_or_else(|_| panic!("Could not run script {}", script)),
+        _ => usage(),
+    }
+}
+
+fn usage() -> ! {
+    println!("Usage: teci-lang [options] [script]");
+    println!("       teci-lang [options] run [--profile] <script>");
+    println!("       teci-lang [options] debug <script>");
+    println!("       teci-lang [--dialect=NAME|FILE] check [--types] <script>");
+    println!("       teci-lang dap");
+    println!("       teci-lang lsp");
+    println!();
+    println!("Options:");
+    println!("  --max-depth=N          limit the depth of calls");
+    println!("  --dialect=NAME|FILE    use a dialect for sources without a pragma");
+    println!("  --gc-stats             report what the garbage collector did");
+    println!("  --optimize             fold constants and drop dead code before running");
+    println!("  --dump-ast             print the tree that would run instead of running it");
+    println!();
+    println!("Sandbox options, for scripts that are not trusted:");
+    println!("  --sandbox              deny the natives that need a capability");
+    println!("  --max-statements=N     stop after running N statements");
+    println!("  --timeout=MS           stop after running for MS milliseconds");
+    println!("  --max-size=N           limit the bytes of strings and numbers and the length");
+    println!("                         of tuples (65536 unless given)");
+    println!("  --allow=CAP[,CAP...]   grant capabilities (clock, fs, input)");
+    std::process::exit(64)
 }
 
 struct Teci {
     interpreter: Interpreter,
+    // Used for sources without a `#dialect` pragma. A pragma typed in the REPL
+    // switches it for the rest of the session.
+    dialect: RefCell<Dialect>,
+    // Report what the garbage collector did once done running
+    gc_stats: bool,
+    // Run the optimizer on the tree before running it
+    optimize: bool,
+    // Print the tree instead of running it
+    dump_ast: bool,
 }
 
 impl Teci {
-    pub fn new() -> Self {
+    pub fn new(dialect: Dialect) -> Self {
         Self {
             interpreter: Interpreter::new(),
+            dialect: RefCell::new(dialect),
+            gc_stats: false,
+            optimize: false,
+            dump_ast: false,
         }
     }
 
+    pub fn with_gc_stats(mut self, gc_stats: bool) -> Self {
+        self.gc_stats = gc_stats;
+        self
+    }
+
+    pub fn with_optimizer(mut self, optimize: bool) -> Self {
+        self.optimize = optimize;
+        self
+    }
+
+    pub fn with_ast_dump(mut self, dump_ast: bool) -> Self {
+        self.dump_ast = dump_ast;
+        self
+    }
+
     fn run_script<S: AsRef<Path>>(&self, path: S) -> io::Result<()> {
         let buf = std::fs::read_to_string(path)?;
-        if self.run(buf).is_err() {
+        let result = self.run(buf);
+        self.report_gc_stats();
+        if let Some(report) = self.interpreter.profiler_report() {
+            eprint!("{}", report);
+        }
+        match result {
+            Ok(_) => Ok(()),
+            Err(TeciResult::Backtrace { .. }) => std::process::exit(70),
+            Err(_) => std::process::exit(65),
+        }
+    }
+
+    // Reports the errors found without running the script, including type
+    // errors with `types`
+    fn check_script<S: AsRef<Path>>(&self, path: S, types: bool) -> io::Result<()> {
+        let buf = std::fs::read_to_string(path)?;
+        let failed = match self.analyze(buf, true) {
+            Ok(statements) => types && !TypeChecker::new().check(&statements).is_empty(),
+            Err(_) => true,
+        };
+        if failed {
             std::process::exit(65);
         }
         Ok(())
     }
 
+    fn debug_script<S: AsRef<Path>>(&self, path: S) -> io::Result<()> {
+        let buf = std::fs::read_to_string(path)?;
+        let files = self.interpreter.allows(Capability::FileSystem);
+        let dialect = match Dialect::of_source(&buf, files) {
+            Some(Ok(dialect)) => dialect,
+            _ => self.dialect.borrow().clone(),
+        };
+        let debugger = Debugger::new(&buf, io::stdin().lock(), stdout()).with_dialect(dialect);
+        self.interpreter.set_debug_hook(Rc::new(debugger));
+        println!("Type 'help' for the list of debugger commands");
+        let result = self.run(buf);
+        self.report_gc_stats();
+        match result {
+            Ok(_) => Ok(()),
+            Err(TeciResult::Backtrace { .. }) => std::process::exit(70),
+            Err(_) => std::process::exit(65),
+        }
+    }
+
     fn run_prompt(&self) {
         let stdin = io::stdin();
         print!(">> ");
@@ -63,18 +253,33 @@ impl Teci {
                 if line == "exit" || line == "quit" {
                     break;
                 }
+                let dialect = self.dialect.borrow().name().to_string();
                 match self.run(line) {
                     Ok(_) => {}
                     Err(_) => {
                         // already reported
                     }
                 }
+                if self.dialect.borrow().name() != dialect {
+                    println!("Using the {} dialect", self.dialect.borrow().name());
+                }
             } else {
                 break;
             }
             print!(">> ");
             let _ = stdout().flush();
         }
+        self.report_gc_stats();
+    }
+
+    fn report_gc_stats(&self) {
+        if self.gc_stats {
+            let stats = self.interpreter.heap_stats();
+            eprintln!(
+                "[gc] {} collections, {} environments freed, {} tracked",
+                stats.collections, stats.freed, stats.tracked
+            );
+        }
     }
 
     fn run(&self, source: String) -> Result<(), TeciResult> {
@@ -83,16 +288,73 @@ impl Teci {
             return Ok(());
         }
 
-        let mut scanner = Scanner::new(source);
+        let mut statements = self.analyze(source, false)?;
+        if self.optimize {
+            statements = Optimizer::new().optimize(&statements)?;
+        }
+        if self.dump_ast {
+            print!("{}", AstPrinter.print(&statements)?);
+            return Ok(());
+        }
+        self.interpreter.interpret(&statements)
+    }
+
+    // Scans, parses and resolves `source`, reporting the errors found. Only
+    // assignments to constants and functions fail the resolution unless
+    // `check`, since the other resolver errors may never happen at run time.
+    fn analyze(&self, source: String, check: bool) -> Result<Vec<Stmt>, TeciResult> {
+        let mut scanner = Scanner::new(source)
+            .with_dialect(self.dialect.borrow().clone())
+            .with_mapping_files(self.interpreter.allows(Capability::FileSystem));
         let tokens = scanner.scan_tokens()?;
+        self.dialect.replace(scanner.dialect().clone());
 
         let mut parser = Parser::new(tokens);
         let statements = parser.parse()?;
 
         parser.succeded();
 
-        self.interpreter.interpret(&statements);
+        // Names defined by earlier runs, like REPL lines, are known too
+        let globals = self.interpreter._globals.borrow().bindings();
+        let known = globals.into_iter().map(|(name, _)| name).collect();
+        let resolver = Resolver::new(known);
+        let resolver = if check {
+            resolver
+        } else {
+            resolver.assignments_only()
+        };
+        let resolution = resolver.resolve(&statements);
+        if let Some(error) = resolution.errors.into_iter().next() {
+            // Already reported
+            return Err(error);
+        }
 
-        Ok(())
+        Ok(statements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_stack_per_call() {
+        // Recursion as deep as `--max-depth` allows, with each call nested
+        // in a block, a `let`, a `?:` and a grouping, fits in the stack given
+        // for it
+        let max_depth = 100_000;
+        let source = format!(
+            "fun down(n) {{\n  if (n == 0) return 0;\n  {{\n    let x = 1 + (n > 0 ? down(n - 1) : 0);\n    return x;\n  }}\n}}\nlet depth = down({});",
+            max_depth - 1
+        );
+        let interpreter = thread::Builder::new()
+            .stack_size(stack_size(max_depth))
+            .spawn(move || {
+                let teci = Teci::new(Dialect::classic());
+                teci.interpreter.set_max_depth(max_depth);
+                teci.run(source).is_ok()
+            })
+            .unwrap();
+        assert!(interpreter.join().unwrap());
     }
 }
@@ -1,6 +1,13 @@
-use crate::{callable::TeciCallable, error::TeciResult, interpreter::Interpreter, object::Object};
+use crate::{
+    callable::{Callable, TeciCallable},
+    error::TeciResult,
+    interpreter::Interpreter,
+    iterator::RangeIterator,
+    object::Object,
+    sandbox::Capability,
+};
 
-use std::time;
+use std::{rc::Rc, time};
 
 pub struct NativeClock;
 
@@ -19,7 +26,133 @@ impl TeciCallable for NativeClock {
         }
     }
 
+    fn name(&self) -> String {
+        "clock".to_string()
+    }
+
     fn to_string(&self) -> String {
         "<fun native::clock>".to_string()
     }
+
+    fn capability(&self) -> Option<Capability> {
+        Some(Capability::Clock)
+    }
+}
+
+// `range(start, end, step)`, an iterator over the numbers from `start` to
+// `end` (not included)
+pub struct NativeRange;
+
+impl TeciCallable for NativeRange {
+    fn arity(&self) -> usize {
+        3
+    }
+
+    fn call(&self, _interpreter: &Interpreter, args: Vec<Object>) -> Result<Object, TeciResult> {
+        if let Some(arg) = args.iter().find(|arg| !arg.is_number()) {
+            return Err(TeciResult::system_error(&format!(
+                "range expects numbers, found a {}",
+                arg.type_name()
+            )));
+        }
+        let [start, end, step] = <[Object; 3]>::try_from(args).unwrap();
+        if step == Object::Int(0) {
+            return Err(TeciResult::system_error("range step cannot be zero"));
+        }
+        Ok(Object::Func(Callable {
+            func: Rc::new(RangeIterator::new(start, end, step)),
+        }))
+    }
+
+    fn name(&self) -> String {
+        "range".to_string()
+    }
+
+    fn to_string(&self) -> String {
+        "<fun native::range>".to_string()
+    }
+}
+
+// `next(iterator)`, the next value of an iterator or generator, or nil once
+// it is done
+pub struct NativeNext;
+
+impl TeciCallable for NativeNext {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn call(&self, interpreter: &Interpreter, args: Vec<Object>) -> Result<Object, TeciResult> {
+        match &args[0] {
+            Object::Func(iterator) if iterator.arity() == 0 => {
+                iterator.call(interpreter, Vec::new())
+            }
+            arg => Err(TeciResult::system_error(&format!(
+                "next expects an iterator, found a {}",
+                arg.type_name()
+            ))),
+        }
+    }
+
+    fn name(&self) -> String {
+        "next".to_string()
+    }
+
+    fn to_string(&self) -> String {
+        "<fun native::next>".to_string()
+    }
+}
+
+// `input(prompt)`, the next line of stdin without the line break, after
+// printing `prompt`, or nil at the end of stdin
+pub struct NativeInput;
+
+impl TeciCallable for NativeInput {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn call(&self, interpreter: &Interpreter, args: Vec<Object>) -> Result<Object, TeciResult> {
+        let [prompt] = <[Object; 1]>::try_from(args).unwrap();
+        interpreter.write(&Interpreter::stringify(prompt))?;
+        match interpreter.read_line() {
+            Ok(Some(line)) => Ok(Object::Str(line.into())),
+            Ok(None) => Ok(Object::Nil),
+            Err(error) => Err(interpreter.call_error(&format!("input failed: {}", error))),
+        }
+    }
+
+    fn name(&self) -> String {
+        "input".to_string()
+    }
+
+    fn to_string(&self) -> String {
+        "<fun native::input>".to_string()
+    }
+
+    fn capability(&self) -> Option<Capability> {
+        Some(Capability::Input)
+    }
+}
+
+// `gc()`, collects the cycles left behind by closures right away, giving how
+// many environments it freed
+pub struct NativeGc;
+
+impl TeciCallable for NativeGc {
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn call(&self, interpreter: &Interpreter, _args: Vec<Object>) -> Result<Object, TeciResult> {
+        Ok(Object::Int(interpreter.collect_garbage() as i64))
+    }
+
+    fn name(&self) -> String {
+        "gc".to_string()
+    }
+
+    fn to_string(&self) -> String {
+        "<fun native::gc>".to_string()
+    }
 }
@@ -0,0 +1,49 @@
+use std::fmt::{self, Debug, Display};
+use std::rc::Rc;
+
+use crate::{callable::Callable, object::Object};
+
+// A value made by natives that has properties, looked up with `.`, like the
+// `fs` module and the file handles it opens
+pub trait TeciObject {
+    // The name of its type, for error messages
+    fn type_name(&self) -> &'static str;
+    fn to_string(&self) -> String;
+    // The property called `name`, if there is one
+    fn get(&self, name: &str) -> Option<Object>;
+    // An iterator over it, for `for-in` loops
+    fn iterator(&self) -> Option<Callable> {
+        None
+    }
+}
+
+#[derive(Clone)]
+pub struct NativeObject {
+    pub object: Rc<dyn TeciObject>,
+}
+
+impl NativeObject {
+    pub fn new(object: impl TeciObject + 'static) -> Self {
+        Self {
+            object: Rc::new(object),
+        }
+    }
+}
+
+impl Debug for NativeObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl Display for NativeObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.object.to_string())
+    }
+}
+
+impl PartialEq for NativeObject {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.object, &other.object)
+    }
+}
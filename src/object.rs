@@ -1,87 +1,531 @@
 use std::{
     cmp::Ordering,
     fmt::{self, Debug},
-    ops::{Add, Div, Mul, Neg, Sub},
+    hash::{Hash, Hasher},
+    ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Neg, Not, Rem, Shl, Shr, Sub},
+    rc::Rc,
 };
 
-use crate::callable::Callable;
+use crate::{
+    bignum::{BigInt, Rational},
+    callable::Callable,
+    native_object::NativeObject,
+};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum Object {
+    Int(i64),
+    // Integers that do not fit an i64, never one that does
+    BigInt(Rc<BigInt>),
+    // Exact fractions, never one that is an integer
+    Rational(Rc<Rational>),
     Num(f64),
-    Str(String),
+    Str(Rc<str>),
     Bool(bool),
     Func(Callable),
+    // `(a, b, c)`, immutable
+    Tuple(Rc<[Object]>),
+    // Made by natives, like modules and file handles
+    Native(NativeObject),
     Nil,
-    ArithmeticError,
-    DivisionByZeroError,
+}
+
+// Why an operator could not give a value. The interpreter reports it as a
+// runtime error at the operator.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OperatorError {
+    // The operator does not apply to operands of these types
+    UnsupportedOperands(&'static str, &'static str),
+    UnsupportedOperand(&'static str),
+    DivisionByZero,
+    // The result is too big to represent, like `1 << 2 ** 64`
+    Overflow,
+}
+
+pub type OperatorResult = Result<Object, OperatorError>;
+
+impl OperatorError {
+    // The error message for the operator `operator`
+    pub fn message(&self, operator: &str) -> String {
+        match self {
+            OperatorError::UnsupportedOperands(left, right) => {
+                format!("Unsupported operand types for '{operator}': {left} and {right}")
+            }
+            OperatorError::UnsupportedOperand(operand) => {
+                format!("Unsupported operand type for '{operator}': {operand}")
+            }
+            OperatorError::DivisionByZero => "Division by zero".to_string(),
+            OperatorError::Overflow => format!("The result of '{operator}' is too big"),
+        }
+    }
+}
+
+// A number on the extended real line, so that integers, fractions and floats
+// can be compared (and hashed) exactly
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Exact {
+    NegativeInfinity,
+    Finite(Rational),
+    Infinity,
+}
+
+impl Object {
+    // The smallest integer representation of `value`
+    pub fn integer(value: BigInt) -> Self {
+        match value.to_i64() {
+            Some(int) => Object::Int(int),
+            None => Object::BigInt(Rc::new(value)),
+        }
+    }
+
+    // The smallest exact representation of `value`
+    pub fn rational(value: Rational) -> Self {
+        if value.is_integer() {
+            Object::integer(value.numerator().clone())
+        } else {
+            Object::Rational(Rc::new(value))
+        }
+    }
+
+    // The name of the type of the value, for error messages. It is the one
+    // annotations and the type checker use, so all numbers are `num`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Int(_) | Object::BigInt(_) | Object::Rational(_) | Object::Num(_) => "num",
+            Object::Str(_) => "str",
+            Object::Bool(_) => "bool",
+            Object::Func(_) => "fun",
+            Object::Tuple(_) => "tuple",
+            Object::Native(native) => native.object.type_name(),
+            Object::Nil => "nil",
+        }
+    }
+
+    // How many bits the digits of an exact number take
+    pub fn exact_bits(&self) -> Option<u64> {
+        match self {
+            Object::Int(int) => Some(64 - int.unsigned_abs().leading_zeros() as u64),
+            Object::BigInt(int) => Some(int.bits()),
+            Object::Rational(fraction) => {
+                Some(fraction.numerator().bits() + fraction.denominator().bits())
+            }
+            _ => None,
+        }
+    }
+
+    // How large the value is, for a sandbox to limit: the bytes of a string
+    // or of the digits of an exact number, or the elements of a tuple
+    pub fn size(&self) -> u64 {
+        match self {
+            Object::Str(s) => s.len() as u64,
+            Object::Tuple(elements) => elements.len() as u64,
+            number => number.exact_bits().unwrap_or(0).div_ceil(8),
+        }
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(
+            self,
+            Object::Int(_) | Object::BigInt(_) | Object::Rational(_) | Object::Num(_)
+        )
+    }
+
+    fn integer_value(&self) -> Option<BigInt> {
+        match self {
+            Object::Int(int) => Some(BigInt::from(*int)),
+            Object::BigInt(int) => Some(int.as_ref().clone()),
+            _ => None,
+        }
+    }
+
+    // The value of an integer or fraction
+    fn exact(&self) -> Option<Rational> {
+        match self {
+            Object::Int(int) => Some(BigInt::from(*int).into()),
+            Object::BigInt(int) => Some(int.as_ref().clone().into()),
+            Object::Rational(fraction) => Some(fraction.as_ref().clone()),
+            _ => None,
+        }
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        match self {
+            Object::Int(int) => Some(*int as f64),
+            Object::BigInt(int) => Some(int.to_f64()),
+            Object::Rational(fraction) => Some(fraction.to_f64()),
+            Object::Num(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    // Any number but NaN, exactly
+    fn extended(&self) -> Option<Exact> {
+        match self {
+            Object::Num(x) if x.is_nan() => None,
+            Object::Num(x) if x.is_infinite() && *x > 0.0 => Some(Exact::Infinity),
+            Object::Num(x) if x.is_infinite() => Some(Exact::NegativeInfinity),
+            Object::Num(x) => Rational::from_f64(*x).map(Exact::Finite),
+            _ => self.exact().map(Exact::Finite),
+        }
+    }
+
+    // Integer division, rounding down
+    pub fn floor_div(self, rhs: Self) -> OperatorResult {
+        arithmetic(
+            &self,
+            &rhs,
+            |left, right| match left.checked_div(right) {
+                Some(q) if left % right != 0 && (left < 0) != (right < 0) => Some(q - 1),
+                q => q,
+            },
+            |left, right| match left.checked_div(right) {
+                Some(q) => Ok(Object::integer(q.floor())),
+                None => Err(OperatorError::DivisionByZero),
+            },
+            |left, right| match right {
+                0.0 => Err(OperatorError::DivisionByZero),
+                _ => Ok(Object::Num((left / right).floor())),
+            },
+        )
+    }
+
+    // Exact for integer exponents, a float otherwise
+    pub fn pow(self, rhs: Self) -> OperatorResult {
+        if let (Object::Int(base), Object::Int(exponent)) = (&self, &rhs)
+            && let Ok(exponent) = u32::try_from(*exponent)
+            && let Some(power) = base.checked_pow(exponent)
+        {
+            return Ok(Object::Int(power));
+        }
+        if let (Some(base), Object::Int(exponent)) = (self.exact(), &rhs) {
+            let power = base.pow(exponent.unsigned_abs());
+            if *exponent >= 0 {
+                return Ok(Object::rational(power));
+            }
+            return match Rational::from_integer(BigInt::from(1)).checked_div(&power) {
+                Some(power) => Ok(Object::rational(power)),
+                None => Err(OperatorError::DivisionByZero),
+            };
+        }
+        match (self.to_f64(), rhs.to_f64()) {
+            (Some(base), Some(exponent)) => Ok(Object::Num(base.powf(exponent))),
+            _ => Err(unsupported(&self, &rhs)),
+        }
+    }
+}
+
+fn unsupported(left: &Object, right: &Object) -> OperatorError {
+    OperatorError::UnsupportedOperands(left.type_name(), right.type_name())
+}
+
+// Applies a bitwise operator to two integers
+fn bitwise(
+    left: &Object,
+    right: &Object,
+    int: impl Fn(i64, i64) -> i64,
+    limbs: impl Fn(u32, u32) -> u32,
+) -> OperatorResult {
+    if let (Object::Int(left), Object::Int(right)) = (left, right) {
+        return Ok(Object::Int(int(*left, *right)));
+    }
+    match (left.integer_value(), right.integer_value()) {
+        (Some(left), Some(right)) => Ok(Object::integer(left.bitwise(&right, limbs))),
+        _ => Err(unsupported(left, right)),
+    }
+}
+
+// Shifts an integer left (or right, for a negative count) by `count` bits
+fn shift_left(value: &Object, count: &Object) -> OperatorResult {
+    if let (Object::Int(value), Object::Int(count)) = (value, count) {
+        match *count {
+            0..63 if (value << count) >> count == *value => return Ok(Object::Int(value << count)),
+            -63..0 => return Ok(Object::Int(value >> -count)),
+            _ => {}
+        }
+    }
+    let (Some(value), Some(count)) = (value.integer_value(), count.integer_value()) else {
+        return Err(unsupported(value, count));
+    };
+    match u32::try_from(count.abs().to_i64().unwrap_or(i64::MAX)) {
+        Ok(bits) if count.is_negative() => Ok(Object::integer(
+            value.div_floor(&BigInt::power_of_two(bits)).unwrap(),
+        )),
+        Ok(bits) => Ok(Object::integer(&value * &BigInt::power_of_two(bits))),
+        // Shifted out all its bits
+        Err(_) if count.is_negative() => Ok(Object::Int(if value.is_negative() { -1 } else { 0 })),
+        Err(_) if value.is_zero() => Ok(Object::Int(0)),
+        Err(_) => Err(OperatorError::Overflow),
+    }
+}
+
+// Applies an operator in the most precise representation both operands allow:
+// machine integers while the result fits, then exact fractions, then floats
+fn arithmetic(
+    left: &Object,
+    right: &Object,
+    int: impl Fn(i64, i64) -> Option<i64>,
+    exact: impl Fn(&Rational, &Rational) -> OperatorResult,
+    float: impl Fn(f64, f64) -> OperatorResult,
+) -> OperatorResult {
+    if let (Object::Int(left), Object::Int(right)) = (left, right)
+        && let Some(result) = int(*left, *right)
+    {
+        return Ok(Object::Int(result));
+    }
+    if let (Some(exact_left), Some(exact_right)) = (left.exact(), right.exact()) {
+        return exact(&exact_left, &exact_right);
+    }
+    match (left.to_f64(), right.to_f64()) {
+        (Some(left), Some(right)) => float(left, right),
+        _ => Err(unsupported(left, right)),
+    }
+}
+
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Object::Int(left), Object::Int(right)) => left == right,
+            (left, right) if left.is_number() && right.is_number() => {
+                left.partial_cmp(right) == Some(Ordering::Equal)
+            }
+            (Object::Str(left), Object::Str(right)) => left == right,
+            (Object::Bool(left), Object::Bool(right)) => left == right,
+            (Object::Func(left), Object::Func(right)) => left == right,
+            (Object::Tuple(left), Object::Tuple(right)) => left == right,
+            (Object::Native(left), Object::Native(right)) => left == right,
+            (Object::Nil, Object::Nil) => true,
+            _ => false,
+        }
+    }
+}
+
+// Numbers that compare equal hash the same, whatever their representation
+impl Hash for Object {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Object::Num(x) if x.is_nan() => x.to_bits().hash(state),
+            number if number.is_number() => number.extended().hash(state),
+            Object::Str(s) => s.hash(state),
+            Object::Bool(b) => b.hash(state),
+            Object::Func(callable) => Rc::as_ptr(&callable.func).cast::<()>().hash(state),
+            Object::Tuple(elements) => elements.hash(state),
+            Object::Native(native) => Rc::as_ptr(&native.object).cast::<()>().hash(state),
+            other => std::mem::discriminant(other).hash(state),
+        }
+    }
 }
 
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Num(x) => write!(f, "{x}"),
+            Self::Int(i) => write!(f, "{i}"),
+            Self::BigInt(i) => write!(f, "{i}"),
+            Self::Rational(q) => write!(f, "{q}"),
+            // Floats always show a fraction or exponent, so they do not look
+            // like integers
+            Self::Num(x) => write!(f, "{x:?}"),
             Self::Str(s) => write!(f, "{s}"),
             Self::Bool(b) => write!(f, "{b}"),
             Self::Nil => write!(f, "nil"),
-            Self::ArithmeticError => write!(f, "ArithmeticError"),
-            Self::DivisionByZeroError => write!(f, "DivisionByZeroError"),
             Self::Func(callable) => write!(f, "{}", callable),
+            Self::Native(native) => write!(f, "{}", native),
+            Self::Tuple(elements) => {
+                write!(f, "(")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    // Quoted, so that `("a, b",)` does not look like a pair
+                    match element {
+                        Self::Str(s) => write!(f, "{s:?}")?,
+                        element => write!(f, "{element}")?,
+                    }
+                }
+                // `(1,)`, like the literal
+                if elements.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
         }
     }
 }
 
 impl Neg for Object {
-    type Output = Self;
+    type Output = OperatorResult;
     fn neg(self) -> Self::Output {
-        if let Object::Num(x) = self {
-            Object::Num(-x)
-        } else {
-            Object::ArithmeticError
+        match self {
+            Object::Int(x) => Ok(match x.checked_neg() {
+                Some(x) => Object::Int(x),
+                None => Object::integer(-&BigInt::from(x)),
+            }),
+            Object::BigInt(x) => Ok(Object::integer(-x.as_ref())),
+            Object::Rational(x) => Ok(Object::Rational(Rc::new(-x.as_ref()))),
+            Object::Num(x) => Ok(Object::Num(-x)),
+            other => Err(OperatorError::UnsupportedOperand(other.type_name())),
         }
     }
 }
 
 impl Add for Object {
-    type Output = Self;
+    type Output = OperatorResult;
     fn add(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Object::Num(left), Object::Num(right)) => Object::Num(left + right),
-            (Object::Str(left), Object::Str(right)) => Object::Str(format!("{left}{right}")),
-            (Object::Str(left), Object::Num(right)) => Object::Str(format!("{left}{right}")),
-            (Object::Num(left), Object::Str(right)) => Object::Str(format!("{left}{right}")),
-            _ => Object::ArithmeticError,
+            (Object::Str(left), Object::Str(right)) => {
+                Ok(Object::Str([&*left, &*right].concat().into()))
+            }
+            (Object::Str(left), right) if right.is_number() => {
+                Ok(Object::Str(format!("{left}{right}").into()))
+            }
+            (left, Object::Str(right)) if left.is_number() => {
+                Ok(Object::Str(format!("{left}{right}").into()))
+            }
+            (left, right) => arithmetic(
+                &left,
+                &right,
+                i64::checked_add,
+                |left, right| Ok(Object::rational(left + right)),
+                |left, right| Ok(Object::Num(left + right)),
+            ),
         }
     }
 }
 
 impl Sub for Object {
-    type Output = Self;
+    type Output = OperatorResult;
     fn sub(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Object::Num(left), Object::Num(right)) => Object::Num(left - right),
-            _ => Object::ArithmeticError,
-        }
+        arithmetic(
+            &self,
+            &rhs,
+            i64::checked_sub,
+            |left, right| Ok(Object::rational(left - right)),
+            |left, right| Ok(Object::Num(left - right)),
+        )
     }
 }
 
 impl Mul for Object {
-    type Output = Self;
+    type Output = OperatorResult;
     fn mul(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Object::Num(left), Object::Num(right)) => Object::Num(left * right),
-            _ => Self::ArithmeticError,
-        }
+        arithmetic(
+            &self,
+            &rhs,
+            i64::checked_mul,
+            |left, right| Ok(Object::rational(left * right)),
+            |left, right| Ok(Object::Num(left * right)),
+        )
     }
 }
 
 impl Div for Object {
-    type Output = Self;
+    type Output = OperatorResult;
+    // Dividing integers is exact, giving a fraction unless it divides evenly.
+    // `~/` is the integer division.
     fn div(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Object::Num(_), Object::Num(0.0)) => Object::DivisionByZeroError,
-            (Object::Num(left), Object::Num(right)) => Object::Num(left / right),
-            _ => Self::ArithmeticError,
+        arithmetic(
+            &self,
+            &rhs,
+            |left, right| match left.checked_rem(right) {
+                Some(0) => left.checked_div(right),
+                _ => None,
+            },
+            |left, right| match left.checked_div(right) {
+                Some(q) => Ok(Object::rational(q)),
+                None => Err(OperatorError::DivisionByZero),
+            },
+            |left, right| match right {
+                0.0 => Err(OperatorError::DivisionByZero),
+                _ => Ok(Object::Num(left / right)),
+            },
+        )
+    }
+}
+
+impl Rem for Object {
+    type Output = OperatorResult;
+    // Takes the sign of the divisor, to go with `floor_div`
+    fn rem(self, rhs: Self) -> Self::Output {
+        arithmetic(
+            &self,
+            &rhs,
+            |left, right| match left.checked_rem(right) {
+                Some(r) if r != 0 && (r < 0) != (right < 0) => Some(r + right),
+                r => r,
+            },
+            |left, right| match left.checked_div(right) {
+                Some(q) => Ok(Object::rational(left - &(right * &q.floor().into()))),
+                None => Err(OperatorError::DivisionByZero),
+            },
+            |left, right| match right {
+                0.0 => Err(OperatorError::DivisionByZero),
+                _ => Ok(Object::Num(left - right * (left / right).floor())),
+            },
+        )
+    }
+}
+
+impl BitAnd for Object {
+    type Output = OperatorResult;
+    fn bitand(self, rhs: Self) -> Self::Output {
+        bitwise(
+            &self,
+            &rhs,
+            |left, right| left & right,
+            |left, right| left & right,
+        )
+    }
+}
+
+impl BitOr for Object {
+    type Output = OperatorResult;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        bitwise(
+            &self,
+            &rhs,
+            |left, right| left | right,
+            |left, right| left | right,
+        )
+    }
+}
+
+impl BitXor for Object {
+    type Output = OperatorResult;
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        bitwise(
+            &self,
+            &rhs,
+            |left, right| left ^ right,
+            |left, right| left ^ right,
+        )
+    }
+}
+
+// The bitwise complement `~`, as `!` is the logical not
+impl Not for Object {
+    type Output = OperatorResult;
+    fn not(self) -> Self::Output {
+        match self {
+            Object::Int(x) => Ok(Object::Int(!x)),
+            Object::BigInt(x) => Ok(Object::integer(&-x.as_ref() - &BigInt::from(1))),
+            other => Err(OperatorError::UnsupportedOperand(other.type_name())),
+        }
+    }
+}
+
+impl Shl for Object {
+    type Output = OperatorResult;
+    fn shl(self, rhs: Self) -> Self::Output {
+        shift_left(&self, &rhs)
+    }
+}
+
+impl Shr for Object {
+    type Output = OperatorResult;
+    // Rounds down, like `~/` by a power of two
+    fn shr(self, rhs: Self) -> Self::Output {
+        match rhs {
+            rhs @ (Object::Int(_) | Object::BigInt(_)) => shift_left(&self, &(-rhs)?),
+            rhs => Err(unsupported(&self, &rhs)),
         }
     }
 }
@@ -89,8 +533,217 @@ impl Div for Object {
 impl PartialOrd for Object {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         match (self, other) {
-            (Object::Num(left), Object::Num(right)) => Some(left.partial_cmp(right)?),
+            (Object::Int(left), Object::Int(right)) => Some(left.cmp(right)),
+            (Object::Num(left), Object::Num(right)) => left.partial_cmp(right),
+            (left, right) if left.is_number() && right.is_number() => {
+                Some(left.extended()?.cmp(&right.extended()?))
+            }
             _ => None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Checked = Result<(), OperatorError>;
+
+    #[test]
+    fn t_integer_arithmetic() -> Checked {
+        assert_eq!((Object::Int(7) + Object::Int(2))?, Object::Int(9));
+        assert_eq!((Object::Int(8) / Object::Int(2))?, Object::Int(4));
+        assert_eq!(Object::Int(7).floor_div(Object::Int(2))?, Object::Int(3));
+        assert_eq!(Object::Int(-7).floor_div(Object::Int(2))?, Object::Int(-4));
+        assert_eq!((Object::Int(-7) % Object::Int(2))?, Object::Int(1));
+        assert_eq!((Object::Int(7) % Object::Int(-2))?, Object::Int(-1));
+        assert_eq!(
+            Object::Int(1) % Object::Int(0),
+            Err(OperatorError::DivisionByZero)
+        );
+        assert_eq!(
+            Object::Int(1).floor_div(Object::Int(0)),
+            Err(OperatorError::DivisionByZero)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn t_bignum_promotion() -> Checked {
+        let big = |digits| Object::integer(BigInt::parse(digits).unwrap());
+        let max_plus_one = (Object::Int(i64::MAX) + Object::Int(1))?;
+        assert_eq!(max_plus_one, big("9223372036854775808"));
+        assert_eq!(max_plus_one.to_string(), "9223372036854775808");
+        assert_eq!((-Object::Int(i64::MIN))?, big("9223372036854775808"));
+        assert_eq!(
+            Object::Int(i64::MIN).floor_div(Object::Int(-1))?,
+            big("9223372036854775808")
+        );
+        assert_eq!(
+            ((Object::Int(4_294_967_296) * Object::Int(4_294_967_296))? * Object::Int(-1))?,
+            big("-18446744073709551616")
+        );
+        // Back to a machine integer once it fits again
+        assert!(matches!(
+            (max_plus_one - Object::Int(1))?,
+            Object::Int(i64::MAX)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn t_rational_arithmetic() -> Checked {
+        let third = (Object::Int(1) / Object::Int(3))?;
+        assert_eq!(third.to_string(), "1/3");
+        assert_eq!((Object::Int(7) / Object::Int(2))?.to_string(), "7/2");
+        let one = ((third.clone() + third.clone())? + third.clone())?;
+        assert!(matches!(one, Object::Int(1)));
+        assert_eq!((third.clone() * Object::Int(3))?, Object::Int(1));
+        assert_eq!(
+            (Object::Int(-7) / Object::Int(2))?.floor_div(Object::Int(1))?,
+            Object::Int(-4)
+        );
+        assert_eq!(
+            ((Object::Int(7) / Object::Int(2))? % Object::Int(1))?.to_string(),
+            "1/2"
+        );
+        assert_eq!(
+            third.clone() / Object::Int(0),
+            Err(OperatorError::DivisionByZero)
+        );
+        assert!(
+            matches!((third + Object::Num(0.5))?, Object::Num(x) if (x - 5.0 / 6.0).abs() < 1e-12)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn t_mixed_arithmetic() -> Checked {
+        assert!(matches!((Object::Int(1) + Object::Num(0.5))?, Object::Num(x) if x == 1.5));
+        assert!(matches!(Object::Num(7.5).floor_div(Object::Int(2))?, Object::Num(x) if x == 3.0));
+        assert!(matches!((Object::Num(-7.5) % Object::Int(2))?, Object::Num(x) if x == 0.5));
+        assert_eq!(
+            Object::Num(1.0) / Object::Int(0),
+            Err(OperatorError::DivisionByZero)
+        );
+        assert_eq!(
+            (Object::Str("n = ".into()) + Object::Int(3))?,
+            Object::Str("n = 3".into())
+        );
+        assert_eq!(
+            Object::Str("a".into()) - Object::Int(1),
+            Err(OperatorError::UnsupportedOperands("str", "num"))
+        );
+        assert_eq!(
+            -Object::Bool(true),
+            Err(OperatorError::UnsupportedOperand("bool"))
+        );
+        assert_eq!(Object::Num(3.0).to_string(), "3.0");
+        assert_eq!(Object::Int(3).to_string(), "3");
+        Ok(())
+    }
+
+    #[test]
+    fn t_numeric_comparisons() -> Checked {
+        assert_eq!(Object::Int(1), Object::Num(1.0));
+        assert_ne!(Object::Int(1), Object::Num(1.5));
+        // 2^53 + 1 is not a float, so it must not compare equal to 2^53
+        assert_ne!(
+            Object::Int(9_007_199_254_740_993),
+            Object::Num(9_007_199_254_740_992.0)
+        );
+        assert!(Object::Int(9_007_199_254_740_993) > Object::Num(9_007_199_254_740_992.0));
+        assert!(Object::Num(0.5) < Object::Int(1));
+        assert!(Object::Int(i64::MAX) < Object::Num(1e19));
+        assert_eq!(Object::Int(1).partial_cmp(&Object::Num(f64::NAN)), None);
+        let third = (Object::Int(1) / Object::Int(3))?;
+        assert!(third < Object::Num(0.3334));
+        assert!(third > Object::Num(0.3333));
+        assert!(third != Object::Num(1.0 / 3.0));
+        assert!(
+            (Object::Int(i64::MAX) + Object::Int(1))? == Object::Num(9_223_372_036_854_775_808.0)
+        );
+        assert!((Object::Int(i64::MAX) * Object::Int(2))? < Object::Num(f64::INFINITY));
+        assert!((Object::Int(i64::MIN) * Object::Int(2))? > Object::Num(f64::NEG_INFINITY));
+        Ok(())
+    }
+
+    #[test]
+    fn t_power_and_bitwise() -> Checked {
+        assert_eq!(Object::Int(2).pow(Object::Int(10))?, Object::Int(1024));
+        assert_eq!(
+            Object::Int(2).pow(Object::Int(64))?.to_string(),
+            "18446744073709551616"
+        );
+        assert_eq!(Object::Int(2).pow(Object::Int(-2))?.to_string(), "1/4");
+        assert_eq!(
+            (Object::Int(2) / Object::Int(3))?
+                .pow(Object::Int(2))?
+                .to_string(),
+            "4/9"
+        );
+        assert_eq!(
+            Object::Int(0).pow(Object::Int(-1)),
+            Err(OperatorError::DivisionByZero)
+        );
+        assert!(matches!(Object::Int(4).pow(Object::Num(0.5))?, Object::Num(x) if x == 2.0));
+
+        assert_eq!((Object::Int(12) & Object::Int(10))?, Object::Int(8));
+        assert_eq!((Object::Int(12) | Object::Int(10))?, Object::Int(14));
+        assert_eq!((Object::Int(12) ^ Object::Int(10))?, Object::Int(6));
+        assert_eq!((!Object::Int(5))?, Object::Int(-6));
+        assert_eq!((Object::Int(1) << Object::Int(3))?, Object::Int(8));
+        assert_eq!((Object::Int(-9) >> Object::Int(1))?, Object::Int(-5));
+        assert_eq!((Object::Int(1) << Object::Int(-1))?, Object::Int(0));
+        let big = (Object::Int(1) << Object::Int(100))?;
+        assert_eq!(big, Object::Int(2).pow(Object::Int(100))?);
+        assert_eq!((big.clone() >> Object::Int(99))?, Object::Int(2));
+        assert_eq!(((!big.clone())? & big.clone())?, Object::Int(0));
+        assert_eq!((Object::Int(-1) >> Object::Int(i64::MAX))?, Object::Int(-1));
+        assert_eq!(
+            Object::Int(1) << Object::Int(i64::MAX),
+            Err(OperatorError::Overflow)
+        );
+        assert_eq!(
+            Object::Num(1.0) & Object::Int(1),
+            Err(OperatorError::UnsupportedOperands("num", "num"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn t_numeric_hashes() -> Checked {
+        use std::hash::DefaultHasher;
+
+        let hash = |object: &Object| {
+            let mut hasher = DefaultHasher::new();
+            object.hash(&mut hasher);
+            hasher.finish()
+        };
+        let two = Object::Int(2);
+        assert_eq!(hash(&two), hash(&Object::Num(2.0)));
+        assert_eq!(hash(&two), hash(&(Object::Int(4) / Object::Int(2))?));
+        let big = (Object::Int(i64::MAX) + Object::Int(1))?;
+        assert_eq!(hash(&big), hash(&Object::Num(9_223_372_036_854_775_808.0)));
+        assert_eq!(
+            hash(&(Object::Int(1) / Object::Int(2))?),
+            hash(&Object::Num(0.5))
+        );
+        assert_ne!(hash(&two), hash(&Object::Str("2".into())));
+        Ok(())
+    }
+
+    #[test]
+    fn t_tuples() {
+        let tuple = |elements: Vec<Object>| Object::Tuple(elements.into());
+        let pair = tuple(vec![Object::Int(1), Object::Str("a, b".into())]);
+        let nested = tuple(vec![pair.clone(), tuple(vec![Object::Nil]), tuple(vec![])]);
+        assert_eq!(nested.to_string(), "((1, \"a, b\"), (nil,), ())");
+        assert_eq!(
+            pair,
+            tuple(vec![Object::Num(1.0), Object::Str("a, b".into())])
+        );
+        assert_ne!(pair, tuple(vec![Object::Int(1)]));
+        assert_eq!(pair.type_name(), "tuple");
+    }
+}
@@ -0,0 +1,457 @@
+use std::{cell::Cell, rc::Rc};
+
+use crate::{
+    error::TeciResult, expr::*, generator::contains_yield, interpreter::Interpreter,
+    object::Object, pattern::MatchArm, stmt::*, token::Token, token_type::TokenType,
+};
+
+// Rewrites the tree before it runs, for `--optimize`: folds operations on
+// literals, drops the branches that can never run and the code after a
+// `return` or `break`, and turns `!!x` into `x` where only its truthiness
+// matters. Operations that fail, like `1 / 0`, are left for the interpreter
+// to report when they run.
+pub struct Optimizer {
+    // Whether `yield` statements may be found, so the statements cannot be
+    // dropped: a function with a `yield` anywhere is a generator, even if it
+    // can never be reached
+    in_generator: Cell<bool>,
+}
+
+impl Optimizer {
+    pub fn new() -> Self {
+        Self {
+            in_generator: Cell::new(false),
+        }
+    }
+
+    pub fn optimize(&self, statements: &[Stmt]) -> Result<Vec<Stmt>, TeciResult> {
+        let mut optimized = Vec::new();
+        for statement in statements {
+            let Some(statement) = statement.accept(self)? else {
+                continue;
+            };
+            let exits = matches!(statement, Stmt::Return(_) | Stmt::Break(_));
+            optimized.push(statement);
+            if exits && !self.in_generator.get() {
+                break;
+            }
+        }
+        Ok(optimized)
+    }
+
+    fn expr(&self, expr: &Expr) -> Result<Expr, TeciResult> {
+        expr.accept(self)
+    }
+
+    fn boxed(&self, expr: &Expr) -> Result<Box<Expr>, TeciResult> {
+        Ok(Box::new(expr.accept(self)?))
+    }
+
+    // An expression only tested for truthiness, where `!!x` is the same as `x`
+    fn condition(&self, expr: &Expr) -> Result<Expr, TeciResult> {
+        let mut condition = self.expr(expr)?;
+        while let Expr::Unary(outer) = &condition
+            && outer.operator.ttype == TokenType::Bang
+            && let Expr::Unary(inner) = outer.right.as_ref()
+            && inner.operator.ttype == TokenType::Bang
+        {
+            condition = (*inner.right).clone();
+        }
+        Ok(condition)
+    }
+
+    fn statement(&self, statement: &Stmt) -> Result<Box<Stmt>, TeciResult> {
+        let optimized = statement.accept(self)?;
+        // An empty block where a statement is needed
+        Ok(Box::new(optimized.unwrap_or_else(|| {
+            Stmt::Block(BlockStmt {
+                brace: statement_token(statement),
+                statements: Vec::new(),
+            })
+        })))
+    }
+}
+
+impl Default for Optimizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The value of a literal
+fn constant(expr: &Expr) -> Option<&Object> {
+    match expr {
+        Expr::Literal(literal) => literal.value.as_ref(),
+        _ => None,
+    }
+}
+
+// A literal for a value computed from the expression at `token`
+fn literal(value: Object, token: &Token) -> Expr {
+    let (ttype, lexeme) = match &value {
+        Object::Str(s) => (TokenType::String, format!("{s:?}")),
+        Object::Bool(true) => (TokenType::True, value.to_string()),
+        Object::Bool(false) => (TokenType::False, value.to_string()),
+        Object::Nil => (TokenType::Nil, value.to_string()),
+        _ => (TokenType::Number, value.to_string()),
+    };
+    Expr::Literal(LiteralExpr {
+        token: Token::new(ttype, lexeme, Some(value.clone()), token.line).with_column(token.column),
+        value: Some(value),
+    })
+}
+
+fn statement_token(statement: &Stmt) -> Token {
+    match statement {
+        Stmt::Break(s) => s.token.clone(),
+        Stmt::Block(s) => s.brace.clone(),
+        Stmt::Destructure(s) => s.keyword.clone(),
+        Stmt::ForIn(s) => s.keyword.clone(),
+        Stmt::If(s) => s.keyword.clone(),
+        Stmt::Expression(s) => s.token.clone(),
+        Stmt::Function(s) => s.name.clone(),
+        Stmt::Print(s) => s.keyword.clone(),
+        Stmt::Return(s) => s.keyword.clone(),
+        Stmt::Let(s) => s.name.clone(),
+        Stmt::While(s) => s.keyword.clone(),
+        Stmt::Yield(s) => s.keyword.clone(),
+    }
+}
+
+impl ExprVisitor<Expr> for Optimizer {
+    fn visit_assign_expr(&self, expr: &AssignExpr) -> Result<Expr, TeciResult> {
+        Ok(Expr::Assign(AssignExpr {
+            name: expr.name.clone(),
+            value: self.boxed(&expr.value)?,
+        }))
+    }
+
+    fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<Expr, TeciResult> {
+        let left = self.expr(&expr.left)?;
+        let right = self.expr(&expr.right)?;
+        if let (Some(l), Some(r)) = (constant(&left), constant(&right))
+            && let Ok(value) = Interpreter::binary(expr.operator.ttype, l.clone(), r.clone())
+        {
+            return Ok(literal(value, &expr.operator));
+        }
+        Ok(Expr::Binary(BinaryExpr {
+            left: Box::new(left),
+            operator: expr.operator.clone(),
+            right: Box::new(right),
+        }))
+    }
+
+    fn visit_call_expr(&self, expr: &CallExpr) -> Result<Expr, TeciResult> {
+        Ok(Expr::Call(CallExpr {
+            callee: self.boxed(&expr.callee)?,
+            paren: expr.paren.clone(),
+            arguments: expr
+                .arguments
+                .iter()
+                .map(|argument| self.expr(argument))
+                .collect::<Result<_, _>>()?,
+        }))
+    }
+
+    fn visit_conditional_expr(&self, expr: &ConditionalExpr) -> Result<Expr, TeciResult> {
+        let condition = self.condition(&expr.condition)?;
+        if let Some(value) = constant(&condition) {
+            return if Interpreter::is_truthy(value) {
+                self.expr(&expr.then_branch)
+            } else {
+                self.expr(&expr.else_branch)
+            };
+        }
+        Ok(Expr::Conditional(ConditionalExpr {
+            condition: Box::new(condition),
+            then_branch: self.boxed(&expr.then_branch)?,
+            else_branch: self.boxed(&expr.else_branch)?,
+        }))
+    }
+
+    fn visit_get_expr(&self, expr: &GetExpr) -> Result<Expr, TeciResult> {
+        Ok(Expr::Get(GetExpr {
+            object: self.boxed(&expr.object)?,
+            name: expr.name.clone(),
+        }))
+    }
+
+    fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<Expr, TeciResult> {
+        let expression = self.expr(&expr.expression)?;
+        if constant(&expression).is_some() {
+            return Ok(expression);
+        }
+        Ok(Expr::Grouping(GroupingExpr {
+            paren: expr.paren.clone(),
+            expression: Box::new(expression),
+        }))
+    }
+
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> Result<Expr, TeciResult> {
+        let left = self.expr(&expr.left)?;
+        if let Some(value) = constant(&left) {
+            // `or` gives the left side when it is true, `and` when it is false
+            let short_circuits =
+                Interpreter::is_truthy(value) == (expr.operator.ttype == TokenType::Or);
+            return if short_circuits {
+                Ok(left)
+            } else {
+                self.expr(&expr.right)
+            };
+        }
+        Ok(Expr::Logical(LogicalExpr {
+            left: Box::new(left),
+            operator: expr.operator.clone(),
+            right: self.boxed(&expr.right)?,
+        }))
+    }
+
+    fn visit_literal_expr(&self, expr: &LiteralExpr) -> Result<Expr, TeciResult> {
+        Ok(Expr::Literal(expr.clone()))
+    }
+
+    fn visit_match_expr(&self, expr: &MatchExpr) -> Result<Expr, TeciResult> {
+        let arms = expr
+            .arms
+            .iter()
+            .map(|arm| {
+                Ok(MatchArm {
+                    pattern: arm.pattern.clone(),
+                    guard: arm.guard.as_ref().map(|g| self.condition(g)).transpose()?,
+                    body: self.expr(&arm.body)?,
+                })
+            })
+            .collect::<Result<_, TeciResult>>()?;
+        Ok(Expr::Match(MatchExpr {
+            keyword: expr.keyword.clone(),
+            value: self.boxed(&expr.value)?,
+            arms,
+            brace: expr.brace.clone(),
+        }))
+    }
+
+    fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<Expr, TeciResult> {
+        let right = if expr.operator.ttype == TokenType::Bang {
+            self.condition(&expr.right)?
+        } else {
+            self.expr(&expr.right)?
+        };
+        if let Some(value) = constant(&right)
+            && let Ok(value) = Interpreter::unary(expr.operator.ttype, value.clone())
+        {
+            return Ok(literal(value, &expr.operator));
+        }
+        Ok(Expr::Unary(UnaryExpr {
+            operator: expr.operator.clone(),
+            right: Box::new(right),
+        }))
+    }
+
+    fn visit_tuple_expr(&self, expr: &TupleExpr) -> Result<Expr, TeciResult> {
+        Ok(Expr::Tuple(TupleExpr {
+            paren: expr.paren.clone(),
+            elements: expr
+                .elements
+                .iter()
+                .map(|element| self.expr(element))
+                .collect::<Result<_, _>>()?,
+        }))
+    }
+
+    fn visit_tupleassign_expr(&self, expr: &TupleAssignExpr) -> Result<Expr, TeciResult> {
+        Ok(Expr::TupleAssign(TupleAssignExpr {
+            paren: expr.paren.clone(),
+            target: expr.target.clone(),
+            value: self.boxed(&expr.value)?,
+        }))
+    }
+
+    fn visit_update_expr(&self, expr: &UpdateExpr) -> Result<Expr, TeciResult> {
+        Ok(Expr::Update(expr.clone()))
+    }
+
+    fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<Expr, TeciResult> {
+        Ok(Expr::Variable(expr.clone()))
+    }
+}
+
+// Statements that are dropped give None
+impl StmtVisitor<Option<Stmt>> for Optimizer {
+    fn visit_break_stmt(&self, stmt: &BreakStmt) -> Result<Option<Stmt>, TeciResult> {
+        Ok(Some(Stmt::Break(stmt.clone())))
+    }
+
+    fn visit_block_stmt(&self, stmt: &BlockStmt) -> Result<Option<Stmt>, TeciResult> {
+        Ok(Some(Stmt::Block(BlockStmt {
+            brace: stmt.brace.clone(),
+            statements: self.optimize(&stmt.statements)?,
+        })))
+    }
+
+    fn visit_destructure_stmt(&self, stmt: &DestructureStmt) -> Result<Option<Stmt>, TeciResult> {
+        Ok(Some(Stmt::Destructure(DestructureStmt {
+            keyword: stmt.keyword.clone(),
+            pattern: stmt.pattern.clone(),
+            initializer: self.expr(&stmt.initializer)?,
+            constant: stmt.constant,
+        })))
+    }
+
+    fn visit_forin_stmt(&self, stmt: &ForInStmt) -> Result<Option<Stmt>, TeciResult> {
+        Ok(Some(Stmt::ForIn(ForInStmt {
+            keyword: stmt.keyword.clone(),
+            name: stmt.name.clone(),
+            iterable: self.expr(&stmt.iterable)?,
+            body: self.statement(&stmt.body)?,
+        })))
+    }
+
+    fn visit_if_stmt(&self, stmt: &IfStmt) -> Result<Option<Stmt>, TeciResult> {
+        let condition = self.condition(&stmt.condition)?;
+        if let Some(value) = constant(&condition)
+            && !self.in_generator.get()
+        {
+            return if Interpreter::is_truthy(value) {
+                stmt.then_branch.accept(self)
+            } else if let Some(else_branch) = &stmt.else_branch {
+                else_branch.accept(self)
+            } else {
+                Ok(None)
+            };
+        }
+        Ok(Some(Stmt::If(IfStmt {
+            keyword: stmt.keyword.clone(),
+            condition,
+            then_branch: self.statement(&stmt.then_branch)?,
+            else_branch: stmt
+                .else_branch
+                .as_ref()
+                .map(|else_branch| self.statement(else_branch))
+                .transpose()?,
+        })))
+    }
+
+    fn visit_expression_stmt(&self, stmt: &ExpressionStmt) -> Result<Option<Stmt>, TeciResult> {
+        Ok(Some(Stmt::Expression(ExpressionStmt {
+            token: stmt.token.clone(),
+            expression: self.expr(&stmt.expression)?,
+        })))
+    }
+
+    fn visit_function_stmt(&self, stmt: &FunctionStmt) -> Result<Option<Stmt>, TeciResult> {
+        let enclosing = self
+            .in_generator
+            .replace(stmt.body.iter().any(contains_yield));
+        let body = self.optimize(&stmt.body);
+        self.in_generator.set(enclosing);
+        Ok(Some(Stmt::Function(FunctionStmt {
+            name: stmt.name.clone(),
+            params: Rc::clone(&stmt.params),
+            param_types: stmt.param_types.clone(),
+            returns: stmt.returns.clone(),
+            body: Rc::new(body?),
+        })))
+    }
+
+    fn visit_print_stmt(&self, stmt: &PrintStmt) -> Result<Option<Stmt>, TeciResult> {
+        Ok(Some(Stmt::Print(PrintStmt {
+            keyword: stmt.keyword.clone(),
+            expression: self.expr(&stmt.expression)?,
+        })))
+    }
+
+    fn visit_return_stmt(&self, stmt: &ReturnStmt) -> Result<Option<Stmt>, TeciResult> {
+        Ok(Some(Stmt::Return(ReturnStmt {
+            keyword: stmt.keyword.clone(),
+            value: stmt.value.as_ref().map(|v| self.expr(v)).transpose()?,
+        })))
+    }
+
+    fn visit_let_stmt(&self, stmt: &LetStmt) -> Result<Option<Stmt>, TeciResult> {
+        Ok(Some(Stmt::Let(LetStmt {
+            name: stmt.name.clone(),
+            annotation: stmt.annotation.clone(),
+            initializer: stmt
+                .initializer
+                .as_ref()
+                .map(|i| self.expr(i))
+                .transpose()?,
+            constant: stmt.constant,
+        })))
+    }
+
+    fn visit_while_stmt(&self, stmt: &WhileStmt) -> Result<Option<Stmt>, TeciResult> {
+        let condition = self.condition(&stmt.condition)?;
+        if let Some(value) = constant(&condition)
+            && !Interpreter::is_truthy(value)
+            && !self.in_generator.get()
+        {
+            return Ok(None);
+        }
+        Ok(Some(Stmt::While(WhileStmt {
+            keyword: stmt.keyword.clone(),
+            condition,
+            body: self.statement(&stmt.body)?,
+        })))
+    }
+
+    fn visit_yield_stmt(&self, stmt: &YieldStmt) -> Result<Option<Stmt>, TeciResult> {
+        Ok(Some(Stmt::Yield(YieldStmt {
+            keyword: stmt.keyword.clone(),
+            value: self.expr(&stmt.value)?,
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, pretty_printer::AstPrinter, scanner::Scanner};
+
+    fn optimize(source: &str) -> Vec<Stmt> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        Optimizer::new().optimize(&statements).unwrap()
+    }
+
+    #[test]
+    fn t_folding_and_dead_code() {
+        let source = r#"
+print "Fib(" + 1 + ")" + (2 * 3 + 1);
+if (true) print "yes"; else print "no";
+while (false) print "never";
+fun f(x) {
+    if (!!x) return x ? "a" : "b";
+    return 1 / 0;
+    print "dead";
+}
+print !!f;
+"#;
+        let expected = r#"(print "Fib(1)7")
+(print "yes")
+(fun f (x)
+  (if x
+    (return (?: x "a" "b")))
+  (return (/ 1 0)))
+(print (! (! f)))
+"#;
+        assert_eq!(AstPrinter.print(&optimize(source)).unwrap(), expected);
+    }
+
+    #[test]
+    fn t_semantics_preserved() {
+        let interpreter = Interpreter::new();
+        interpreter.set_output(Box::new(std::io::sink()));
+        // Still a generator, even if the `yield` can never run
+        let statements = optimize("fun g() { return; yield 1; }\nlet done = next(g());");
+        assert!(interpreter.interpret(&statements).is_ok());
+
+        match interpreter.interpret(&optimize("let x = 1 / 0;")) {
+            Err(TeciResult::Backtrace { error, .. }) => match *error {
+                TeciResult::RuntimeError { message, .. } => assert_eq!(message, "Division by zero"),
+                error => panic!("unexpected error {:?}", error),
+            },
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+}
@@ -1,22 +1,27 @@
 use crate::{
     error::TeciResult,
     expr::{
-        AssignExpr, BinaryExpr, CallExpr, Expr, GroupingExpr, LiteralExpr, LogicalExpr, UnaryExpr,
+        AssignExpr, BinaryExpr, CallExpr, ConditionalExpr, Expr, GetExpr, GroupingExpr,
+        LiteralExpr, LogicalExpr, MatchExpr, TupleAssignExpr, TupleExpr, UnaryExpr, UpdateExpr,
         VariableExpr,
     },
     object::Object,
+    pattern::{MatchArm, Pattern},
     stmt::{
-        BlockStmt, BreakStmt, ExpressionStmt, FunctionStmt, IfStmt, LetStmt, PrintStmt, ReturnStmt,
-        Stmt, WhileStmt,
+        BlockStmt, BreakStmt, DestructureStmt, ExpressionStmt, ForInStmt, FunctionStmt, IfStmt,
+        LetStmt, PrintStmt, ReturnStmt, Stmt, WhileStmt, YieldStmt,
     },
     token::Token,
     token_type::TokenType,
+    types::Type,
 };
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
     had_error: bool,
+    // Errors that don't stop the parse, like too many arguments
+    errors: Vec<TeciResult>,
 }
 
 impl Parser {
@@ -25,16 +30,51 @@ impl Parser {
             tokens,
             current: 0,
             had_error: false,
+            errors: Vec::new(),
         }
     }
 
     pub fn parse(&mut self) -> Result<Vec<Stmt>, TeciResult> {
+        let (statements, mut errors) = self.parse_partial();
+        errors.iter().for_each(|error| error.report(""));
+        if errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(errors.swap_remove(0))
+        }
+    }
+
+    // Keeps parsing after a syntax error so every error is found, returning the
+    // declarations that did parse along with them. The errors are not
+    // reported, so an editor can parse as the text is typed.
+    pub fn parse_partial(&mut self) -> (Vec<Stmt>, Vec<TeciResult>) {
         let mut statements = Vec::new();
         while !self.is_at_end() {
-            statements.push(self.declaration()?);
+            match self.declaration() {
+                Ok(statement) => statements.push(statement),
+                Err(e) => self.errors.push(e),
+            }
         }
 
-        Ok(statements)
+        (statements, std::mem::take(&mut self.errors))
+    }
+
+    pub fn parse_expression(&mut self) -> Result<Expr, TeciResult> {
+        let result = self.expression().and_then(|expr| {
+            if self.is_at_end() {
+                Ok(expr)
+            } else {
+                Err(self.error(self.peek(), "Expected end of expression"))
+            }
+        });
+        let mut errors = std::mem::take(&mut self.errors);
+        match result {
+            Ok(expr) if errors.is_empty() => return Ok(expr),
+            Ok(_) => {}
+            Err(e) => errors.push(e),
+        }
+        errors.iter().for_each(|error| error.report(""));
+        Err(errors.swap_remove(0))
     }
 
     pub fn succeded(&self) -> bool {
@@ -42,7 +82,7 @@ impl Parser {
     }
 
     fn declaration(&mut self) -> Result<Stmt, TeciResult> {
-        let res = if self.is_match(&[TokenType::Let]) {
+        let res = if self.is_match(&[TokenType::Let, TokenType::Const]) {
             self.let_declaration()
         } else if self.is_match(&[TokenType::Fun]) {
             self.function_declaration("function")
@@ -57,20 +97,131 @@ impl Parser {
         res
     }
 
+    // `let` or `const`, after the keyword
     fn let_declaration(&mut self) -> Result<Stmt, TeciResult> {
+        let keyword = self.previous();
+        if self.is_match(&[TokenType::LeftParen]) {
+            return self.destructure_declaration(keyword);
+        }
         let name = self.consume(TokenType::Identifier, "Expected variable name")?;
-
-        let initializer = if self.is_match(&[TokenType::Assign]) {
-            Some(self.expression()?)
+        let constant = keyword.ttype == TokenType::Const;
+        let annotation = if self.is_match(&[TokenType::Colon]) {
+            Some(self.type_annotation()?)
         } else {
             None
         };
+
+        let initializer = self.let_initializer(constant)?;
+        if annotation.is_none() && self.check(TokenType::Comma) {
+            return self.declarators(keyword, name, initializer);
+        }
         self.consume(
             TokenType::Semicolon,
             "Expected ';' after variable declaration",
         )?;
 
-        Ok(Stmt::Let(LetStmt { name, initializer }))
+        Ok(Stmt::Let(LetStmt {
+            name,
+            annotation,
+            initializer,
+            constant,
+        }))
+    }
+
+    // `= value` after a declared name. Commas after it separate declarations,
+    // so a tuple has to be in parentheses.
+    fn let_initializer(&mut self, constant: bool) -> Result<Option<Expr>, TeciResult> {
+        if self.is_match(&[TokenType::Assign]) {
+            Ok(Some(self.assignment()?))
+        } else if constant {
+            Err(self.error(self.peek(), "Expected '=' after constant name"))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // `let a = 1, b;`, after the first initializer. The names are declared
+    // together, like `let (a, b) = (1, nil);`, so an initializer can't see the
+    // names before it.
+    fn declarators(
+        &mut self,
+        keyword: Token,
+        name: Token,
+        initializer: Option<Expr>,
+    ) -> Result<Stmt, TeciResult> {
+        let constant = keyword.ttype == TokenType::Const;
+        let nil = |name: &Token| {
+            Expr::Literal(LiteralExpr {
+                token: name.clone(),
+                value: Some(Object::Nil),
+            })
+        };
+        let mut elements = vec![initializer.unwrap_or_else(|| nil(&name))];
+        let mut names = vec![Pattern::Binding(name)];
+        while self.is_match(&[TokenType::Comma]) {
+            let name = self.consume(TokenType::Identifier, "Expected variable name")?;
+            elements.push(match self.let_initializer(constant)? {
+                Some(initializer) => initializer,
+                None => nil(&name),
+            });
+            names.push(Pattern::Binding(name));
+        }
+        self.consume(
+            TokenType::Semicolon,
+            "Expected ';' after variable declaration",
+        )?;
+
+        Ok(Stmt::Destructure(DestructureStmt {
+            pattern: Pattern::Tuple(keyword.clone(), names),
+            initializer: Expr::Tuple(TupleExpr {
+                paren: keyword.clone(),
+                elements,
+            }),
+            keyword,
+            constant,
+        }))
+    }
+
+    // `let (a, (b, _)) = value;`, after the `(`
+    fn destructure_declaration(&mut self, keyword: Token) -> Result<Stmt, TeciResult> {
+        let pattern = self.let_pattern()?;
+        self.consume(TokenType::Assign, "Expected '=' after the pattern")?;
+        let initializer = self.expression()?;
+        self.consume(
+            TokenType::Semicolon,
+            "Expected ';' after variable declaration",
+        )?;
+
+        Ok(Stmt::Destructure(DestructureStmt {
+            constant: keyword.ttype == TokenType::Const,
+            keyword,
+            pattern,
+            initializer,
+        }))
+    }
+
+    // A tuple of names, `_` and tuples of them, after the `(`
+    fn let_pattern(&mut self) -> Result<Pattern, TeciResult> {
+        let paren = self.previous();
+        let mut patterns = Vec::new();
+        while !self.check(TokenType::RightParen) {
+            if self.is_match(&[TokenType::LeftParen]) {
+                patterns.push(self.let_pattern()?);
+            } else {
+                let name = self.consume(TokenType::Identifier, "Expected variable name")?;
+                patterns.push(if name.lexeme == "_" {
+                    Pattern::Wildcard
+                } else {
+                    Pattern::Binding(name)
+                });
+            }
+            if !self.is_match(&[TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(TokenType::RightParen, "Expected ')' after the pattern")?;
+
+        Ok(Pattern::Tuple(paren, patterns))
     }
 
     fn function_declaration(&mut self, kind: &str) -> Result<Stmt, TeciResult> {
@@ -81,16 +232,18 @@ impl Parser {
         )?;
 
         let mut params = Vec::new();
+        let mut param_types = Vec::new();
         if !self.check(TokenType::RightParen) {
             loop {
                 if params.len() == 255 {
-                    let err = TeciResult::parse_error(
+                    let err = self.error(
                         self.peek(),
                         "Function calls cannot accept more than 255 arguments",
                     );
-                    err.report("");
+                    self.errors.push(err);
                 } else {
                     params.push(self.consume(TokenType::Identifier, "Expected parameter name")?);
+                    param_types.push(self.optional_annotation(TokenType::Colon)?);
                 }
 
                 if !self.is_match(&[TokenType::Comma]) {
@@ -99,6 +252,7 @@ impl Parser {
             }
         }
         self.consume(TokenType::RightParen, "Expected ')' after parameters")?;
+        let returns = self.optional_annotation(TokenType::Arrow)?;
 
         self.consume(
             TokenType::LeftBrace,
@@ -109,10 +263,86 @@ impl Parser {
         Ok(Stmt::Function(FunctionStmt {
             name,
             params: params.into(),
+            param_types,
+            returns,
             body: body.into(),
         }))
     }
 
+    // The type after `separator` if there is one, `any` if not
+    fn optional_annotation(&mut self, separator: TokenType) -> Result<Type, TeciResult> {
+        if self.is_match(&[separator]) {
+            self.type_annotation()
+        } else {
+            Ok(Type::Any)
+        }
+    }
+
+    // `num`, `str?`, `list<T>`, `(T, U)` or `fun(T, U) -> V`
+    fn type_annotation(&mut self) -> Result<Type, TeciResult> {
+        let annotation = if self.is_match(&[TokenType::LeftParen]) {
+            Type::Tuple(self.type_list()?)
+        } else if self.is_match(&[TokenType::Nil]) {
+            Type::Nil
+        } else if self.is_match(&[TokenType::Fun]) {
+            if self.is_match(&[TokenType::LeftParen]) {
+                let params = self.type_list()?;
+                self.consume(TokenType::Arrow, "Expected '->' after parameter types")?;
+                Type::function(params, self.type_annotation()?)
+            } else {
+                Type::Fun(None)
+            }
+        } else {
+            let name = self.consume(TokenType::Identifier, "Expected a type")?;
+            match name.lexeme.as_str() {
+                "list" => {
+                    self.consume(TokenType::Less, "Expected '<' after 'list'")?;
+                    let element = self.type_annotation()?;
+                    self.close_angle_bracket()?;
+                    Type::List(Box::new(element))
+                }
+                lexeme => match Type::named(lexeme) {
+                    Some(annotation) => annotation,
+                    None => {
+                        let message = format!("Unknown type '{}'", lexeme);
+                        return Err(self.error(name, &message));
+                    }
+                },
+            }
+        };
+
+        if self.is_match(&[TokenType::Question]) {
+            return Ok(annotation.nullable());
+        }
+        Ok(annotation)
+    }
+
+    // Comma separated types up to a `)`, after the `(`
+    fn type_list(&mut self) -> Result<Vec<Type>, TeciResult> {
+        let mut types = Vec::new();
+        while !self.check(TokenType::RightParen) {
+            types.push(self.type_annotation()?);
+            if !self.is_match(&[TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(TokenType::RightParen, "Expected ')' after types")?;
+        Ok(types)
+    }
+
+    // The `>` closing a `list<`, which may be half of a `>>` in `list<list<T>>`
+    fn close_angle_bracket(&mut self) -> Result<(), TeciResult> {
+        if self.check(TokenType::GreaterGreater) {
+            let token = &mut self.tokens[self.current];
+            token.ttype = TokenType::Greater;
+            token.lexeme = ">".into();
+            token.column += 1;
+            return Ok(());
+        }
+        self.consume(TokenType::Greater, "Expected '>' after the element type")?;
+        Ok(())
+    }
+
     fn statement(&mut self) -> Result<Stmt, TeciResult> {
         if self.is_match(&[TokenType::Break]) {
             self.break_statement()
@@ -122,6 +352,8 @@ impl Parser {
             self.print_statement()
         } else if self.is_match(&[TokenType::Return]) {
             self.return_statement()
+        } else if self.is_match(&[TokenType::Yield]) {
+            self.yield_statement()
         } else if self.is_match(&[TokenType::While]) {
             self.while_statement()
         } else if self.is_match(&[TokenType::For]) {
@@ -129,6 +361,7 @@ impl Parser {
         } else if self.is_match(&[TokenType::LeftBrace]) {
             // I do this in order to be able to reuse the self.block() for other block parsing in the future
             Ok(Stmt::Block(BlockStmt {
+                brace: self.previous(),
                 statements: self.block()?,
             }))
         } else {
@@ -137,14 +370,18 @@ impl Parser {
     }
 
     fn print_statement(&mut self) -> Result<Stmt, TeciResult> {
+        let keyword = self.previous();
         let expr = self.expression()?;
         self.consume(TokenType::Semicolon, "Expected ';' after expression")?;
 
-        Ok(Stmt::Print(PrintStmt { expression: expr }))
+        Ok(Stmt::Print(PrintStmt {
+            keyword,
+            expression: expr,
+        }))
     }
 
     fn return_statement(&mut self) -> Result<Stmt, TeciResult> {
-        let _keyword = self.previous();
+        let keyword = self.previous();
 
         let value = if self.check(TokenType::Semicolon) {
             None
@@ -153,14 +390,26 @@ impl Parser {
         };
         self.consume(TokenType::Semicolon, "Expected ';' after return value")?;
 
-        Ok(Stmt::Return(ReturnStmt { _keyword, value }))
+        Ok(Stmt::Return(ReturnStmt { keyword, value }))
+    }
+
+    fn yield_statement(&mut self) -> Result<Stmt, TeciResult> {
+        let keyword = self.previous();
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expected ';' after yield value")?;
+
+        Ok(Stmt::Yield(YieldStmt { keyword, value }))
     }
 
     fn expr_statement(&mut self) -> Result<Stmt, TeciResult> {
+        let token = self.peek();
         let expr = self.expression()?;
         self.consume(TokenType::Semicolon, "Expected ';' after expression")?;
 
-        Ok(Stmt::Expression(ExpressionStmt { expression: expr }))
+        Ok(Stmt::Expression(ExpressionStmt {
+            token,
+            expression: expr,
+        }))
     }
 
     fn block(&mut self) -> Result<Vec<Stmt>, TeciResult> {
@@ -174,6 +423,7 @@ impl Parser {
     }
 
     fn if_statement(&mut self) -> Result<Stmt, TeciResult> {
+        let keyword = self.previous();
         self.consume(TokenType::LeftParen, "Expected '(' after 'if'")?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, "Expected ')' after if condition")?;
@@ -186,6 +436,7 @@ impl Parser {
         };
 
         Ok(Stmt::If(IfStmt {
+            keyword,
             condition,
             then_branch,
             else_branch,
@@ -193,17 +444,27 @@ impl Parser {
     }
 
     fn while_statement(&mut self) -> Result<Stmt, TeciResult> {
+        let keyword = self.previous();
         self.consume(TokenType::LeftParen, "Expected '(' after 'while'")?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, "Expected ')' after while condition")?;
         let body = Box::new(self.statement()?);
 
-        Ok(Stmt::While(WhileStmt { condition, body }))
+        Ok(Stmt::While(WhileStmt {
+            keyword,
+            condition,
+            body,
+        }))
     }
 
     fn for_statement(&mut self) -> Result<Stmt, TeciResult> {
+        let keyword = self.previous();
         self.consume(TokenType::LeftParen, "Expected '(' after 'for'")?;
 
+        if self.check(TokenType::Identifier) && self.check_next(TokenType::In) {
+            return self.for_in_statement(keyword);
+        }
+
         let initializer = if self.is_match(&[TokenType::Semicolon]) {
             None
         } else if self.is_match(&[TokenType::Let]) {
@@ -219,6 +480,7 @@ impl Parser {
         };
         self.consume(TokenType::Semicolon, "Expected ';' after loop condition")?;
 
+        let increment_token = self.peek();
         let increment = if self.check(TokenType::RightParen) {
             None
         } else {
@@ -230,15 +492,24 @@ impl Parser {
 
         if let Some(inc) = increment {
             body = Stmt::Block(BlockStmt {
-                statements: vec![body, Stmt::Expression(ExpressionStmt { expression: inc })],
+                brace: keyword.clone(),
+                statements: vec![
+                    body,
+                    Stmt::Expression(ExpressionStmt {
+                        token: increment_token,
+                        expression: inc,
+                    }),
+                ],
             });
         }
 
         body = Stmt::While(WhileStmt {
+            keyword: keyword.clone(),
             condition: if let Some(cond) = condition {
                 cond
             } else {
                 Expr::Literal(LiteralExpr {
+                    token: keyword.clone(),
                     value: Some(Object::Bool(true)),
                 })
             },
@@ -247,6 +518,7 @@ impl Parser {
 
         if let Some(init) = initializer {
             body = Stmt::Block(BlockStmt {
+                brace: keyword,
                 statements: vec![init, body],
             });
         }
@@ -254,48 +526,151 @@ impl Parser {
         Ok(body)
     }
 
+    // `for (name in iterable) body`, after the '('
+    fn for_in_statement(&mut self, keyword: Token) -> Result<Stmt, TeciResult> {
+        let name = self.advance();
+        self.advance();
+        let iterable = self.expression()?;
+        self.consume(TokenType::RightParen, "Expected ')' after iterable")?;
+        let body = self.statement()?;
+
+        Ok(Stmt::ForIn(ForInStmt {
+            keyword,
+            name,
+            iterable,
+            body: Box::new(body),
+        }))
+    }
+
     fn break_statement(&mut self) -> Result<Stmt, TeciResult> {
         self.consume(TokenType::Semicolon, "Expected ';' after 'break'")?;
 
         Ok(Stmt::Break(BreakStmt {
-            token: Token::new(
-                TokenType::Break,
-                "break".to_string(),
-                None,
-                self.previous().line,
-            ),
+            token: Token::new(TokenType::Break, "break", None, self.previous().line),
         }))
     }
 
     fn expression(&mut self) -> Result<Expr, TeciResult> {
-        self.assignment()
-        /* TODO: comma expressions -> create new kind of expression and parse it
-        let mut expr = self.equality()?;
+        self.comma()
+    }
+
+    // Commas make tuples wherever an expression goes, with or without
+    // parentheses: `return a, b;` and `(a, b)` are the same tuple. Where
+    // commas separate things, like call arguments, the parts are parsed with
+    // `assignment` instead.
+    fn comma(&mut self) -> Result<Expr, TeciResult> {
+        let expr = self.assignment()?;
+        if !self.check(TokenType::Comma) {
+            return Ok(expr);
+        }
+
+        let paren = self.peek();
+        let mut elements = vec![expr];
         while self.is_match(&[TokenType::Comma]) {
-            expr = self.equality()?;
+            elements.push(self.assignment()?);
         }
-        Ok(expr) */
+
+        Ok(Expr::Tuple(TupleExpr { paren, elements }))
     }
 
     fn assignment(&mut self) -> Result<Expr, TeciResult> {
-        let expr = self.or()?;
-
-        if self.is_match(&[TokenType::Assign]) {
+        let expr = self.conditional()?;
+
+        if self.is_match(&[
+            TokenType::Assign,
+            TokenType::PlusAssign,
+            TokenType::MinusAssign,
+            TokenType::StarAssign,
+            TokenType::SlashAssign,
+            TokenType::PercentAssign,
+        ]) {
             let equals = self.previous();
             let value = self.assignment()?;
             if let Expr::Variable(var_exp) = expr {
                 let name = var_exp.name;
+                let value = match Parser::compound_operator(&equals) {
+                    // `a += b` is `a = a + b`
+                    Some(operator) => Expr::Binary(BinaryExpr {
+                        left: Box::new(Expr::Variable(VariableExpr { name: name.clone() })),
+                        operator,
+                        right: Box::new(value),
+                    }),
+                    None => value,
+                };
                 return Ok(Expr::Assign(AssignExpr {
                     name,
                     value: Box::new(value),
                 }));
             }
+            // `(a, b) = (b, a)`
+            if let Expr::Tuple(tuple) = &expr
+                && equals.ttype == TokenType::Assign
+                && let Some(target) = Parser::assignment_pattern(&expr)
+            {
+                return Ok(Expr::TupleAssign(TupleAssignExpr {
+                    paren: tuple.paren.clone(),
+                    target,
+                    value: Box::new(value),
+                }));
+            }
             self.error(equals, "Invalid assignment target");
         }
 
         Ok(expr)
     }
 
+    // The pattern assigning to a tuple of variables assigns with, if it is one
+    fn assignment_pattern(target: &Expr) -> Option<Pattern> {
+        match target {
+            Expr::Variable(var_exp) if var_exp.name.lexeme == "_" => Some(Pattern::Wildcard),
+            Expr::Variable(var_exp) => Some(Pattern::Binding(var_exp.name.clone())),
+            Expr::Tuple(tuple) => Some(Pattern::Tuple(
+                tuple.paren.clone(),
+                tuple
+                    .elements
+                    .iter()
+                    .map(Parser::assignment_pattern)
+                    .collect::<Option<_>>()?,
+            )),
+            _ => None,
+        }
+    }
+
+    // The operator a compound assignment applies, as a token of its own
+    fn compound_operator(equals: &Token) -> Option<Token> {
+        let ttype = match equals.ttype {
+            TokenType::PlusAssign => TokenType::Plus,
+            TokenType::MinusAssign => TokenType::Minus,
+            TokenType::StarAssign => TokenType::Star,
+            TokenType::SlashAssign => TokenType::Slash,
+            TokenType::PercentAssign => TokenType::Percent,
+            _ => return None,
+        };
+        let lexeme = equals.lexeme.trim_end_matches('=').into();
+        Some(Token {
+            ttype,
+            lexeme,
+            ..equals.clone()
+        })
+    }
+
+    // `a ? b : c ? d : e` is `a ? b : (c ? d : e)`
+    fn conditional(&mut self) -> Result<Expr, TeciResult> {
+        let expr = self.or()?;
+        if self.is_match(&[TokenType::Question]) {
+            let then_branch = self.expression()?;
+            self.consume(TokenType::Colon, "Expected ':' after then branch of '?'")?;
+            let else_branch = self.conditional()?;
+            return Ok(Expr::Conditional(ConditionalExpr {
+                condition: Box::new(expr),
+                then_branch: Box::new(then_branch),
+                else_branch: Box::new(else_branch),
+            }));
+        }
+
+        Ok(expr)
+    }
+
     fn or(&mut self) -> Result<Expr, TeciResult> {
         let mut expr = self.and()?;
         while self.is_match(&[TokenType::Or]) {
@@ -342,13 +717,73 @@ impl Parser {
     }
 
     fn comparison(&mut self) -> Result<Expr, TeciResult> {
-        let mut expr = self.term()?;
+        let mut expr = self.bit_or()?;
         while self.is_match(&[
             TokenType::Greater,
             TokenType::GreaterEqual,
             TokenType::Less,
             TokenType::LessEqual,
         ]) {
+            let operator = self.previous();
+            let right = self.bit_or()?;
+            expr = Expr::Binary(BinaryExpr {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_or(&mut self) -> Result<Expr, TeciResult> {
+        let mut expr = self.bit_xor()?;
+        while self.is_match(&[TokenType::Pipe]) {
+            let operator = self.previous();
+            let right = self.bit_xor()?;
+            expr = Expr::Binary(BinaryExpr {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_xor(&mut self) -> Result<Expr, TeciResult> {
+        let mut expr = self.bit_and()?;
+        while self.is_match(&[TokenType::Caret]) {
+            let operator = self.previous();
+            let right = self.bit_and()?;
+            expr = Expr::Binary(BinaryExpr {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_and(&mut self) -> Result<Expr, TeciResult> {
+        let mut expr = self.shift()?;
+        while self.is_match(&[TokenType::Ampersand]) {
+            let operator = self.previous();
+            let right = self.shift()?;
+            expr = Expr::Binary(BinaryExpr {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    fn shift(&mut self) -> Result<Expr, TeciResult> {
+        let mut expr = self.term()?;
+        while self.is_match(&[TokenType::LessLess, TokenType::GreaterGreater]) {
             let operator = self.previous();
             let right = self.term()?;
             expr = Expr::Binary(BinaryExpr {
@@ -378,7 +813,12 @@ impl Parser {
 
     fn factor(&mut self) -> Result<Expr, TeciResult> {
         let mut expr = self.unary()?;
-        while self.is_match(&[TokenType::Star, TokenType::Slash]) {
+        while self.is_match(&[
+            TokenType::Star,
+            TokenType::Slash,
+            TokenType::TildeSlash,
+            TokenType::Percent,
+        ]) {
             let operator = self.previous();
             let right = self.unary()?;
             expr = Expr::Binary(BinaryExpr {
@@ -392,15 +832,51 @@ impl Parser {
     }
 
     fn unary(&mut self) -> Result<Expr, TeciResult> {
-        if self.is_match(&[TokenType::Bang, TokenType::Minus]) {
+        if self.is_match(&[TokenType::Bang, TokenType::Minus, TokenType::Tilde]) {
             let operator = self.previous();
             let right = self.unary()?;
             Ok(Expr::Unary(UnaryExpr {
                 operator,
                 right: Box::new(right),
             }))
+        } else if self.is_match(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let operator = self.previous();
+            let target = self.unary()?;
+            Ok(self.update(target, operator, true))
         } else {
-            self.call()
+            self.power()
+        }
+    }
+
+    // `**` binds tighter than a unary operator on its left (`-2 ** 2` is -4)
+    // but not on its right, and groups to the right
+    fn power(&mut self) -> Result<Expr, TeciResult> {
+        let expr = self.call()?;
+        if self.is_match(&[TokenType::StarStar]) {
+            let operator = self.previous();
+            let right = self.unary()?;
+            return Ok(Expr::Binary(BinaryExpr {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            }));
+        }
+
+        Ok(expr)
+    }
+
+    // `++` or `--` on `target`, which has to be a variable
+    fn update(&mut self, target: Expr, operator: Token, prefix: bool) -> Expr {
+        match target {
+            Expr::Variable(var_exp) => Expr::Update(UpdateExpr {
+                name: var_exp.name,
+                operator,
+                prefix,
+            }),
+            target => {
+                self.error(operator, "Invalid increment target");
+                target
+            }
         }
     }
 
@@ -409,11 +885,23 @@ impl Parser {
         loop {
             if self.is_match(&[TokenType::LeftParen]) {
                 expr = self.finish_call(&expr)?;
+            } else if self.is_match(&[TokenType::Dot]) {
+                let name =
+                    self.consume(TokenType::Identifier, "Expected a property name after '.'")?;
+                expr = Expr::Get(GetExpr {
+                    object: Box::new(expr),
+                    name,
+                });
             } else {
                 break;
             }
         }
 
+        if self.is_match(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let operator = self.previous();
+            expr = self.update(expr, operator, false);
+        }
+
         Ok(expr)
     }
 
@@ -423,13 +911,13 @@ impl Parser {
         if !self.check(TokenType::RightParen) {
             loop {
                 if arguments.len() == 255 {
-                    let err = TeciResult::parse_error(
+                    let err = self.error(
                         self.peek(),
                         "Function calls cannot accept more than 255 arguments",
                     );
-                    err.report("");
+                    self.errors.push(err);
                 } else {
-                    arguments.push(self.expression()?);
+                    arguments.push(self.assignment()?);
                 }
 
                 if !self.is_match(&[TokenType::Comma]) {
@@ -449,30 +937,34 @@ impl Parser {
     fn primary(&mut self) -> Result<Expr, TeciResult> {
         if self.is_match(&[TokenType::False]) {
             return Ok(Expr::Literal(LiteralExpr {
+                token: self.previous(),
                 value: Some(Object::Bool(false)),
             }));
         }
         if self.is_match(&[TokenType::True]) {
             return Ok(Expr::Literal(LiteralExpr {
+                token: self.previous(),
                 value: Some(Object::Bool(true)),
             }));
         }
         if self.is_match(&[TokenType::Nil]) {
             return Ok(Expr::Literal(LiteralExpr {
+                token: self.previous(),
                 value: Some(Object::Nil),
             }));
         }
         if self.is_match(&[TokenType::String, TokenType::Number]) {
+            let token = self.previous();
             return Ok(Expr::Literal(LiteralExpr {
-                value: self.previous().literal,
+                value: token.literal.clone(),
+                token,
             }));
         }
+        if self.is_match(&[TokenType::Match]) {
+            return self.match_expression();
+        }
         if self.is_match(&[TokenType::LeftParen]) {
-            let expr = self.expression()?;
-            self.consume(TokenType::RightParen, "Expected ')' after expression")?;
-            return Ok(Expr::Grouping(GroupingExpr {
-                expression: Box::new(expr),
-            }));
+            return self.parenthesized();
         }
         if self.is_match(&[TokenType::Identifier]) {
             return Ok(Expr::Variable(VariableExpr {
@@ -480,7 +972,136 @@ impl Parser {
             }));
         }
 
-        Err(TeciResult::teci_error(0, "unimplemented (yet)"))
+        Err(self.error(self.peek(), "Expected expression"))
+    }
+
+    // A grouping, or a tuple if there is a comma: `()`, `(a,)`, `(a, b)`
+    fn parenthesized(&mut self) -> Result<Expr, TeciResult> {
+        let paren = self.previous();
+        let mut elements = Vec::new();
+        let mut is_tuple = self.check(TokenType::RightParen);
+        while !self.check(TokenType::RightParen) {
+            elements.push(self.assignment()?);
+            if !self.is_match(&[TokenType::Comma]) {
+                break;
+            }
+            is_tuple = true;
+        }
+        self.consume(TokenType::RightParen, "Expected ')' after expression")?;
+
+        if is_tuple {
+            return Ok(Expr::Tuple(TupleExpr { paren, elements }));
+        }
+        Ok(Expr::Grouping(GroupingExpr {
+            paren,
+            expression: Box::new(elements.pop().unwrap()),
+        }))
+    }
+
+    // `match value { pattern if guard => body, .. }`, after the `match`
+    fn match_expression(&mut self) -> Result<Expr, TeciResult> {
+        let keyword = self.previous();
+        let value = self.expression()?;
+        self.consume(TokenType::LeftBrace, "Expected '{' after match value")?;
+
+        let mut arms = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            let pattern = self.pattern()?;
+            let guard = if self.is_match(&[TokenType::If]) {
+                Some(self.expression()?)
+            } else {
+                None
+            };
+            self.consume(TokenType::FatArrow, "Expected '=>' after pattern")?;
+            let body = self.assignment()?;
+            arms.push(MatchArm {
+                pattern,
+                guard,
+                body,
+            });
+            if !self.is_match(&[TokenType::Comma]) {
+                break;
+            }
+        }
+        let brace = self.consume(TokenType::RightBrace, "Expected '}' after match arms")?;
+
+        Ok(Expr::Match(MatchExpr {
+            keyword,
+            value: Box::new(value),
+            arms,
+            brace,
+        }))
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, TeciResult> {
+        let mut alternatives = vec![self.single_pattern()?];
+        while self.is_match(&[TokenType::Pipe]) {
+            alternatives.push(self.single_pattern()?);
+        }
+        if alternatives.len() == 1 {
+            return Ok(alternatives.pop().unwrap());
+        }
+
+        let pattern = Pattern::Alternatives(alternatives);
+        if let Some(name) = pattern.bindings().first() {
+            let name = (*name).clone();
+            return Err(self.error(name, "Cannot bind names in alternative patterns"));
+        }
+        Ok(pattern)
+    }
+
+    fn single_pattern(&mut self) -> Result<Pattern, TeciResult> {
+        if self.is_match(&[TokenType::LeftParen]) {
+            let paren = self.previous();
+            let mut patterns = Vec::new();
+            while !self.check(TokenType::RightParen) {
+                patterns.push(self.pattern()?);
+                if !self.is_match(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+            self.consume(TokenType::RightParen, "Expected ')' after the pattern")?;
+            return Ok(Pattern::Tuple(paren, patterns));
+        }
+        if self.is_match(&[TokenType::Identifier]) {
+            let name = self.previous();
+            return Ok(if name.lexeme == "_" {
+                Pattern::Wildcard
+            } else {
+                Pattern::Binding(name)
+            });
+        }
+
+        let (token, start) = self.pattern_literal()?;
+        if self.is_match(&[TokenType::DotDot]) {
+            let (_, end) = self.pattern_literal()?;
+            if !start.is_number() || !end.is_number() {
+                return Err(self.error(token, "Range patterns need numbers"));
+            }
+            return Ok(Pattern::Range(start, end));
+        }
+        Ok(Pattern::Literal(start))
+    }
+
+    // A literal in a pattern, where numbers may have a `-`
+    fn pattern_literal(&mut self) -> Result<(Token, Object), TeciResult> {
+        let negative = self.is_match(&[TokenType::Minus]);
+        let token = self.peek();
+        let value = match token.ttype {
+            TokenType::Number => token.literal.clone().unwrap(),
+            TokenType::String if !negative => token.literal.clone().unwrap(),
+            TokenType::True if !negative => Object::Bool(true),
+            TokenType::False if !negative => Object::Bool(false),
+            TokenType::Nil if !negative => Object::Nil,
+            _ => return Err(self.error(token, "Expected a pattern")),
+        };
+        self.advance();
+
+        if negative {
+            let value = (-value).expect("numbers can always be negated");
+            return Ok((token, value));
+        }
+        Ok((token, value))
     }
 
     fn consume(&mut self, ttype: TokenType, error_message: &str) -> Result<Token, TeciResult> {
@@ -491,9 +1112,13 @@ impl Parser {
         }
     }
 
+    // The error is not reported here, see `parse_partial`
     fn error(&mut self, token: Token, message: &str) -> TeciResult {
         self.had_error = true;
-        TeciResult::parse_error(token, message)
+        TeciResult::ParseError {
+            token,
+            message: message.to_string(),
+        }
     }
 
     fn synchronize(&mut self) {
@@ -507,11 +1132,13 @@ impl Parser {
                 TokenType::Class
                     | TokenType::Fun
                     | TokenType::Let
+                    | TokenType::Const
                     | TokenType::For
                     | TokenType::If
                     | TokenType::While
                     | TokenType::Print
                     | TokenType::Return
+                    | TokenType::Yield
             ) {
                 return;
             }
@@ -537,6 +1164,13 @@ impl Parser {
         }
     }
 
+    // Like `check`, for the token after the next one
+    fn check_next(&self, ttype: TokenType) -> bool {
+        self.tokens
+            .get(self.current + 1)
+            .is_some_and(|token| token.ttype == ttype)
+    }
+
     fn peek(&self) -> Token {
         self.tokens.get(self.current).unwrap().clone()
     }
@@ -0,0 +1,133 @@
+use crate::{error::TeciResult, expr::Expr, object::Object, token::Token};
+
+#[derive(Clone)]
+pub enum Pattern {
+    // `_`, matching anything
+    Wildcard,
+    // A name, matching anything and binding it to the name
+    Binding(Token),
+    // A number, string, boolean or nil, matching values equal to it
+    Literal(Object),
+    // `start..end`, matching the numbers from `start` up to `end`, not
+    // included, like `range`
+    Range(Object, Object),
+    // `a | b`, matching what any of the patterns matches. They bind nothing.
+    Alternatives(Vec<Pattern>),
+    // `(a, b)`, matching tuples of as many values, each matching its pattern.
+    // The token is the `(`, for errors.
+    Tuple(Token, Vec<Pattern>),
+}
+
+// `pattern if guard => body` in a `match`
+#[derive(Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: Expr,
+}
+
+impl Pattern {
+    // Whether `value` matches, adding the names it binds to `bindings`
+    pub fn matches(&self, value: &Object, bindings: &mut Vec<(Token, Object)>) -> bool {
+        match self {
+            Pattern::Wildcard => true,
+            Pattern::Binding(name) => {
+                bindings.push((name.clone(), value.clone()));
+                true
+            }
+            Pattern::Literal(literal) => value == literal,
+            Pattern::Range(start, end) => value.is_number() && start <= value && value < end,
+            Pattern::Alternatives(alternatives) => alternatives
+                .iter()
+                .any(|pattern| pattern.matches(value, bindings)),
+            Pattern::Tuple(_, patterns) => match value {
+                Object::Tuple(values) if values.len() == patterns.len() => patterns
+                    .iter()
+                    .zip(values.iter())
+                    .all(|(pattern, value)| pattern.matches(value, bindings)),
+                _ => false,
+            },
+        }
+    }
+
+    // The names `value` binds to in `let pattern = value;`, where the value
+    // must match. Only names, `_` and tuples of them make it here.
+    pub fn destructure(
+        &self,
+        value: Object,
+        bindings: &mut Vec<(Token, Object)>,
+    ) -> Result<(), TeciResult> {
+        match (self, value) {
+            (Pattern::Binding(name), value) => bindings.push((name.clone(), value)),
+            (Pattern::Tuple(_, patterns), Object::Tuple(values))
+                if values.len() == patterns.len() =>
+            {
+                for (pattern, value) in patterns.iter().zip(values.iter()) {
+                    pattern.destructure(value.clone(), bindings)?;
+                }
+            }
+            (Pattern::Tuple(paren, patterns), Object::Tuple(values)) => {
+                return Err(TeciResult::runtime_error(
+                    paren.clone(),
+                    &format!(
+                        "Expected a tuple of {} values, found {} values",
+                        patterns.len(),
+                        values.len()
+                    ),
+                ));
+            }
+            (Pattern::Tuple(paren, patterns), value) => {
+                return Err(TeciResult::runtime_error(
+                    paren.clone(),
+                    &format!(
+                        "Expected a tuple of {} values, found a {}",
+                        patterns.len(),
+                        value.type_name()
+                    ),
+                ));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // The names the pattern binds
+    pub fn bindings(&self) -> Vec<&Token> {
+        match self {
+            Pattern::Binding(name) => vec![name],
+            Pattern::Alternatives(patterns) | Pattern::Tuple(_, patterns) => {
+                patterns.iter().flat_map(Pattern::bindings).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token_type::TokenType;
+
+    #[test]
+    fn t_matches() {
+        let token = |lexeme: &str| Token::new(TokenType::Identifier, lexeme, None, 1);
+        let int = |i| Pattern::Literal(Object::Int(i));
+        let mut bindings = Vec::new();
+
+        assert!(int(1).matches(&Object::Num(1.0), &mut bindings));
+        assert!(!int(1).matches(&Object::Str("1".into()), &mut bindings));
+        let range = Pattern::Range(Object::Int(1), Object::Int(5));
+        assert!(range.matches(&Object::Int(1), &mut bindings));
+        assert!(range.matches(&Object::Num(4.5), &mut bindings));
+        assert!(!range.matches(&Object::Int(5), &mut bindings));
+        assert!(!range.matches(&Object::Nil, &mut bindings));
+        let alternatives = Pattern::Alternatives(vec![int(1), int(2)]);
+        assert!(alternatives.matches(&Object::Int(2), &mut bindings));
+        assert!(!alternatives.matches(&Object::Int(3), &mut bindings));
+        assert!(bindings.is_empty());
+
+        assert!(Pattern::Binding(token("n")).matches(&Object::Int(3), &mut bindings));
+        assert_eq!(bindings[0].0.lexeme, "n");
+        assert_eq!(bindings[0].1, Object::Int(3));
+    }
+}
@@ -1,40 +1,209 @@
-use crate::{error::TeciError, expr::*};
+use crate::{error::TeciResult, expr::*, object::Object, pattern::Pattern, stmt::*};
 
+// Prints the tree as s-expressions, one statement per line and the
+// statements inside others indented, for `--dump-ast`
 pub struct AstPrinter;
 
 impl AstPrinter {
-    pub fn print(&self, expr: &Expr) -> Result<String, TeciError> {
-        expr.accept(self)
+    pub fn print(&self, statements: &[Stmt]) -> Result<String, TeciResult> {
+        let mut printed = String::new();
+        for statement in statements {
+            printed += &statement.accept(self)?;
+            printed.push('\n');
+        }
+        Ok(printed)
     }
 
-    fn parenthesize(&self, lex: &String, exprs: &[Box<Expr>]) -> Result<String, TeciError> {
-        let mut builder = format!("({lex}");
+    fn parenthesize(&self, name: &str, exprs: &[&Expr]) -> Result<String, TeciResult> {
+        let mut builder = format!("({name}");
         for expr in exprs {
             builder = format!("{builder} {}", expr.accept(self)?);
         }
-        builder = format!("{builder})");
+        builder.push(')');
+        Ok(builder)
+    }
+
+    // A statement with the statements inside it indented on their own lines
+    fn nest(&self, head: String, statements: &[&Stmt]) -> Result<String, TeciResult> {
+        let mut builder = format!("({head}");
+        for statement in statements {
+            for line in statement.accept(self)?.lines() {
+                builder = format!("{builder}\n  {line}");
+            }
+        }
+        builder.push(')');
         Ok(builder)
     }
 }
 
+fn value(value: &Object) -> String {
+    match value {
+        Object::Str(s) => format!("{s:?}"),
+        value => value.to_string(),
+    }
+}
+
+fn pattern(p: &Pattern) -> String {
+    match p {
+        Pattern::Wildcard => "_".to_string(),
+        Pattern::Binding(name) => name.lexeme.to_string(),
+        Pattern::Literal(literal) => value(literal),
+        Pattern::Range(start, end) => format!("{start}..{end}"),
+        Pattern::Alternatives(patterns) => {
+            let patterns: Vec<_> = patterns.iter().map(pattern).collect();
+            patterns.join(" | ")
+        }
+        Pattern::Tuple(_, patterns) => {
+            let patterns: Vec<_> = patterns.iter().map(pattern).collect();
+            format!("({})", patterns.join(" "))
+        }
+    }
+}
+
 impl ExprVisitor<String> for AstPrinter {
-    fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<String, TeciError> {
+    fn visit_assign_expr(&self, expr: &AssignExpr) -> Result<String, TeciResult> {
+        self.parenthesize(&format!("= {}", expr.name.lexeme), &[&expr.value])
+    }
+
+    fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<String, TeciResult> {
+        self.parenthesize(&expr.operator.lexeme, &[&expr.left, &expr.right])
+    }
+
+    fn visit_call_expr(&self, expr: &CallExpr) -> Result<String, TeciResult> {
+        let mut exprs = vec![expr.callee.as_ref()];
+        exprs.extend(&expr.arguments);
+        self.parenthesize("call", &exprs)
+    }
+
+    fn visit_conditional_expr(&self, expr: &ConditionalExpr) -> Result<String, TeciResult> {
         self.parenthesize(
-            &expr.operator.lexeme,
-            &[expr.left.clone(), expr.right.clone()],
+            "?:",
+            &[&expr.condition, &expr.then_branch, &expr.else_branch],
         )
     }
-    fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<String, TeciError> {
-        self.parenthesize(&expr.operator.lexeme, &[expr.right.clone()])
+
+    fn visit_get_expr(&self, expr: &GetExpr) -> Result<String, TeciResult> {
+        self.parenthesize(&format!(". {}", expr.name.lexeme), &[&expr.object])
+    }
+
+    fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<String, TeciResult> {
+        self.parenthesize("group", &[&expr.expression])
+    }
+
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> Result<String, TeciResult> {
+        self.parenthesize(&expr.operator.lexeme, &[&expr.left, &expr.right])
+    }
+
+    fn visit_literal_expr(&self, expr: &LiteralExpr) -> Result<String, TeciResult> {
+        Ok(expr.value.as_ref().map_or("nil".to_string(), value))
+    }
+
+    fn visit_match_expr(&self, expr: &MatchExpr) -> Result<String, TeciResult> {
+        let mut builder = format!("(match {}", expr.value.accept(self)?);
+        for arm in &expr.arms {
+            let guard = match &arm.guard {
+                Some(guard) => format!(" if {}", guard.accept(self)?),
+                None => String::new(),
+            };
+            builder = format!(
+                "{builder} ({}{guard} {})",
+                pattern(&arm.pattern),
+                arm.body.accept(self)?
+            );
+        }
+        builder.push(')');
+        Ok(builder)
+    }
+
+    fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<String, TeciResult> {
+        self.parenthesize(&expr.operator.lexeme, &[&expr.right])
     }
-    fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<String, TeciError> {
-        self.parenthesize(&"group".to_string(), &[expr.expression.clone()])
+
+    fn visit_tuple_expr(&self, expr: &TupleExpr) -> Result<String, TeciResult> {
+        let elements: Vec<_> = expr.elements.iter().collect();
+        self.parenthesize("tuple", &elements)
     }
-    fn visit_literal_expr(&self, expr: &LiteralExpr) -> Result<String, TeciError> {
-        if let Some(val) = &expr.value {
-            Ok(val.to_string())
+
+    fn visit_tupleassign_expr(&self, expr: &TupleAssignExpr) -> Result<String, TeciResult> {
+        self.parenthesize(&format!("= {}", pattern(&expr.target)), &[&expr.value])
+    }
+
+    fn visit_update_expr(&self, expr: &UpdateExpr) -> Result<String, TeciResult> {
+        Ok(if expr.prefix {
+            format!("({} {})", expr.operator.lexeme, expr.name.lexeme)
         } else {
-            Ok("nil".to_string())
+            format!("({} {})", expr.name.lexeme, expr.operator.lexeme)
+        })
+    }
+
+    fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<String, TeciResult> {
+        Ok(expr.name.lexeme.to_string())
+    }
+}
+
+impl StmtVisitor<String> for AstPrinter {
+    fn visit_break_stmt(&self, _stmt: &BreakStmt) -> Result<String, TeciResult> {
+        Ok("(break)".to_string())
+    }
+
+    fn visit_block_stmt(&self, stmt: &BlockStmt) -> Result<String, TeciResult> {
+        let statements: Vec<_> = stmt.statements.iter().collect();
+        self.nest("block".to_string(), &statements)
+    }
+
+    fn visit_destructure_stmt(&self, stmt: &DestructureStmt) -> Result<String, TeciResult> {
+        let keyword = if stmt.constant { "const" } else { "let" };
+        self.parenthesize(
+            &format!("{keyword} {}", pattern(&stmt.pattern)),
+            &[&stmt.initializer],
+        )
+    }
+
+    fn visit_forin_stmt(&self, stmt: &ForInStmt) -> Result<String, TeciResult> {
+        let head = format!("for {} {}", stmt.name.lexeme, stmt.iterable.accept(self)?);
+        self.nest(head, &[&stmt.body])
+    }
+
+    fn visit_if_stmt(&self, stmt: &IfStmt) -> Result<String, TeciResult> {
+        let head = format!("if {}", stmt.condition.accept(self)?);
+        match &stmt.else_branch {
+            Some(else_branch) => self.nest(head, &[&stmt.then_branch, else_branch]),
+            None => self.nest(head, &[&stmt.then_branch]),
         }
     }
+
+    fn visit_expression_stmt(&self, stmt: &ExpressionStmt) -> Result<String, TeciResult> {
+        stmt.expression.accept(self)
+    }
+
+    fn visit_function_stmt(&self, stmt: &FunctionStmt) -> Result<String, TeciResult> {
+        let params: Vec<_> = stmt.params.iter().map(|p| p.lexeme.to_string()).collect();
+        let head = format!("fun {} ({})", stmt.name.lexeme, params.join(" "));
+        let body: Vec<_> = stmt.body.iter().collect();
+        self.nest(head, &body)
+    }
+
+    fn visit_print_stmt(&self, stmt: &PrintStmt) -> Result<String, TeciResult> {
+        self.parenthesize("print", &[&stmt.expression])
+    }
+
+    fn visit_return_stmt(&self, stmt: &ReturnStmt) -> Result<String, TeciResult> {
+        let value: Vec<_> = stmt.value.iter().collect();
+        self.parenthesize("return", &value)
+    }
+
+    fn visit_let_stmt(&self, stmt: &LetStmt) -> Result<String, TeciResult> {
+        let keyword = if stmt.constant { "const" } else { "let" };
+        let initializer: Vec<_> = stmt.initializer.iter().collect();
+        self.parenthesize(&format!("{keyword} {}", stmt.name.lexeme), &initializer)
+    }
+
+    fn visit_while_stmt(&self, stmt: &WhileStmt) -> Result<String, TeciResult> {
+        let head = format!("while {}", stmt.condition.accept(self)?);
+        self.nest(head, &[&stmt.body])
+    }
+
+    fn visit_yield_stmt(&self, stmt: &YieldStmt) -> Result<String, TeciResult> {
+        self.parenthesize("yield", &[&stmt.value])
+    }
 }
@@ -0,0 +1,98 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FunctionProfile {
+    pub calls: usize,
+    // Including the functions it calls, counted once for recursive calls
+    pub time: Duration,
+}
+
+// A function as the profiler tells them apart
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FunctionId {
+    // Written in teci, by the line and column of its name in the declaration,
+    // as functions in different scopes can share a name
+    Declared(usize, usize),
+    // Natives and the like, which have a name of their own
+    Named(String),
+}
+
+// Counts the calls to each function, native or not, and the time spent in
+// them for `run --profile`
+#[derive(Debug, Default)]
+pub struct Profiler {
+    functions: HashMap<FunctionId, FunctionProfile>,
+    // What to call each function in the report
+    names: HashMap<FunctionId, String>,
+    // How many calls to each function are running, as only the outermost
+    // one is timed
+    active: HashMap<FunctionId, usize>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Gives when the call started, if it is the outermost call to `function`.
+    // `name` is only called the first time the function is.
+    pub fn enter(
+        &mut self,
+        function: &FunctionId,
+        name: impl FnOnce() -> String,
+    ) -> Option<Instant> {
+        if !self.names.contains_key(function) {
+            self.names.insert(function.clone(), name());
+        }
+        self.functions.entry(function.clone()).or_default().calls += 1;
+        let active = self.active.entry(function.clone()).or_default();
+        *active += 1;
+        (*active == 1).then(Instant::now)
+    }
+
+    pub fn exit(&mut self, function: &FunctionId, started: Option<Instant>) {
+        if let Some(active) = self.active.get_mut(function) {
+            *active -= 1;
+        }
+        if let (Some(started), Some(profile)) = (started, self.functions.get_mut(function)) {
+            profile.time += started.elapsed();
+        }
+    }
+
+    // The functions called, the slowest first
+    pub fn functions(&self) -> Vec<(&str, FunctionProfile)> {
+        let mut functions: Vec<_> = self
+            .functions
+            .iter()
+            .map(|(function, profile)| (self.names[function].as_str(), *profile))
+            .collect();
+        functions.sort_by(|a, b| b.1.time.cmp(&a.1.time).then(a.0.cmp(b.0)));
+        functions
+    }
+
+    pub fn report(&self) -> String {
+        let functions = self.functions();
+        let width = functions
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .chain(Some("function".len()))
+            .max()
+            .unwrap_or_default();
+        let mut report = format!(
+            "{:width$}  {:>10}  {:>12}\n",
+            "function", "calls", "time (ms)"
+        );
+        for (name, profile) in functions {
+            report += &format!(
+                "{:width$}  {:>10}  {:>12.3}\n",
+                name,
+                profile.calls,
+                profile.time.as_secs_f64() * 1000.0
+            );
+        }
+        report
+    }
+}
@@ -0,0 +1,604 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+};
+
+use crate::{
+    error::TeciResult,
+    expr::*,
+    span::{Span, stmt_span},
+    stmt::*,
+    symbol::Symbol,
+    token::Token,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DefinitionKind {
+    Variable,
+    Constant,
+    Function,
+    Parameter,
+}
+
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub name: Token,
+    pub kind: DefinitionKind,
+    pub params: Vec<Token>,
+    // The whole declaration, not just its name
+    pub span: Span,
+    // The function the definition is nested in, if any
+    pub parent: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+pub struct Resolution {
+    pub definitions: Vec<Definition>,
+    // Every use of a name, with the definition it refers to
+    pub references: Vec<(Token, usize)>,
+    pub errors: Vec<TeciResult>,
+}
+
+struct Binding {
+    definition: usize,
+    // False while its own initializer is being resolved
+    ready: bool,
+}
+
+// Statically links every use of a name to its `let`/`fun`/parameter definition
+pub struct Resolver {
+    natives: Vec<String>,
+    // The first scope holds the globals
+    scopes: RefCell<Vec<HashMap<Symbol, Binding>>>,
+    resolution: RefCell<Resolution>,
+    // Names used inside functions that were not found in any scope yet; they
+    // may be globals declared further down the file
+    deferred: RefCell<Vec<Token>>,
+    function: Cell<Option<usize>>,
+    loop_depth: Cell<usize>,
+    assignments_only: bool,
+}
+
+impl Resolver {
+    pub fn new(natives: Vec<String>) -> Self {
+        Self {
+            natives,
+            scopes: RefCell::new(vec![HashMap::new()]),
+            resolution: RefCell::new(Resolution::default()),
+            deferred: RefCell::new(Vec::new()),
+            function: Cell::new(None),
+            loop_depth: Cell::new(0),
+            assignments_only: false,
+        }
+    }
+
+    // Only reports assignments to constants and functions, which are wrong
+    // wherever they are. The other errors, like an undefined name in a
+    // function that is never called, may never happen at run time.
+    pub fn assignments_only(mut self) -> Self {
+        self.assignments_only = true;
+        self
+    }
+
+    pub fn resolve(self, statements: &[Stmt]) -> Resolution {
+        let resolution = self.resolve_unreported(statements);
+        resolution.errors.iter().for_each(|error| error.report(""));
+        resolution
+    }
+
+    // Like `resolve`, leaving the errors for the caller to show, as an editor
+    // does while the text is typed
+    pub fn resolve_unreported(self, statements: &[Stmt]) -> Resolution {
+        for statement in statements {
+            let _ = statement.accept(&self);
+        }
+
+        for name in self.deferred.take() {
+            let global = self.scopes.borrow()[0]
+                .get(&name.lexeme)
+                .map(|b| b.definition);
+            match global {
+                Some(definition) => self.reference(name, definition),
+                None => self.undefined(name),
+            }
+        }
+
+        self.resolution.into_inner()
+    }
+
+    fn error(&self, token: &Token, message: &str) {
+        if !self.assignments_only {
+            self.push_error(token, message);
+        }
+    }
+
+    fn push_error(&self, token: &Token, message: &str) {
+        self.resolution
+            .borrow_mut()
+            .errors
+            .push(TeciResult::ResolveError {
+                token: token.clone(),
+                message: message.to_string(),
+            });
+    }
+
+    fn undefined(&self, name: Token) {
+        if !self
+            .natives
+            .iter()
+            .any(|native| name.lexeme == native.as_str())
+        {
+            self.error(&name, &format!("Undefined variable '{}'", name.lexeme));
+        }
+    }
+
+    fn declare(&self, name: &Token, kind: DefinitionKind, params: Vec<Token>, span: Span) {
+        // A constant stays as it is for the rest of its scope
+        let redeclared = self
+            .scopes
+            .borrow()
+            .last()
+            .unwrap()
+            .get(&name.lexeme)
+            .map(|b| b.definition);
+        if let Some(definition) = redeclared
+            && self.resolution.borrow().definitions[definition].kind == DefinitionKind::Constant
+        {
+            self.push_error(
+                name,
+                &format!("Cannot redeclare constant '{}'", name.lexeme),
+            );
+        }
+
+        let mut resolution = self.resolution.borrow_mut();
+        let definition = resolution.definitions.len();
+        resolution.definitions.push(Definition {
+            name: name.clone(),
+            kind,
+            params,
+            span,
+            parent: self.function.get(),
+        });
+        self.scopes.borrow_mut().last_mut().unwrap().insert(
+            name.lexeme.clone(),
+            Binding {
+                definition,
+                ready: false,
+            },
+        );
+    }
+
+    fn define(&self, name: &Token) {
+        if let Some(binding) = self
+            .scopes
+            .borrow_mut()
+            .last_mut()
+            .unwrap()
+            .get_mut(&name.lexeme)
+        {
+            binding.ready = true;
+        }
+    }
+
+    fn reference(&self, name: Token, definition: usize) {
+        self.resolution
+            .borrow_mut()
+            .references
+            .push((name, definition));
+    }
+
+    // The definition `name` refers to, if it is known yet
+    fn resolve_name(&self, name: &Token) -> Option<usize> {
+        let scopes = self.scopes.borrow();
+        for (depth, scope) in scopes.iter().enumerate().rev() {
+            if let Some(binding) = scope.get(&name.lexeme) {
+                // Globals may be redefined in terms of themselves, e.g. in the REPL
+                if !binding.ready && depth > 0 {
+                    self.error(name, "Cannot read a local variable in its own initializer");
+                }
+                let definition = binding.definition;
+                drop(scopes);
+                self.reference(name.clone(), definition);
+                return Some(definition);
+            }
+        }
+        drop(scopes);
+
+        if self.function.get().is_some() {
+            self.deferred.borrow_mut().push(name.clone());
+        } else {
+            self.undefined(name.clone());
+        }
+        None
+    }
+
+    // Resolves a name being assigned to, which must not be a constant or a
+    // function. Globals declared further down are left to the interpreter.
+    fn resolve_assignment(&self, name: &Token) {
+        let Some(definition) = self.resolve_name(name) else {
+            return;
+        };
+        let kind = match self.resolution.borrow().definitions[definition].kind {
+            DefinitionKind::Constant => "constant",
+            DefinitionKind::Function => "function",
+            _ => return,
+        };
+        self.push_error(
+            name,
+            &format!("Cannot assign to {} '{}'", kind, name.lexeme),
+        );
+    }
+
+    fn resolve_block(&self, statements: &[Stmt]) {
+        self.scopes.borrow_mut().push(HashMap::new());
+        for statement in statements {
+            let _ = statement.accept(self);
+        }
+        self.scopes.borrow_mut().pop();
+    }
+
+    fn resolve_expr(&self, expr: &Expr) {
+        let _ = expr.accept(self);
+    }
+}
+
+impl StmtVisitor<()> for Resolver {
+    fn visit_break_stmt(&self, stmt: &BreakStmt) -> Result<(), TeciResult> {
+        if self.loop_depth.get() == 0 {
+            self.error(&stmt.token, "Found a 'break' statement outside a loop");
+        }
+        Ok(())
+    }
+
+    fn visit_block_stmt(&self, stmt: &BlockStmt) -> Result<(), TeciResult> {
+        self.resolve_block(&stmt.statements);
+        Ok(())
+    }
+
+    fn visit_if_stmt(&self, stmt: &IfStmt) -> Result<(), TeciResult> {
+        self.resolve_expr(&stmt.condition);
+        stmt.then_branch.accept(self)?;
+        if let Some(else_branch) = &stmt.else_branch {
+            else_branch.accept(self)?;
+        }
+        Ok(())
+    }
+
+    fn visit_expression_stmt(&self, stmt: &ExpressionStmt) -> Result<(), TeciResult> {
+        self.resolve_expr(&stmt.expression);
+        Ok(())
+    }
+
+    fn visit_forin_stmt(&self, stmt: &ForInStmt) -> Result<(), TeciResult> {
+        self.resolve_expr(&stmt.iterable);
+        self.scopes.borrow_mut().push(HashMap::new());
+        self.declare(
+            &stmt.name,
+            DefinitionKind::Variable,
+            Vec::new(),
+            Span::of(&stmt.name),
+        );
+        self.define(&stmt.name);
+        self.loop_depth.set(self.loop_depth.get() + 1);
+        let result = stmt.body.accept(self);
+        self.loop_depth.set(self.loop_depth.get() - 1);
+        self.scopes.borrow_mut().pop();
+        result
+    }
+
+    fn visit_function_stmt(&self, stmt: &FunctionStmt) -> Result<(), TeciResult> {
+        self.declare(
+            &stmt.name,
+            DefinitionKind::Function,
+            stmt.params.to_vec(),
+            stmt_span(&Stmt::Function(stmt.clone())),
+        );
+        // Defined before the body so it can call itself
+        self.define(&stmt.name);
+
+        let enclosing_function = self
+            .function
+            .replace(Some(self.resolution.borrow().definitions.len() - 1));
+        let enclosing_loops = self.loop_depth.replace(0);
+        self.scopes.borrow_mut().push(HashMap::new());
+        for param in stmt.params.iter() {
+            self.declare(
+                param,
+                DefinitionKind::Parameter,
+                Vec::new(),
+                Span::of(param),
+            );
+            self.define(param);
+        }
+        for statement in stmt.body.iter() {
+            let _ = statement.accept(self);
+        }
+        self.scopes.borrow_mut().pop();
+        self.loop_depth.set(enclosing_loops);
+        self.function.set(enclosing_function);
+
+        Ok(())
+    }
+
+    fn visit_print_stmt(&self, stmt: &PrintStmt) -> Result<(), TeciResult> {
+        self.resolve_expr(&stmt.expression);
+        Ok(())
+    }
+
+    fn visit_return_stmt(&self, stmt: &ReturnStmt) -> Result<(), TeciResult> {
+        if self.function.get().is_none() {
+            self.error(&stmt.keyword, "Cannot return from top-level code");
+        }
+        if let Some(value) = &stmt.value {
+            self.resolve_expr(value);
+        }
+        Ok(())
+    }
+
+    fn visit_yield_stmt(&self, stmt: &YieldStmt) -> Result<(), TeciResult> {
+        if self.function.get().is_none() {
+            self.error(&stmt.keyword, "Cannot yield from top-level code");
+        }
+        self.resolve_expr(&stmt.value);
+        Ok(())
+    }
+
+    fn visit_let_stmt(&self, stmt: &LetStmt) -> Result<(), TeciResult> {
+        self.declare(
+            &stmt.name,
+            if stmt.constant {
+                DefinitionKind::Constant
+            } else {
+                DefinitionKind::Variable
+            },
+            Vec::new(),
+            stmt_span(&Stmt::Let(stmt.clone())),
+        );
+        if let Some(initializer) = &stmt.initializer {
+            self.resolve_expr(initializer);
+        }
+        self.define(&stmt.name);
+        Ok(())
+    }
+
+    fn visit_destructure_stmt(&self, stmt: &DestructureStmt) -> Result<(), TeciResult> {
+        let span = stmt_span(&Stmt::Destructure(stmt.clone()));
+        let names = stmt.pattern.bindings();
+        let kind = if stmt.constant {
+            DefinitionKind::Constant
+        } else {
+            DefinitionKind::Variable
+        };
+        for name in &names {
+            self.declare(name, kind, Vec::new(), span);
+        }
+        self.resolve_expr(&stmt.initializer);
+        for name in names {
+            self.define(name);
+        }
+        Ok(())
+    }
+
+    fn visit_while_stmt(&self, stmt: &WhileStmt) -> Result<(), TeciResult> {
+        self.resolve_expr(&stmt.condition);
+        self.loop_depth.set(self.loop_depth.get() + 1);
+        let result = stmt.body.accept(self);
+        self.loop_depth.set(self.loop_depth.get() - 1);
+        result
+    }
+}
+
+impl ExprVisitor<()> for Resolver {
+    fn visit_assign_expr(&self, expr: &AssignExpr) -> Result<(), TeciResult> {
+        self.resolve_expr(&expr.value);
+        self.resolve_assignment(&expr.name);
+        Ok(())
+    }
+
+    fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<(), TeciResult> {
+        self.resolve_expr(&expr.left);
+        self.resolve_expr(&expr.right);
+        Ok(())
+    }
+
+    fn visit_call_expr(&self, expr: &CallExpr) -> Result<(), TeciResult> {
+        self.resolve_expr(&expr.callee);
+        for argument in &expr.arguments {
+            self.resolve_expr(argument);
+        }
+        Ok(())
+    }
+
+    fn visit_conditional_expr(&self, expr: &ConditionalExpr) -> Result<(), TeciResult> {
+        self.resolve_expr(&expr.condition);
+        self.resolve_expr(&expr.then_branch);
+        self.resolve_expr(&expr.else_branch);
+        Ok(())
+    }
+
+    fn visit_get_expr(&self, expr: &GetExpr) -> Result<(), TeciResult> {
+        self.resolve_expr(&expr.object);
+        Ok(())
+    }
+
+    fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<(), TeciResult> {
+        self.resolve_expr(&expr.expression);
+        Ok(())
+    }
+
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> Result<(), TeciResult> {
+        self.resolve_expr(&expr.left);
+        self.resolve_expr(&expr.right);
+        Ok(())
+    }
+
+    fn visit_literal_expr(&self, _expr: &LiteralExpr) -> Result<(), TeciResult> {
+        Ok(())
+    }
+
+    fn visit_match_expr(&self, expr: &MatchExpr) -> Result<(), TeciResult> {
+        self.resolve_expr(&expr.value);
+        for arm in &expr.arms {
+            self.scopes.borrow_mut().push(HashMap::new());
+            for name in arm.pattern.bindings() {
+                self.declare(name, DefinitionKind::Variable, Vec::new(), Span::of(name));
+                self.define(name);
+            }
+            if let Some(guard) = &arm.guard {
+                self.resolve_expr(guard);
+            }
+            self.resolve_expr(&arm.body);
+            self.scopes.borrow_mut().pop();
+        }
+        Ok(())
+    }
+
+    fn visit_tuple_expr(&self, expr: &TupleExpr) -> Result<(), TeciResult> {
+        for element in &expr.elements {
+            self.resolve_expr(element);
+        }
+        Ok(())
+    }
+
+    fn visit_tupleassign_expr(&self, expr: &TupleAssignExpr) -> Result<(), TeciResult> {
+        self.resolve_expr(&expr.value);
+        for name in expr.target.bindings() {
+            self.resolve_assignment(name);
+        }
+        Ok(())
+    }
+
+    fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<(), TeciResult> {
+        self.resolve_expr(&expr.right);
+        Ok(())
+    }
+
+    fn visit_update_expr(&self, expr: &UpdateExpr) -> Result<(), TeciResult> {
+        self.resolve_assignment(&expr.name);
+        Ok(())
+    }
+
+    fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<(), TeciResult> {
+        self.resolve_name(&expr.name);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    fn resolve(source: &str) -> Resolution {
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        Resolver::new(vec!["clock".to_string()]).resolve(&statements)
+    }
+
+    // (line of the use, line of the definition) for every reference to `name`
+    fn uses(resolution: &Resolution, name: &str) -> Vec<(usize, usize)> {
+        resolution
+            .references
+            .iter()
+            .filter(|(token, _)| token.lexeme == name)
+            .map(|(token, d)| (token.line, resolution.definitions[*d].name.line))
+            .collect()
+    }
+
+    #[test]
+    fn t_shadowing() {
+        let resolution = resolve("let a = 1;\n{\n  let a = 2;\n  print a;\n}\nprint a;");
+        assert_eq!(uses(&resolution, "a"), vec![(4, 3), (6, 1)]);
+        assert!(resolution.errors.is_empty());
+    }
+
+    #[test]
+    fn t_functions_see_later_globals() {
+        let resolution =
+            resolve("fun f(x) {\n  return g(x) + x;\n}\nfun g(y) {\n  return clock() + y;\n}");
+        assert_eq!(uses(&resolution, "g"), vec![(2, 4)]);
+        assert_eq!(uses(&resolution, "x"), vec![(2, 1), (2, 1)]);
+        let f = &resolution.definitions[0];
+        assert_eq!(f.kind, DefinitionKind::Function);
+        assert_eq!(f.params[0].lexeme, "x");
+        assert_eq!(resolution.definitions[1].parent, Some(0));
+        assert!(resolution.errors.is_empty());
+    }
+
+    #[test]
+    fn t_resolve_errors() {
+        let resolution = resolve("{\n  let a = a;\n}\nprint b;\nreturn 1;\nbreak;\nyield 2;");
+        let messages: Vec<String> = resolution
+            .errors
+            .iter()
+            .map(|e| match e {
+                TeciResult::ResolveError { message, .. } => message.clone(),
+                _ => panic!("expected a resolve error"),
+            })
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Cannot read a local variable in its own initializer",
+                "Undefined variable 'b'",
+                "Cannot return from top-level code",
+                "Found a 'break' statement outside a loop",
+                "Cannot yield from top-level code",
+            ]
+        );
+    }
+
+    #[test]
+    fn t_assign_to_constants() {
+        let resolution = resolve(
+            "const a = 1;\na = 2;\nfun f() {}\nf++;\nconst (b, c) = (1, 2);\n(b, c) = (c, b);\nlet d = 1;\nd += a;",
+        );
+        let errors: Vec<(usize, String)> = resolution
+            .errors
+            .iter()
+            .map(|e| match e {
+                TeciResult::ResolveError { token, message } => (token.line, message.clone()),
+                _ => panic!("expected a resolve error"),
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (2, "Cannot assign to constant 'a'".to_string()),
+                (4, "Cannot assign to function 'f'".to_string()),
+                (6, "Cannot assign to constant 'b'".to_string()),
+                (6, "Cannot assign to constant 'c'".to_string()),
+            ]
+        );
+        let resolution = resolve("const a = 1;\nlet a = 2;\n{\n  const b = 1;\n  fun b() {}\n}");
+        let errors: Vec<(usize, String)> = resolution
+            .errors
+            .iter()
+            .map(|e| match e {
+                TeciResult::ResolveError { token, message } => (token.line, message.clone()),
+                _ => panic!("expected a resolve error"),
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (2, "Cannot redeclare constant 'a'".to_string()),
+                (5, "Cannot redeclare constant 'b'".to_string()),
+            ]
+        );
+        assert_eq!(resolution.definitions[0].kind, DefinitionKind::Constant);
+
+        let tokens = Scanner::new("const a = 1;\nfun f() { return h(a); }\na = b;".to_string())
+            .scan_tokens()
+            .unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        let resolution = Resolver::new(Vec::new())
+            .assignments_only()
+            .resolve(&statements);
+        assert_eq!(resolution.errors.len(), 1);
+        assert!(matches!(
+            &resolution.errors[0],
+            TeciResult::ResolveError { token, .. } if token.line == 3
+        ));
+    }
+}
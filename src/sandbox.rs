@@ -0,0 +1,221 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    time::{Duration, Instant},
+};
+
+// The largest value a sandbox lets a script build unless told otherwise.
+// Big numbers take time to compute and print that grows with their size,
+// much faster than the size itself.
+pub const DEFAULT_MAX_SIZE: u64 = 64 * 1024;
+
+// What natives may do beyond computing their result. A native that needs a
+// capability the sandbox does not grant fails when called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    Clock,
+    // Reading and writing files, with the `fs` module
+    FileSystem,
+    // Reading stdin, with `input`
+    Input,
+}
+
+impl Capability {
+    pub fn named(name: &str) -> Option<Capability> {
+        match name {
+            "clock" => Some(Capability::Clock),
+            "fs" => Some(Capability::FileSystem),
+            "input" => Some(Capability::Input),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Capability::Clock => write!(f, "clock"),
+            Capability::FileSystem => write!(f, "fs"),
+            Capability::Input => write!(f, "input"),
+        }
+    }
+}
+
+// Limits on what a script may do, for running code that is not trusted,
+// like student submissions. A new sandbox grants no capabilities and only
+// limits the size of values, to `DEFAULT_MAX_SIZE`.
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    max_statements: Option<u64>,
+    timeout: Option<Duration>,
+    // The largest value a script may build, as `Object::size` measures it
+    max_size: u64,
+    max_depth: Option<usize>,
+    capabilities: HashSet<Capability>,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self {
+            max_statements: None,
+            timeout: None,
+            max_size: DEFAULT_MAX_SIZE,
+            max_depth: None,
+            capabilities: HashSet::new(),
+        }
+    }
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_statements(mut self, max_statements: u64) -> Self {
+        self.max_statements = Some(max_statements);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    pub fn with_capability(mut self, capability: Capability) -> Self {
+        self.capabilities.insert(capability);
+        self
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+}
+
+// The limit a script went over
+#[derive(Debug, Clone, PartialEq)]
+pub enum Limit {
+    Statements(u64),
+    Time(Duration),
+    Size(u64),
+    Depth(usize),
+    Capability(Capability),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Statements(max) => write!(f, "Ran more than {} statements", max),
+            Limit::Time(timeout) => write!(f, "Ran for longer than {:?}", timeout),
+            Limit::Size(max) => write!(f, "Built a value larger than {}", max),
+            Limit::Depth(max) => write!(f, "Made calls deeper than {}", max),
+            Limit::Capability(capability) => {
+                write!(
+                    f,
+                    "Needs the '{}' capability, which is not granted",
+                    capability
+                )
+            }
+        }
+    }
+}
+
+// A sandbox, and what a run in it has used up so far
+#[derive(Debug)]
+pub struct Limiter {
+    sandbox: Sandbox,
+    statements: u64,
+    started: Instant,
+}
+
+impl Limiter {
+    pub fn new(sandbox: Sandbox) -> Self {
+        Self {
+            sandbox,
+            statements: 0,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn sandbox(&self) -> &Sandbox {
+        &self.sandbox
+    }
+
+    // Starts counting again, for a new run
+    pub fn restart(&mut self) {
+        self.statements = 0;
+        self.started = Instant::now();
+    }
+
+    pub fn count_statement(&mut self) -> Result<(), Limit> {
+        self.statements += 1;
+        if let Some(max) = self.sandbox.max_statements
+            && self.statements > max
+        {
+            return Err(Limit::Statements(max));
+        }
+        if let Some(timeout) = self.sandbox.timeout
+            && self.started.elapsed() > timeout
+        {
+            return Err(Limit::Time(timeout));
+        }
+        Ok(())
+    }
+
+    pub fn check_size(&self, size: u64) -> Result<(), Limit> {
+        if size > self.sandbox.max_size {
+            Err(Limit::Size(self.sandbox.max_size))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn check_depth(&self, depth: usize) -> Result<(), Limit> {
+        match self.sandbox.max_depth {
+            Some(max) if depth >= max => Err(Limit::Depth(max)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_capability(&self, capability: Capability) -> Result<(), Limit> {
+        if self.sandbox.allows(capability) {
+            Ok(())
+        } else {
+            Err(Limit::Capability(capability))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_limiter() {
+        let mut limiter = Limiter::new(Sandbox::new().with_max_statements(2).with_max_size(3));
+        assert!(limiter.count_statement().is_ok());
+        assert!(limiter.count_statement().is_ok());
+        assert_eq!(limiter.count_statement(), Err(Limit::Statements(2)));
+        limiter.restart();
+        assert!(limiter.count_statement().is_ok());
+
+        assert!(limiter.check_size(3).is_ok());
+        assert_eq!(limiter.check_size(4), Err(Limit::Size(3)));
+        let limiter = Limiter::new(Sandbox::new());
+        assert!(limiter.check_size(DEFAULT_MAX_SIZE + 1).is_err());
+        assert!(limiter.check_depth(1_000_000).is_ok());
+        assert_eq!(
+            limiter.check_capability(Capability::Clock),
+            Err(Limit::Capability(Capability::Clock))
+        );
+    }
+}
@@ -1,4 +1,21 @@
-use crate::{error::TeciResult, object::Object, token::Token, token_type::TokenType};
+use crate::{
+    bignum::BigInt,
+    dialect::{Dialect, PRAGMA},
+    error::TeciResult,
+    object::Object,
+    token::Token,
+    token_type::TokenType,
+};
+
+// A piece of source text as seen by `Scanner::scan_lossless`
+#[derive(Debug, Clone)]
+pub enum Piece {
+    Token(Token),
+    // Whitespace and comments
+    Trivia(String),
+    // Text that could not be scanned, like an unterminated string
+    Invalid(String),
+}
 
 #[allow(dead_code)]
 #[derive(Debug)]
@@ -8,6 +25,14 @@ pub struct Scanner {
     start: usize,
     current: usize,
     line: usize,
+    // Where the token being scanned starts, for its position
+    start_line: usize,
+    start_column: usize,
+    line_start: usize,
+    errors: Vec<TeciResult>,
+    dialect: Dialect,
+    // Whether a pragma may name a mapping file
+    mapping_files: bool,
 }
 
 impl Scanner {
@@ -18,29 +43,109 @@ impl Scanner {
             start: 0,
             current: 0,
             line: 1,
+            start_line: 1,
+            start_column: 0,
+            line_start: 0,
+            errors: Vec::new(),
+            dialect: Dialect::classic(),
+            mapping_files: true,
         }
     }
 
+    // The dialect to scan with, unless the source names one in a pragma
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    // Whether a pragma may load a mapping file, which sandboxes without the
+    // 'fs' capability don't allow
+    pub fn with_mapping_files(mut self, mapping_files: bool) -> Self {
+        self.mapping_files = mapping_files;
+        self
+    }
+
+    // The dialect the source was scanned with
+    pub fn dialect(&self) -> &Dialect {
+        &self.dialect
+    }
+
     pub fn scan_tokens(&mut self) -> Result<Vec<Token>, TeciResult> {
-        let mut had_error: Option<TeciResult> = None;
+        let (tokens, mut errors) = self.scan_partial();
+        errors.iter().for_each(|error| error.report(""));
+        match errors.pop() {
+            Some(e) => Err(e),
+            None => Ok(tokens),
+        }
+    }
+
+    // Scans the whole source even if some of it is invalid, returning every
+    // token that could be read along with every error found, unreported
+    pub fn scan_partial(&mut self) -> (Vec<Token>, Vec<TeciResult>) {
         while !self.is_at_end() {
-            self.start = self.current;
-            if let Err(e) = self.scan_token() {
-                e.report("");
-                had_error = Some(e);
-            }
+            self.scan_next();
         }
 
-        self.tokens
-            .push(Token::new(TokenType::Eof, "".to_string(), None, self.line));
+        self.tokens.push(
+            Token::new(TokenType::Eof, "", None, self.line)
+                .with_column(self.current - self.line_start),
+        );
 
-        if let Some(e) = had_error {
-            Err(e)
-        } else {
-            Ok(self.tokens.clone())
+        (self.tokens.clone(), std::mem::take(&mut self.errors))
+    }
+
+    // Scans the whole source keeping what `scan_partial` throws away, so the
+    // pieces put back together are exactly the source
+    pub fn scan_lossless(&mut self) -> Vec<Piece> {
+        let mut pieces = Vec::new();
+        while !self.is_at_end() {
+            let tokens = self.tokens.len();
+            let scanned = self.scan_next();
+            let text: String = self.source[self.start..self.current].iter().collect();
+            let piece = if self.tokens.len() > tokens {
+                Piece::Token(self.tokens[tokens].clone())
+            } else if scanned
+                && (text.trim().is_empty() || text.starts_with('/') || text.starts_with(PRAGMA))
+            {
+                Piece::Trivia(text)
+            } else {
+                Piece::Invalid(text)
+            };
+            pieces.push(piece);
+        }
+
+        pieces
+    }
+
+    // Scans whatever comes next (a token, whitespace or a comment) and tells
+    // whether it could be scanned
+    fn scan_next(&mut self) -> bool {
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.current - self.line_start;
+        match self.scan_token() {
+            Ok(()) => true,
+            Err(e) => {
+                self.errors.push(e);
+                false
+            }
+        }
+    }
+
+    // An error in the current line. Scanning only collects errors, so editors
+    // can scan as the text is typed without them being reported.
+    fn error(&self, message: &str) -> TeciResult {
+        TeciResult::TeciError {
+            line: self.line,
+            message: message.to_string(),
         }
     }
 
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+    }
+
     fn scan_token(&mut self) -> Result<(), TeciResult> {
         let c = self.advance();
         match c {
@@ -49,11 +154,69 @@ impl Scanner {
             '{' => self.add_token(TokenType::LeftBrace),
             '}' => self.add_token(TokenType::RightBrace),
             ',' => self.add_token(TokenType::Comma),
-            '.' => self.add_token(TokenType::Dot),
+            '.' => {
+                let ttype = if self.next_is_and_advance('.') {
+                    TokenType::DotDot
+                } else {
+                    TokenType::Dot
+                };
+                self.add_token(ttype);
+            }
             ';' => self.add_token(TokenType::Semicolon),
-            '+' => self.add_token(TokenType::Plus),
-            '-' => self.add_token(TokenType::Minus),
-            '*' => self.add_token(TokenType::Star),
+            '+' => {
+                let ttype = if self.next_is_and_advance('+') {
+                    TokenType::PlusPlus
+                } else if self.next_is_and_advance('=') {
+                    TokenType::PlusAssign
+                } else {
+                    TokenType::Plus
+                };
+                self.add_token(ttype);
+            }
+            '-' => {
+                let ttype = if self.next_is_and_advance('-') {
+                    TokenType::MinusMinus
+                } else if self.next_is_and_advance('=') {
+                    TokenType::MinusAssign
+                } else if self.next_is_and_advance('>') {
+                    TokenType::Arrow
+                } else {
+                    TokenType::Minus
+                };
+                self.add_token(ttype);
+            }
+            '*' => {
+                let ttype = if self.next_is_and_advance('*') {
+                    TokenType::StarStar
+                } else if self.next_is_and_advance('=') {
+                    TokenType::StarAssign
+                } else {
+                    TokenType::Star
+                };
+                self.add_token(ttype);
+            }
+            '%' => {
+                let ttype = if self.next_is_and_advance('=') {
+                    TokenType::PercentAssign
+                } else {
+                    TokenType::Percent
+                };
+                self.add_token(ttype);
+            }
+            '?' => self.add_token(TokenType::Question),
+            ':' => self.add_token(TokenType::Colon),
+            '&' => self.add_token(TokenType::Ampersand),
+            '|' => self.add_token(TokenType::Pipe),
+            '^' => self.add_token(TokenType::Caret),
+            // `//` starts a comment, so integer division is `~/`
+            '~' => {
+                let ttype = if self.next_is_and_advance('/') {
+                    TokenType::TildeSlash
+                } else {
+                    TokenType::Tilde
+                };
+                self.add_token(ttype);
+            }
             '!' => {
                 let ttype = if self.next_is_and_advance('=') {
                     TokenType::BangEqual
@@ -65,6 +228,8 @@ impl Scanner {
             '<' => {
                 let ttype = if self.next_is_and_advance('=') {
                     TokenType::LessEqual
+                } else if self.next_is_and_advance('<') {
+                    TokenType::LessLess
                 } else {
                     TokenType::Less
                 };
@@ -73,6 +238,8 @@ impl Scanner {
             '>' => {
                 let ttype = if self.next_is_and_advance('=') {
                     TokenType::GreaterEqual
+                } else if self.next_is_and_advance('>') {
+                    TokenType::GreaterGreater
                 } else {
                     TokenType::Greater
                 };
@@ -81,6 +248,8 @@ impl Scanner {
             '=' => {
                 let ttype = if self.next_is_and_advance('=') {
                     TokenType::Equals
+                } else if self.next_is_and_advance('>') {
+                    TokenType::FatArrow
                 } else {
                     TokenType::Assign
                 };
@@ -99,14 +268,15 @@ impl Scanner {
                 } else if self.next_is_and_advance('*') {
                     // Block comment starts
                     self.read_comment()?;
+                } else if self.next_is_and_advance('=') {
+                    self.add_token(TokenType::SlashAssign);
                 } else {
                     self.add_token(TokenType::Slash);
                 }
             }
+            '#' if self.start == 0 => self.read_pragma()?,
             ' ' | '\r' | '\t' => {}
-            '\n' => {
-                self.line += 1;
-            }
+            '\n' => self.new_line(),
             '"' => self.read_string()?,
             '0'..='9' => self.read_number(),
             _ => {
@@ -123,6 +293,24 @@ impl Scanner {
         Ok(())
     }
 
+    // `#dialect <name>` on the first line selects the keywords for the rest
+    fn read_pragma(&mut self) -> Result<(), TeciResult> {
+        let line: String = self.source.iter().take_while(|c| **c != '\n').collect();
+        let Some(dialect) = Dialect::of_source(&line, self.mapping_files) else {
+            // Just an unexpected '#'
+            return Ok(());
+        };
+
+        self.current = line.chars().count();
+        match dialect {
+            Ok(dialect) => {
+                self.dialect = dialect;
+                Ok(())
+            }
+            Err(message) => Err(self.error(&message)),
+        }
+    }
+
     fn read_identifier(&mut self) -> Result<(), TeciResult> {
         while Scanner::is_alphanumeric(self.peek()) {
             self.advance();
@@ -134,7 +322,7 @@ impl Scanner {
             .unwrap()
             .iter()
             .collect();
-        if let Some(ttype) = Scanner::keyword(check.as_str()) {
+        if let Some(ttype) = self.dialect.keyword(&check) {
             self.add_token(ttype);
         } else {
             self.add_token(TokenType::Identifier);
@@ -148,7 +336,9 @@ impl Scanner {
             match ch {
                 '"' => break,
                 '\n' => {
-                    self.line += 1;
+                    self.advance();
+                    self.new_line();
+                    continue;
                 }
                 _ => {}
             }
@@ -156,7 +346,7 @@ impl Scanner {
         }
 
         if self.is_at_end() {
-            return Err(TeciResult::teci_error(self.line, "Unterminated string."));
+            return Err(self.error("Unterminated string."));
         }
 
         self.advance();
@@ -168,8 +358,8 @@ impl Scanner {
             .get(self.start + 1..self.current - 1)
             .unwrap()
             .iter()
-            .collect();
-        self.add_token_object(TokenType::String, Some(Object::Str(literal)));
+            .collect::<String>();
+        self.add_token_object(TokenType::String, Some(Object::Str(literal.into())));
 
         Ok(())
     }
@@ -191,10 +381,10 @@ impl Scanner {
                 }
                 Some('\n') => {
                     self.advance();
-                    self.line += 1;
+                    self.new_line();
                 }
                 None => {
-                    return Err(TeciResult::teci_error(self.line, "Unterminated comment."));
+                    return Err(self.error("Unterminated comment."));
                 }
                 _ => {
                     self.advance();
@@ -221,7 +411,15 @@ impl Scanner {
             .unwrap()
             .iter()
             .collect();
-        self.add_token_object(TokenType::Number, Some(Object::Num(value.parse().unwrap())));
+        // Integers too big for an i64 are read as bignums
+        let number = match value.parse() {
+            Ok(int) => Object::Int(int),
+            Err(_) => match BigInt::parse(&value) {
+                Some(int) => Object::integer(int),
+                None => Object::Num(value.parse().unwrap()),
+            },
+        };
+        self.add_token_object(TokenType::Number, Some(number));
     }
 
     fn is_digit(ch: Option<char>) -> bool {
@@ -249,9 +447,10 @@ impl Scanner {
             .get(self.start..self.current)
             .unwrap()
             .iter()
-            .collect();
-        self.tokens
-            .push(Token::new(ttype, lexeme, literal, self.line));
+            .collect::<String>();
+        self.tokens.push(
+            Token::new(ttype, lexeme, literal, self.start_line).with_column(self.start_column),
+        );
     }
 
     fn advance(&mut self) -> char {
@@ -281,28 +480,4 @@ impl Scanner {
     fn peek_next(&self) -> Option<char> {
         self.source.get(self.current + 1).copied()
     }
-
-    fn keyword(check: &str) -> Option<TokenType> {
-        match check {
-            "and" => Some(TokenType::And),
-            "break" => Some(TokenType::Break),
-            "class" => Some(TokenType::Class),
-            "else" => Some(TokenType::Else),
-            "false" => Some(TokenType::False),
-            "for" => Some(TokenType::For),
-            "fun" => Some(TokenType::Fun),
-            "if" => Some(TokenType::If),
-            "let" => Some(TokenType::Let),
-            "nil" => Some(TokenType::Nil),
-            "or" => Some(TokenType::Or),
-            "print" => Some(TokenType::Print),
-            "escuchadmebien" => Some(TokenType::Print),
-            "return" => Some(TokenType::Return),
-            "super" => Some(TokenType::Super),
-            "this" => Some(TokenType::This),
-            "true" => Some(TokenType::True),
-            "while" => Some(TokenType::While),
-            _ => None,
-        }
-    }
 }
@@ -0,0 +1,108 @@
+use crate::{expr::Expr, stmt::Stmt, token::Token, token_type::TokenType};
+
+// Lines start at 1 (like `Token::line`), columns at 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn of(token: &Token) -> Span {
+        let start = Position {
+            line: token.line,
+            column: token.column,
+        };
+        let end = match token.lexeme.rsplit_once('\n') {
+            Some((before, last)) => Position {
+                line: token.line + before.matches('\n').count() + 1,
+                column: last.chars().count(),
+            },
+            None => Position {
+                line: token.line,
+                column: token.column + token.lexeme.chars().count(),
+            },
+        };
+        Span { start, end }
+    }
+
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end.max(self.end),
+        }
+    }
+
+    pub fn contains(&self, position: Position) -> bool {
+        self.start <= position && position <= self.end
+    }
+}
+
+pub fn expr_span(expr: &Expr) -> Span {
+    match expr {
+        Expr::Assign(e) => Span::of(&e.name).to(expr_span(&e.value)),
+        Expr::Binary(e) => expr_span(&e.left).to(expr_span(&e.right)),
+        Expr::Call(e) => expr_span(&e.callee).to(Span::of(&e.paren)),
+        Expr::Conditional(e) => expr_span(&e.condition).to(expr_span(&e.else_branch)),
+        Expr::Get(e) => expr_span(&e.object).to(Span::of(&e.name)),
+        Expr::Grouping(e) => Span::of(&e.paren).to(expr_span(&e.expression)),
+        Expr::Logical(e) => expr_span(&e.left).to(expr_span(&e.right)),
+        Expr::Literal(e) => Span::of(&e.token),
+        Expr::Match(e) => Span::of(&e.keyword).to(Span::of(&e.brace)),
+        Expr::Tuple(e) => match (e.elements.first(), e.elements.last()) {
+            // Without parentheses the token is the first comma
+            (Some(first), Some(last)) if e.paren.ttype == TokenType::Comma => {
+                expr_span(first).to(expr_span(last))
+            }
+            (_, Some(last)) => Span::of(&e.paren).to(expr_span(last)),
+            _ => Span::of(&e.paren),
+        },
+        Expr::TupleAssign(e) => Span::of(&e.paren).to(expr_span(&e.value)),
+        Expr::Unary(e) => Span::of(&e.operator).to(expr_span(&e.right)),
+        Expr::Update(e) if e.prefix => Span::of(&e.operator).to(Span::of(&e.name)),
+        Expr::Update(e) => Span::of(&e.name).to(Span::of(&e.operator)),
+        Expr::Variable(e) => Span::of(&e.name),
+    }
+}
+
+fn statements_span(start: Span, statements: &[Stmt]) -> Span {
+    match statements.last() {
+        Some(last) => start.to(stmt_span(last)),
+        None => start,
+    }
+}
+
+pub fn stmt_span(stmt: &Stmt) -> Span {
+    match stmt {
+        Stmt::Break(s) => Span::of(&s.token),
+        Stmt::Block(s) => statements_span(Span::of(&s.brace), &s.statements),
+        Stmt::Destructure(s) => Span::of(&s.keyword).to(expr_span(&s.initializer)),
+        Stmt::ForIn(s) => Span::of(&s.keyword).to(stmt_span(&s.body)),
+        Stmt::If(s) => {
+            let span = Span::of(&s.keyword).to(stmt_span(&s.then_branch));
+            match &s.else_branch {
+                Some(else_branch) => span.to(stmt_span(else_branch)),
+                None => span,
+            }
+        }
+        Stmt::Expression(s) => Span::of(&s.token).to(expr_span(&s.expression)),
+        Stmt::Function(s) => statements_span(Span::of(&s.name), &s.body),
+        Stmt::Print(s) => Span::of(&s.keyword).to(expr_span(&s.expression)),
+        Stmt::Return(s) => match &s.value {
+            Some(value) => Span::of(&s.keyword).to(expr_span(value)),
+            None => Span::of(&s.keyword),
+        },
+        Stmt::Let(s) => match &s.initializer {
+            Some(initializer) => Span::of(&s.name).to(expr_span(initializer)),
+            None => Span::of(&s.name),
+        },
+        Stmt::While(s) => Span::of(&s.keyword).to(stmt_span(&s.body)),
+        Stmt::Yield(s) => Span::of(&s.keyword).to(expr_span(&s.value)),
+    }
+}
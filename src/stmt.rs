@@ -2,12 +2,16 @@ use std::rc::Rc;
 
 use crate::error::*;
 use crate::expr::*;
+use crate::pattern::*;
 use crate::token::*;
+use crate::types::*;
 
 #[derive(Clone)]
 pub enum Stmt {
     Break(BreakStmt),
     Block(BlockStmt),
+    Destructure(DestructureStmt),
+    ForIn(ForInStmt),
     If(IfStmt),
     Expression(ExpressionStmt),
     Function(FunctionStmt),
@@ -15,6 +19,7 @@ pub enum Stmt {
     Return(ReturnStmt),
     Let(LetStmt),
     While(WhileStmt),
+    Yield(YieldStmt),
 }
 
 impl Stmt {
@@ -22,6 +27,8 @@ impl Stmt {
         match self {
             Stmt::Break(exp) => exp.accept(visitor),
             Stmt::Block(exp) => exp.accept(visitor),
+            Stmt::Destructure(exp) => exp.accept(visitor),
+            Stmt::ForIn(exp) => exp.accept(visitor),
             Stmt::If(exp) => exp.accept(visitor),
             Stmt::Expression(exp) => exp.accept(visitor),
             Stmt::Function(exp) => exp.accept(visitor),
@@ -29,6 +36,7 @@ impl Stmt {
             Stmt::Return(exp) => exp.accept(visitor),
             Stmt::Let(exp) => exp.accept(visitor),
             Stmt::While(exp) => exp.accept(visitor),
+            Stmt::Yield(exp) => exp.accept(visitor),
         }
     }
 }
@@ -40,11 +48,29 @@ pub struct BreakStmt {
 
 #[derive(Clone)]
 pub struct BlockStmt {
+    pub brace: Token,
     pub statements: Vec<Stmt>,
 }
 
+#[derive(Clone)]
+pub struct DestructureStmt {
+    pub keyword: Token,
+    pub pattern: Pattern,
+    pub initializer: Expr,
+    pub constant: bool,
+}
+
+#[derive(Clone)]
+pub struct ForInStmt {
+    pub keyword: Token,
+    pub name: Token,
+    pub iterable: Expr,
+    pub body: Box<Stmt>,
+}
+
 #[derive(Clone)]
 pub struct IfStmt {
+    pub keyword: Token,
     pub condition: Expr,
     pub then_branch: Box<Stmt>,
     pub else_branch: Option<Box<Stmt>>,
@@ -52,6 +78,7 @@ pub struct IfStmt {
 
 #[derive(Clone)]
 pub struct ExpressionStmt {
+    pub token: Token,
     pub expression: Expr,
 }
 
@@ -59,35 +86,49 @@ pub struct ExpressionStmt {
 pub struct FunctionStmt {
     pub name: Token,
     pub params: Rc<Vec<Token>>,
+    pub param_types: Vec<Type>,
+    pub returns: Type,
     pub body: Rc<Vec<Stmt>>,
 }
 
 #[derive(Clone)]
 pub struct PrintStmt {
+    pub keyword: Token,
     pub expression: Expr,
 }
 
 #[derive(Clone)]
 pub struct ReturnStmt {
-    pub _keyword: Token,
+    pub keyword: Token,
     pub value: Option<Expr>,
 }
 
 #[derive(Clone)]
 pub struct LetStmt {
     pub name: Token,
+    pub annotation: Option<Type>,
     pub initializer: Option<Expr>,
+    pub constant: bool,
 }
 
 #[derive(Clone)]
 pub struct WhileStmt {
+    pub keyword: Token,
     pub condition: Expr,
     pub body: Box<Stmt>,
 }
 
+#[derive(Clone)]
+pub struct YieldStmt {
+    pub keyword: Token,
+    pub value: Expr,
+}
+
 pub trait StmtVisitor<T> {
     fn visit_break_stmt(&self, stmt: &BreakStmt) -> Result<T, TeciResult>;
     fn visit_block_stmt(&self, stmt: &BlockStmt) -> Result<T, TeciResult>;
+    fn visit_destructure_stmt(&self, stmt: &DestructureStmt) -> Result<T, TeciResult>;
+    fn visit_forin_stmt(&self, stmt: &ForInStmt) -> Result<T, TeciResult>;
     fn visit_if_stmt(&self, stmt: &IfStmt) -> Result<T, TeciResult>;
     fn visit_expression_stmt(&self, stmt: &ExpressionStmt) -> Result<T, TeciResult>;
     fn visit_function_stmt(&self, stmt: &FunctionStmt) -> Result<T, TeciResult>;
@@ -95,6 +136,7 @@ pub trait StmtVisitor<T> {
     fn visit_return_stmt(&self, stmt: &ReturnStmt) -> Result<T, TeciResult>;
     fn visit_let_stmt(&self, stmt: &LetStmt) -> Result<T, TeciResult>;
     fn visit_while_stmt(&self, stmt: &WhileStmt) -> Result<T, TeciResult>;
+    fn visit_yield_stmt(&self, stmt: &YieldStmt) -> Result<T, TeciResult>;
 }
 
 impl BreakStmt {
@@ -109,6 +151,18 @@ impl BlockStmt {
     }
 }
 
+impl DestructureStmt {
+    pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, TeciResult> {
+        visitor.visit_destructure_stmt(self)
+    }
+}
+
+impl ForInStmt {
+    pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, TeciResult> {
+        visitor.visit_forin_stmt(self)
+    }
+}
+
 impl IfStmt {
     pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, TeciResult> {
         visitor.visit_if_stmt(self)
@@ -150,3 +204,9 @@ impl WhileStmt {
         visitor.visit_while_stmt(self)
     }
 }
+
+impl YieldStmt {
+    pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, TeciResult> {
+        visitor.visit_yield_stmt(self)
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    borrow::Borrow,
+    cell::RefCell,
+    collections::HashSet,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    rc::Rc,
+};
+
+thread_local! {
+    static INTERNER: RefCell<Interner> = RefCell::new(Interner::default());
+}
+
+// Interned texts are swept once there are this many, so names no longer in
+// use don't pile up over a long session, like a REPL or an editor's
+const FIRST_SWEEP: usize = 1024;
+
+#[derive(Default)]
+struct Interner {
+    symbols: HashSet<Rc<str>>,
+    sweep_at: usize,
+}
+
+impl Interner {
+    fn intern(&mut self, text: &str) -> Rc<str> {
+        if let Some(symbol) = self.symbols.get(text) {
+            return Rc::clone(symbol);
+        }
+        if self.symbols.len() >= self.sweep_at {
+            // Only the interner holds these
+            self.symbols.retain(|symbol| Rc::strong_count(symbol) > 1);
+            self.sweep_at = FIRST_SWEEP.max(self.symbols.len() * 2);
+        }
+        let symbol: Rc<str> = Rc::from(text);
+        self.symbols.insert(Rc::clone(&symbol));
+        symbol
+    }
+}
+
+// A string for the lexemes of tokens and the names environments are keyed
+// by. Identifiers are interned: every symbol for the same name shares one
+// allocation, so cloning is a reference count and comparing usually stops at
+// the pointer. Other lexemes, like string literals, get their own allocation
+// and compare by text. Hashing always goes over the text, as it must hash
+// like the `str` it borrows as.
+#[derive(Clone)]
+pub struct Symbol(Rc<str>);
+
+impl Symbol {
+    pub fn intern(text: &str) -> Symbol {
+        Symbol(INTERNER.with(|interner| interner.borrow_mut().intern(text)))
+    }
+
+    // A symbol that is not interned, for text that is rarely a name
+    pub fn new(text: &str) -> Symbol {
+        Symbol(Rc::from(text))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(text: &str) -> Self {
+        Symbol::intern(text)
+    }
+}
+
+impl From<&Symbol> for Symbol {
+    fn from(symbol: &Symbol) -> Self {
+        symbol.clone()
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(text: String) -> Self {
+        Symbol::intern(&text)
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0) || self.0 == other.0
+    }
+}
+
+impl Eq for Symbol {}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+// Like the text, so maps can be looked up with a `str` too
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_interning() {
+        let a = Symbol::intern("counter");
+        let b = Symbol::from("counter".to_string());
+        assert!(Rc::ptr_eq(&a.0, &b.0));
+        assert_eq!(a, b);
+        assert_ne!(a, Symbol::intern("count"));
+        assert_eq!(a, "counter");
+        assert_eq!(Symbol::new("counter"), a);
+        assert!(!Rc::ptr_eq(&Symbol::new("counter").0, &a.0));
+    }
+
+    #[test]
+    fn t_sweeping() {
+        let kept = Symbol::intern("kept");
+        for i in 0..10 * FIRST_SWEEP {
+            Symbol::intern(&format!("name{}", i));
+        }
+        let interned = INTERNER.with(|interner| interner.borrow().symbols.len());
+        assert!(interned <= FIRST_SWEEP);
+        assert!(Rc::ptr_eq(&Symbol::intern("kept").0, &kept.0));
+    }
+}
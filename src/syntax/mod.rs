@@ -0,0 +1,803 @@
+use std::{fmt, rc::Rc};
+
+use crate::{
+    dialect::Dialect,
+    error::TeciResult,
+    object::Object,
+    parser::Parser,
+    scanner::{Piece, Scanner},
+    stmt::Stmt,
+    token::Token,
+    token_type::TokenType,
+};
+
+// A lossless syntax tree: every character of the source, whitespace, comments
+// and invalid text included, is in exactly one leaf. Nodes only store their
+// length, not their position, so unchanged parts of the tree can be shared
+// between the trees before and after an edit.
+//
+// The tree is only as deep as the brackets: the root holds one `Item` per top
+// level declaration, and inside those `{...}` and `(...)` make `Block` and
+// `Group` nodes. The `Stmt` AST is derived from it with the regular `Parser`.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyntaxKind {
+    Root,
+    Item,
+    Block,
+    Group,
+    Token(TokenType),
+    Whitespace,
+    Comment,
+    Invalid,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GreenToken {
+    pub kind: SyntaxKind,
+    pub text: String,
+    pub literal: Option<Object>,
+}
+
+impl GreenToken {
+    fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    fn is_trivia(&self) -> bool {
+        !matches!(self.kind, SyntaxKind::Token(_))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GreenNode {
+    pub kind: SyntaxKind,
+    // In characters
+    pub len: usize,
+    pub children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self {
+        let len = children.iter().map(GreenElement::len).sum();
+        Self {
+            kind,
+            len,
+            children,
+        }
+    }
+
+    fn write_text(&self, text: &mut String) {
+        for child in &self.children {
+            match child {
+                GreenElement::Node(node) => node.write_text(text),
+                GreenElement::Token(token) => text.push_str(&token.text),
+            }
+        }
+    }
+
+    fn tokens<'a>(&'a self, tokens: &mut Vec<&'a GreenToken>) {
+        for child in &self.children {
+            match child {
+                GreenElement::Node(node) => node.tokens(tokens),
+                GreenElement::Token(token) => tokens.push(token),
+            }
+        }
+    }
+
+    fn dump(&self, f: &mut fmt::Formatter<'_>, indent: usize, offset: usize) -> fmt::Result {
+        writeln!(
+            f,
+            "{:indent$}{:?}@{}..{}",
+            "",
+            self.kind,
+            offset,
+            offset + self.len
+        )?;
+        let mut offset = offset;
+        for child in &self.children {
+            match child {
+                GreenElement::Node(node) => node.dump(f, indent + 2, offset)?,
+                GreenElement::Token(token) => writeln!(
+                    f,
+                    "{:indent$}{:?}@{}..{} {:?}",
+                    "",
+                    token.kind,
+                    offset,
+                    offset + token.len(),
+                    token.text,
+                    indent = indent + 2
+                )?,
+            }
+            offset += child.len();
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    fn len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.len,
+            GreenElement::Token(token) => token.len(),
+        }
+    }
+
+    fn first_token(&self) -> Option<&GreenToken> {
+        match self {
+            GreenElement::Node(node) => node.children.iter().find_map(|c| c.first_token()),
+            GreenElement::Token(token) => (!token.is_trivia()).then_some(token.as_ref()),
+        }
+    }
+
+    fn last_token(&self) -> Option<&GreenToken> {
+        match self {
+            GreenElement::Node(node) => node.children.iter().rev().find_map(|c| c.last_token()),
+            GreenElement::Token(token) => (!token.is_trivia()).then_some(token.as_ref()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SyntaxTree {
+    root: Rc<GreenNode>,
+    // The dialect the source was scanned with
+    dialect: Dialect,
+}
+
+impl SyntaxTree {
+    pub fn parse(source: &str) -> Self {
+        let (leaves, dialect) = lex(source, Dialect::classic());
+        let (children, _) = build_items(leaves, true);
+        Self {
+            root: Rc::new(GreenNode::new(SyntaxKind::Root, children)),
+            dialect,
+        }
+    }
+
+    pub fn dialect(&self) -> &Dialect {
+        &self.dialect
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        self.root.write_text(&mut text);
+        text
+    }
+
+    // Replaces the characters in `start..end` with `replacement`. Only the
+    // items touched by the edit are scanned and split again; the rest of the
+    // new tree is shared with this one.
+    pub fn edit(&self, start: usize, end: usize, replacement: &str) -> SyntaxTree {
+        // A pragma changes how the whole file is scanned
+        let mut leaves = Vec::new();
+        self.root.tokens(&mut leaves);
+        let pragma = leaves
+            .first()
+            .filter(|leaf| leaf.text.starts_with('#'))
+            .map(|leaf| leaf.len());
+        if pragma.is_some_and(|len| start <= len) || (start == 0 && replacement.starts_with('#')) {
+            let mut text: Vec<char> = self.text().chars().collect();
+            text.splice(start..end, replacement.chars());
+            return SyntaxTree::parse(&text.into_iter().collect::<String>());
+        }
+
+        let children = &self.root.children;
+        let offsets: Vec<usize> = children
+            .iter()
+            .scan(0, |offset, child| {
+                let child_start = *offset;
+                *offset += child.len();
+                Some(child_start)
+            })
+            .collect();
+
+        // Children touching the edit, including those right next to it, as
+        // the edit could join them
+        let mut first = (0..children.len())
+            .find(|&i| offsets[i] + children[i].len() >= start)
+            .unwrap_or(children.len());
+        // Trivia at the end of the file goes with the item after it, once the
+        // edit makes one
+        while first > 0 && matches!(children[first - 1], GreenElement::Token(_)) {
+            first -= 1;
+        }
+        // Scanned on its own, an item starting with '#' would look like a pragma
+        let looks_like_pragma = |i: usize| {
+            leaves_of(&children[i])
+                .first()
+                .is_some_and(|l| l.text.starts_with('#'))
+        };
+        while first > 0 && first < children.len() && looks_like_pragma(first) {
+            first -= 1;
+        }
+        let mut last = (0..children.len())
+            .rev()
+            .find(|&i| offsets[i] <= end)
+            .unwrap_or(0)
+            .max(first);
+        if first == children.len() {
+            return SyntaxTree::parse(&format!("{}{}", self.text(), replacement));
+        }
+
+        loop {
+            let at_end = last + 1 >= children.len();
+            let mut text = String::new();
+            for child in &children[first..=last] {
+                match child {
+                    GreenElement::Node(node) => node.write_text(&mut text),
+                    GreenElement::Token(token) => text.push_str(&token.text),
+                }
+            }
+            let region_start = offsets[first];
+            let mut chars: Vec<char> = text.chars().collect();
+            let edit_end = (end - region_start).min(chars.len());
+            chars.splice(start - region_start..edit_end, replacement.chars());
+            let text: String = chars.into_iter().collect();
+
+            let (leaves, _) = lex(&text, self.dialect.clone());
+            // An item that ends with a `}` is continued by what the region
+            // starts with now, e.g. an `else`
+            let continues_previous = first > 0
+                && children[first - 1]
+                    .last_token()
+                    .is_some_and(|t| t.kind == SyntaxKind::Token(TokenType::RightBrace))
+                && leaves
+                    .iter()
+                    .find(|l| !l.is_trivia())
+                    .is_some_and(|l| continues_item(l.kind));
+            if continues_previous {
+                first -= 1;
+                while first > 0 && looks_like_pragma(first) {
+                    first -= 1;
+                }
+                continue;
+            }
+            let (items, complete) = build_items(leaves, at_end);
+            // The items after the region are only reusable if the new ones end
+            // where an item can end, and the next one does not continue them
+            let continued = !at_end
+                && children[last + 1]
+                    .first_token()
+                    .is_some_and(|t| continues_item(t.kind));
+            if at_end || (complete && !continued) {
+                let mut new_children = children[..first].to_vec();
+                new_children.extend(items);
+                new_children.extend(children[last + 1..].iter().cloned());
+                return SyntaxTree {
+                    root: Rc::new(GreenNode::new(SyntaxKind::Root, new_children)),
+                    dialect: self.dialect.clone(),
+                };
+            }
+            last += 1;
+        }
+    }
+
+    // The significant tokens, positioned as the `Scanner` would, and ending
+    // with `Eof`
+    pub fn tokens(&self) -> Vec<Token> {
+        let mut leaves = Vec::new();
+        self.root.tokens(&mut leaves);
+
+        let mut tokens = Vec::new();
+        let (mut line, mut column) = (1, 0);
+        for leaf in leaves {
+            if let SyntaxKind::Token(ttype) = leaf.kind {
+                tokens.push(
+                    Token::new(ttype, leaf.text.clone(), leaf.literal.clone(), line)
+                        .with_column(column),
+                );
+            }
+            for c in leaf.text.chars() {
+                if c == '\n' {
+                    line += 1;
+                    column = 0;
+                } else {
+                    column += 1;
+                }
+            }
+        }
+        tokens.push(Token::new(TokenType::Eof, "", None, line).with_column(column));
+
+        tokens
+    }
+
+    // An error for every piece of text the scanner could not read
+    pub fn errors(&self) -> Vec<TeciResult> {
+        let mut leaves = Vec::new();
+        self.root.tokens(&mut leaves);
+
+        let mut errors = Vec::new();
+        let mut line = 1;
+        for leaf in leaves {
+            if leaf.kind == SyntaxKind::Invalid {
+                let message = if leaf.text.starts_with('"') {
+                    "Unterminated string.".to_string()
+                } else if leaf.text.starts_with("/*") {
+                    "Unterminated comment.".to_string()
+                } else if let Some(Err(message)) = Dialect::of_source(&leaf.text, true) {
+                    message
+                } else {
+                    "Unexpected character.".to_string()
+                };
+                errors.push(TeciResult::TeciError { line, message });
+            }
+            line += leaf.text.matches('\n').count();
+        }
+        errors
+    }
+
+    pub fn statements(&self) -> (Vec<Stmt>, Vec<TeciResult>) {
+        Parser::new(self.tokens()).parse_partial()
+    }
+}
+
+impl fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.root.dump(f, 0, 0)
+    }
+}
+
+fn leaves_of(element: &GreenElement) -> Vec<&GreenToken> {
+    match element {
+        GreenElement::Node(node) => {
+            let mut leaves = Vec::new();
+            node.tokens(&mut leaves);
+            leaves
+        }
+        GreenElement::Token(token) => vec![token],
+    }
+}
+
+// Scans the source into leaves, merging runs of whitespace into one, and
+// returns them with the dialect they were scanned with
+fn lex(source: &str, dialect: Dialect) -> (Vec<GreenToken>, Dialect) {
+    let mut scanner = Scanner::new(source.to_string()).with_dialect(dialect);
+    let mut leaves: Vec<GreenToken> = Vec::new();
+    for piece in scanner.scan_lossless() {
+        let leaf = match piece {
+            Piece::Token(token) => GreenToken {
+                kind: SyntaxKind::Token(token.ttype),
+                text: token.lexeme.to_string(),
+                literal: token.literal,
+            },
+            // Comments and the pragma
+            Piece::Trivia(text) if !text.trim().is_empty() => GreenToken {
+                kind: SyntaxKind::Comment,
+                text,
+                literal: None,
+            },
+            Piece::Trivia(text) => GreenToken {
+                kind: SyntaxKind::Whitespace,
+                text,
+                literal: None,
+            },
+            Piece::Invalid(text) => GreenToken {
+                kind: SyntaxKind::Invalid,
+                text,
+                literal: None,
+            },
+        };
+        match leaves.last_mut() {
+            Some(last)
+                if last.kind == leaf.kind
+                    && matches!(leaf.kind, SyntaxKind::Whitespace | SyntaxKind::Invalid) =>
+            {
+                last.text.push_str(&leaf.text)
+            }
+            _ => leaves.push(leaf),
+        }
+    }
+    (leaves, scanner.dialect().clone())
+}
+
+// Whether a token after a `}` continues the item instead of starting the next
+// one, as an `else` or the rest of an expression like `match x { .. } + 1` do
+fn continues_item(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::Token(
+            TokenType::Else
+                | TokenType::Semicolon
+                | TokenType::Comma
+                | TokenType::RightParen
+                | TokenType::Dot
+                | TokenType::Plus
+                | TokenType::Star
+                | TokenType::StarStar
+                | TokenType::Slash
+                | TokenType::TildeSlash
+                | TokenType::Percent
+                | TokenType::Ampersand
+                | TokenType::Pipe
+                | TokenType::Caret
+                | TokenType::LessLess
+                | TokenType::GreaterGreater
+                | TokenType::Equals
+                | TokenType::BangEqual
+                | TokenType::Less
+                | TokenType::LessEqual
+                | TokenType::Greater
+                | TokenType::GreaterEqual
+                | TokenType::Question
+                | TokenType::Colon
+                | TokenType::And
+                | TokenType::Or
+        )
+    )
+}
+
+// Groups leaves into items. An item ends with a `;` or a `}` outside any
+// brackets, unless something that continues it follows the `}`. Trivia goes with the item after
+// it, and what is left after the last item hangs from the root when `at_end`.
+//
+// Also tells whether the last item ended properly, with nothing left after it.
+fn build_items(leaves: Vec<GreenToken>, at_end: bool) -> (Vec<GreenElement>, bool) {
+    let mut items = Vec::new();
+    // Brackets open in the current item, each with the children so far. The
+    // first entry is the item itself.
+    let mut open: Vec<(SyntaxKind, Vec<GreenElement>)> = vec![(SyntaxKind::Item, Vec::new())];
+    // Trivia seen after a `}` that may or may not end the item
+    let mut pending: Option<Vec<GreenElement>> = None;
+    let mut has_tokens = false;
+
+    for leaf in leaves {
+        let kind = leaf.kind;
+        let leaf = GreenElement::Token(Rc::new(leaf));
+        if let Some(trivia) = pending.as_mut() {
+            if matches!(kind, SyntaxKind::Token(_)) {
+                let trivia = pending.take().unwrap();
+                if continues_item(kind) {
+                    open[0].1.extend(trivia);
+                } else {
+                    let item = std::mem::replace(&mut open[0].1, trivia);
+                    items.push(GreenElement::Node(Rc::new(GreenNode::new(
+                        SyntaxKind::Item,
+                        item,
+                    ))));
+                }
+            } else {
+                trivia.push(leaf);
+                continue;
+            }
+        }
+
+        match kind {
+            SyntaxKind::Token(TokenType::LeftBrace) => open.push((SyntaxKind::Block, vec![leaf])),
+            SyntaxKind::Token(TokenType::LeftParen) => open.push((SyntaxKind::Group, vec![leaf])),
+            SyntaxKind::Token(TokenType::RightBrace | TokenType::RightParen) => {
+                let closes = if kind == SyntaxKind::Token(TokenType::RightBrace) {
+                    SyntaxKind::Block
+                } else {
+                    SyntaxKind::Group
+                };
+                let top = open.last_mut().unwrap();
+                top.1.push(leaf);
+                if top.0 == closes {
+                    let (kind, children) = open.pop().unwrap();
+                    let node = GreenElement::Node(Rc::new(GreenNode::new(kind, children)));
+                    open.last_mut().unwrap().1.push(node);
+                    if open.len() == 1 && closes == SyntaxKind::Block {
+                        pending = Some(Vec::new());
+                    }
+                }
+            }
+            SyntaxKind::Token(TokenType::Semicolon) if open.len() == 1 => {
+                open[0].1.push(leaf);
+                let item = std::mem::take(&mut open[0].1);
+                items.push(GreenElement::Node(Rc::new(GreenNode::new(
+                    SyntaxKind::Item,
+                    item,
+                ))));
+                has_tokens = false;
+                continue;
+            }
+            _ => open.last_mut().unwrap().1.push(leaf),
+        }
+        if matches!(kind, SyntaxKind::Token(_)) {
+            has_tokens = true;
+        }
+    }
+
+    // Unclosed brackets are closed by the end of the source
+    while open.len() > 1 {
+        let (kind, children) = open.pop().unwrap();
+        let node = GreenElement::Node(Rc::new(GreenNode::new(kind, children)));
+        open.last_mut().unwrap().1.push(node);
+    }
+    let rest = open.pop().unwrap().1;
+    let complete = match pending {
+        Some(trivia) => {
+            items.push(GreenElement::Node(Rc::new(GreenNode::new(
+                SyntaxKind::Item,
+                rest,
+            ))));
+            if at_end {
+                items.extend(trivia);
+                true
+            } else {
+                trivia.is_empty()
+            }
+        }
+        None if has_tokens => {
+            items.push(GreenElement::Node(Rc::new(GreenNode::new(
+                SyntaxKind::Item,
+                rest,
+            ))));
+            false
+        }
+        None => {
+            let complete = rest.is_empty();
+            if at_end {
+                items.extend(rest);
+            }
+            complete
+        }
+    };
+
+    (items, complete)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "// Adds things
+fun add(a, b) {
+    /* the /* nested */ sum */
+    return a + b;
+}
+
+let x = add(1, 2);   // three
+if (x > 2) { print \"big\"; } else { print \"small\"; }
+while (x < 10) x = x + 1;
+print x;  ";
+
+    fn significant(tokens: &[Token]) -> Vec<String> {
+        tokens
+            .iter()
+            .map(|t| format!("{:?} {:?} {}:{}", t.ttype, t.lexeme, t.line, t.column))
+            .collect()
+    }
+
+    fn items(tree: &SyntaxTree) -> Vec<String> {
+        tree.root
+            .children
+            .iter()
+            .map(|child| {
+                let mut text = String::new();
+                match child {
+                    GreenElement::Node(node) => node.write_text(&mut text),
+                    GreenElement::Token(token) => text.push_str(&token.text),
+                }
+                text.trim().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn t_lossless() {
+        for source in [
+            SOURCE,
+            "",
+            "  \n\t",
+            "let s = \"unterminated\n  ;",
+            "fun f( { let ñ = 1 /* open",
+            "print 1 @ # 2;\r\n}}",
+        ] {
+            assert_eq!(SyntaxTree::parse(source).text(), source);
+        }
+    }
+
+    #[test]
+    fn t_items() {
+        let tree = SyntaxTree::parse(SOURCE);
+        assert_eq!(
+            items(&tree),
+            vec![
+                "// Adds things\nfun add(a, b) {\n    /* the /* nested */ sum */\n    return a + b;\n}",
+                "let x = add(1, 2);",
+                "// three\nif (x > 2) { print \"big\"; } else { print \"small\"; }",
+                "while (x < 10) x = x + 1;",
+                "print x;",
+                "",
+            ]
+        );
+        let function = tree.to_string();
+        assert!(
+            function
+                .starts_with("Root@0..201\n  Item@0..81\n    Comment@0..14 \"// Adds things\"\n")
+        );
+        assert!(function.contains("\n    Group@22..28\n      Token(LeftParen)@22..23 \"(\"\n"));
+
+        // A `}` ends the item unless the rest of an expression follows it
+        let tree = SyntaxTree::parse("let y = match x { _ => 1 } + 1;\n{ }\n-y;");
+        assert_eq!(
+            items(&tree),
+            vec!["let y = match x { _ => 1 } + 1;", "{ }", "-y;"]
+        );
+    }
+
+    #[test]
+    fn t_derives_the_ast() {
+        let tree = SyntaxTree::parse(SOURCE);
+        let scanned = Scanner::new(SOURCE.to_string()).scan_tokens().unwrap();
+        assert_eq!(significant(&tree.tokens()), significant(&scanned));
+
+        let (statements, errors) = tree.statements();
+        assert_eq!(statements.len(), 5);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn t_incremental_edits() {
+        let tree = SyntaxTree::parse(SOURCE);
+        let offset = |s: &str| SOURCE[..SOURCE.find(s).unwrap()].chars().count();
+
+        // (start, end, replacement) applied to SOURCE
+        let edits = [
+            (offset("1, 2"), offset("1, 2") + 1, "40"),
+            (offset("x > 2"), offset("x > 2"), "x == 3 or "),
+            // Joins the `let` and the `if`
+            (offset("   // three"), offset("   // three") + 1, ""),
+            // Comments out everything after it
+            (offset("while"), offset("while"), "/*"),
+            (offset("print x"), SOURCE.chars().count(), ""),
+            (SOURCE.chars().count(), SOURCE.chars().count(), "\nprint 1;"),
+            (0, 0, "{"),
+        ];
+        for (start, end, replacement) in edits {
+            let mut expected: Vec<char> = SOURCE.chars().collect();
+            expected.splice(start..end, replacement.chars());
+            let expected: String = expected.into_iter().collect();
+
+            let edited = tree.edit(start, end, replacement);
+            assert_eq!(edited.text(), expected);
+            assert_eq!(edited.to_string(), SyntaxTree::parse(&expected).to_string());
+            assert_eq!(
+                significant(&edited.tokens()),
+                significant(&SyntaxTree::parse(&expected).tokens())
+            );
+        }
+
+        // Items away from the edit are shared with the old tree
+        let edited = tree.edit(offset("1, 2"), offset("1, 2") + 1, "40");
+        let shared = |i: usize| match (&tree.root.children[i], &edited.root.children[i]) {
+            (GreenElement::Node(a), GreenElement::Node(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        };
+        assert!(shared(0));
+        assert!(!shared(1));
+        assert!(shared(2) && shared(3) && shared(4));
+    }
+
+    #[test]
+    fn t_edits_match_parsing() {
+        // Editing a tree gives the tree parsing the edited text would, for
+        // every edit of a few kinds at every position
+        let replacements = [
+            "", "=>", ";", "{", "}", "(", ")", "\n", " ", "x", "else", "+ 1", "\"", "/*", "*/",
+            "// c\n", "#",
+        ];
+        for source in [
+            SOURCE,
+            "// c\n",
+            "let a = 1; /* c */\n  ",
+            "{ } + 1;\n} else {",
+        ] {
+            let tree = SyntaxTree::parse(source);
+            let chars: Vec<char> = source.chars().collect();
+            for start in 0..=chars.len() {
+                for end in [
+                    start,
+                    (start + 1).min(chars.len()),
+                    (start + 4).min(chars.len()),
+                ] {
+                    for replacement in replacements {
+                        let mut expected = chars.clone();
+                        expected.splice(start..end, replacement.chars());
+                        let expected: String = expected.into_iter().collect();
+                        let edited = tree.edit(start, end, replacement);
+                        let parsed = SyntaxTree::parse(&expected);
+                        assert!(
+                            edited.root == parsed.root,
+                            "replacing {}..{} of {:?} with {:?} gives\n{}instead of\n{}",
+                            start,
+                            end,
+                            source,
+                            replacement,
+                            edited,
+                            parsed
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn t_single_character_edits() {
+        // Deleting any character of the examples, or inserting or replacing
+        // one at any position, gives the tree parsing would. The characters
+        // put in are picked at random, with a fixed seed, from those the
+        // examples use; letters and digits scan alike, so one of each stands
+        // for the rest.
+        let mut seed: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut random = |n: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed as usize % n
+        };
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/teci");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let source = std::fs::read_to_string(&path).unwrap();
+            let tree = SyntaxTree::parse(&source);
+            let chars: Vec<char> = source.chars().collect();
+            let mut alphabet: Vec<char> = chars
+                .iter()
+                .copied()
+                .filter(|c| !c.is_alphanumeric())
+                .collect();
+            alphabet.extend(['x', '1', '#', '"', '/', '*']);
+            alphabet.sort();
+            alphabet.dedup();
+
+            for start in 0..=chars.len() {
+                let mut edits = vec![(start, (start + 1).min(chars.len()), None)];
+                for _ in 0..4 {
+                    edits.push((start, start, Some(alphabet[random(alphabet.len())])));
+                    if start < chars.len() {
+                        edits.push((start, start + 1, Some(alphabet[random(alphabet.len())])));
+                    }
+                }
+                for (start, end, c) in edits {
+                    let replacement = c.map(String::from).unwrap_or_default();
+                    let mut expected = chars.clone();
+                    expected.splice(start..end, c);
+                    let expected: String = expected.into_iter().collect();
+                    let edited = tree.edit(start, end, &replacement);
+                    assert!(
+                        edited.root == SyntaxTree::parse(&expected).root,
+                        "replacing {}..{} of {} with {:?}",
+                        start,
+                        end,
+                        path.display(),
+                        replacement
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn t_dialect_pragma() {
+        let source = "#dialect teci\nsea x = verdadero;\nmientras (x) romper;\n";
+        let tree = SyntaxTree::parse(source);
+        assert_eq!(tree.text(), source);
+        assert_eq!(tree.dialect().name(), "teci");
+        assert!(
+            tree.to_string()
+                .contains("\n    Comment@0..13 \"#dialect teci\"\n")
+        );
+        assert!(tree.errors().is_empty());
+        let (statements, errors) = tree.statements();
+        assert_eq!(statements.len(), 2);
+        assert!(errors.is_empty());
+
+        // Editing the pragma scans everything again
+        let classic = tree.edit(10, 13, "classic");
+        assert_eq!(classic.dialect().name(), "classic");
+        assert!(!classic.statements().1.is_empty());
+
+        let broken = tree.edit(10, 13, "nope.md");
+        assert_eq!(broken.errors().len(), 1);
+    }
+}
@@ -1,9 +1,11 @@
 use std::{cell::RefCell, rc::Rc};
 
 use crate::{
-    callable::TeciCallable,
+    callable::{Callable, TeciCallable},
     envirnoment::Environment,
     error::TeciResult,
+    gc::Traced,
+    generator::{Generator, contains_yield},
     interpreter::Interpreter,
     object::Object,
     stmt::{FunctionStmt, Stmt},
@@ -17,6 +19,8 @@ pub struct TeciFunction {
     params: Rc<Vec<Token>>,
     body: Rc<Vec<Stmt>>,
     closure: Rc<RefCell<Environment>>,
+    // Calls to functions with a `yield` return a generator instead of running
+    is_generator: bool,
 }
 
 impl TeciFunction {
@@ -26,22 +30,64 @@ impl TeciFunction {
             params: Rc::clone(&declaration.params),
             body: Rc::clone(&declaration.body),
             closure: Rc::clone(closure),
+            is_generator: declaration.body.iter().any(contains_yield),
         }
     }
 }
 
 impl TeciCallable for TeciFunction {
+    // A trampoline: calls in tail position come back as `TailCall`s and are
+    // made here, in place of the call that ended with them, so tail
+    // recursion runs in constant Rust stack
     fn call(&self, interpreter: &Interpreter, args: Vec<Object>) -> Result<Object, TeciResult> {
+        let mut result = self.call_once(interpreter, args);
+        let mut discard = false;
+        let mut tail_calls = 0;
+        while let Err(TeciResult::TailCall {
+            function,
+            call_site,
+            arguments,
+            discard: discarded,
+        }) = result
+        {
+            discard |= discarded;
+            tail_calls += 1;
+            result = interpreter.tail_call(&function, call_site, arguments);
+        }
+        // Like `Interpreter::call`, frames are left in place on failure for
+        // the backtrace
+        if result.is_ok() {
+            interpreter.end_tail_calls(tail_calls);
+        }
+        // A call made as a statement leaves the function returning nil
+        result.map(|value| if discard { Object::Nil } else { value })
+    }
+
+    fn call_once(
+        &self,
+        interpreter: &Interpreter,
+        args: Vec<Object>,
+    ) -> Result<Object, TeciResult> {
         let mut env = Environment::with_enclosing(Rc::clone(&self.closure));
 
         self.params.iter().zip(args).for_each(|(p, a)| {
             env.define(&p.lexeme, a);
         });
 
-        if let Err(TeciResult::Return { _value }) = interpreter.execute_block(&self.body, env) {
-            Ok(_value)
-        } else {
-            Ok(Object::Nil)
+        if self.is_generator {
+            let env = Rc::new(RefCell::new(env));
+            interpreter.track(&env);
+            let generator =
+                Generator::new(self.name.lexeme.to_string(), Rc::clone(&self.body), env);
+            return Ok(Object::Func(Callable {
+                func: Rc::new(generator),
+            }));
+        }
+
+        match interpreter.execute_body(&self.body, env) {
+            Ok(_) | Err(TeciResult::Break) => Ok(Object::Nil),
+            Err(TeciResult::Return { _value }) => Ok(_value),
+            Err(e) => Err(e),
         }
     }
 
@@ -49,7 +95,19 @@ impl TeciCallable for TeciFunction {
         self.params.len()
     }
 
+    fn name(&self) -> String {
+        self.name.lexeme.to_string()
+    }
+
     fn to_string(&self) -> String {
         format!("<fun {}>", self.name.lexeme)
     }
+
+    fn declaration(&self) -> Option<&Token> {
+        Some(&self.name)
+    }
+
+    fn trace(&self, visit: &mut dyn FnMut(Traced)) {
+        visit(Traced::Environment(&self.closure));
+    }
 }
@@ -1,25 +1,45 @@
 use std::fmt;
 
 use crate::object::Object;
+use crate::symbol::Symbol;
 use crate::token_type::TokenType;
 
 #[derive(Debug, Clone)]
 pub struct Token {
     pub ttype: TokenType,
-    pub lexeme: String,
+    pub lexeme: Symbol,
     pub literal: Option<Object>,
     pub line: usize,
+    // Zero based, in characters from the start of the line
+    pub column: usize,
 }
 
 impl Token {
-    pub fn new(ttype: TokenType, lexeme: String, literal: Option<Object>, line: usize) -> Self {
+    pub fn new(
+        ttype: TokenType,
+        lexeme: impl AsRef<str>,
+        literal: Option<Object>,
+        line: usize,
+    ) -> Self {
+        // Only names are looked up and compared often enough to intern
+        let lexeme = if ttype == TokenType::Identifier {
+            Symbol::intern(lexeme.as_ref())
+        } else {
+            Symbol::new(lexeme.as_ref())
+        };
         Token {
             ttype,
             lexeme,
             literal,
             line,
+            column: 0,
         }
     }
+
+    pub fn with_column(mut self, column: usize) -> Self {
+        self.column = column;
+        self
+    }
 }
 
 impl fmt::Display for Token {
@@ -12,7 +12,14 @@ pub enum TokenType {
     Minus,
     Star,
     Slash,
+    Percent,
     Semicolon,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
+    Question,
+    Colon,
     // One or two char tokens
     Bang,
     BangEqual,
@@ -22,6 +29,22 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    // Two char tokens
+    DotDot,
+    FatArrow,
+    Arrow,
+    StarStar,
+    LessLess,
+    GreaterGreater,
+    PlusPlus,
+    MinusMinus,
+    PlusAssign,
+    MinusAssign,
+    StarAssign,
+    SlashAssign,
+    PercentAssign,
+    // Integer division
+    TildeSlash,
     // Literals
     Number,
     Identifier,
@@ -30,6 +53,7 @@ pub enum TokenType {
     And,
     Or,
     If,
+    In,
     Else,
     True,
     False,
@@ -37,13 +61,16 @@ pub enum TokenType {
     While,
     Fun,
     Class,
+    Const,
     Return,
     Let,
+    Match,
     This,
     Super,
     Print,
     Nil,
     Break,
+    Yield,
     // EOF
     Eof,
 }
@@ -0,0 +1,558 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use crate::{
+    error::TeciResult, expr::*, generator::contains_yield, object::Object, pattern::Pattern,
+    stmt::*, symbol::Symbol, token::Token, token_type::TokenType, types::Type,
+};
+
+// Infers the types of expressions and reports the operations that cannot
+// work, like `"a" - 1`, before anything runs. Unannotated variables and
+// parameters are `any`, so code without annotations always passes.
+pub struct TypeChecker {
+    // The first scope holds the globals
+    scopes: RefCell<Vec<HashMap<Symbol, Type>>>,
+    // The functions being checked, innermost last, with their return types
+    functions: RefCell<Vec<(Token, Type)>>,
+    errors: RefCell<Vec<TeciResult>>,
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        let natives = HashMap::from([
+            ("clock".into(), Type::function(vec![], Type::Num)),
+            ("gc".into(), Type::function(vec![], Type::Num)),
+            ("fs".into(), Type::Any),
+            (
+                "input".into(),
+                Type::function(vec![Type::Any], Type::Str.nullable()),
+            ),
+            (
+                "next".into(),
+                Type::function(vec![Type::Fun(None)], Type::Any),
+            ),
+            (
+                "range".into(),
+                Type::function(vec![Type::Num, Type::Num, Type::Num], Type::Fun(None)),
+            ),
+        ]);
+        Self {
+            scopes: RefCell::new(vec![natives]),
+            functions: RefCell::new(Vec::new()),
+            errors: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn check(self, statements: &[Stmt]) -> Vec<TeciResult> {
+        self.check_statements(statements);
+        self.errors.into_inner()
+    }
+
+    fn error(&self, token: &Token, message: &str) {
+        self.errors
+            .borrow_mut()
+            .push(TeciResult::type_error(token.clone(), message));
+    }
+
+    fn declare(&self, name: &Token, annotation: Type) {
+        self.scopes
+            .borrow_mut()
+            .last_mut()
+            .unwrap()
+            .insert(name.lexeme.clone(), annotation);
+    }
+
+    fn lookup(&self, name: &Token) -> Type {
+        self.scopes
+            .borrow()
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.lexeme).cloned())
+            .unwrap_or(Type::Any)
+    }
+
+    fn type_of(&self, expr: &Expr) -> Type {
+        expr.accept(self).unwrap_or(Type::Any)
+    }
+
+    fn check_statements(&self, statements: &[Stmt]) {
+        // Functions may call the ones declared further down
+        for statement in statements {
+            if let Stmt::Function(function) = statement {
+                self.declare(&function.name, TypeChecker::signature(function));
+            }
+        }
+        for statement in statements {
+            let _ = statement.accept(self);
+        }
+    }
+
+    fn check_block(&self, statements: &[Stmt]) {
+        self.scopes.borrow_mut().push(HashMap::new());
+        self.check_statements(statements);
+        self.scopes.borrow_mut().pop();
+    }
+
+    fn signature(function: &FunctionStmt) -> Type {
+        // Calling a generator function gives the generator
+        let returns = if function.returns == Type::Any && function.body.iter().any(contains_yield) {
+            Type::Fun(None)
+        } else {
+            function.returns.clone()
+        };
+        Type::function(function.param_types.clone(), returns)
+    }
+
+    fn is_number(found: &Type) -> bool {
+        found.is_assignable_to(&Type::Num)
+    }
+
+    // The types of the names `pattern` binds when it matches a value of type
+    // `found`. With `strict`, values that cannot match are errors, as in
+    // destructuring assignments.
+    fn destructure(
+        &self,
+        pattern: &Pattern,
+        found: &Type,
+        strict: bool,
+        bindings: &mut Vec<(Token, Type)>,
+    ) {
+        match (pattern, found) {
+            (Pattern::Binding(name), found) => bindings.push((name.clone(), found.clone())),
+            (Pattern::Tuple(_, patterns), Type::Tuple(types)) if patterns.len() == types.len() => {
+                for (pattern, found) in patterns.iter().zip(types) {
+                    self.destructure(pattern, found, strict, bindings);
+                }
+            }
+            (Pattern::Tuple(paren, patterns), found) => {
+                if strict && *found != Type::Any {
+                    let found = match found {
+                        Type::Tuple(types) => format!("{} values", types.len()),
+                        found => found.to_string(),
+                    };
+                    let message = format!(
+                        "Expected a tuple of {} values, found {}",
+                        patterns.len(),
+                        found
+                    );
+                    self.error(paren, &message);
+                }
+                for pattern in patterns {
+                    self.destructure(pattern, &Type::Any, strict, bindings);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_assignment(&self, name: &Token, found: &Type) {
+        let declared = self.lookup(name);
+        if !found.is_assignable_to(&declared) {
+            let message = format!(
+                "'{}' is declared as {} but assigned {}",
+                name.lexeme, declared, found
+            );
+            self.error(name, &message);
+        }
+    }
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExprVisitor<Type> for TypeChecker {
+    fn visit_assign_expr(&self, expr: &AssignExpr) -> Result<Type, TeciResult> {
+        let value = self.type_of(&expr.value);
+        self.check_assignment(&expr.name, &value);
+        Ok(value)
+    }
+
+    fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<Type, TeciResult> {
+        let left = self.type_of(&expr.left);
+        let right = self.type_of(&expr.right);
+        let operator = &expr.operator;
+        let result = match operator.ttype {
+            TokenType::Equals | TokenType::BangEqual => Some(Type::Bool),
+            TokenType::Plus => match (&left, &right) {
+                (Type::Any, _) | (_, Type::Any) => Some(Type::Any),
+                (Type::Num, Type::Num) => Some(Type::Num),
+                (Type::Str, Type::Str | Type::Num) | (Type::Num, Type::Str) => Some(Type::Str),
+                _ => None,
+            },
+            TokenType::Less
+            | TokenType::LessEqual
+            | TokenType::Greater
+            | TokenType::GreaterEqual => (TypeChecker::is_number(&left)
+                && TypeChecker::is_number(&right))
+            .then_some(Type::Bool),
+            _ => (TypeChecker::is_number(&left) && TypeChecker::is_number(&right))
+                .then_some(Type::Num),
+        };
+
+        Ok(result.unwrap_or_else(|| {
+            let message = format!(
+                "Unsupported operand types for '{}': {} and {}",
+                operator.lexeme, left, right
+            );
+            self.error(operator, &message);
+            Type::Any
+        }))
+    }
+
+    fn visit_call_expr(&self, expr: &CallExpr) -> Result<Type, TeciResult> {
+        let callee = self.type_of(&expr.callee);
+        let arguments: Vec<Type> = expr.arguments.iter().map(|a| self.type_of(a)).collect();
+
+        match callee {
+            Type::Fun(Some(signature)) => {
+                if signature.params.len() != arguments.len() {
+                    let message = format!(
+                        "Expected {} arguments but found {} instead",
+                        signature.params.len(),
+                        arguments.len()
+                    );
+                    self.error(&expr.paren, &message);
+                }
+                for (i, (param, argument)) in signature.params.iter().zip(&arguments).enumerate() {
+                    if !argument.is_assignable_to(param) {
+                        let message =
+                            format!("Argument {} should be {}, found {}", i + 1, param, argument);
+                        self.error(&expr.paren, &message);
+                    }
+                }
+                Ok(signature.returns)
+            }
+            Type::Fun(None) | Type::Any => Ok(Type::Any),
+            callee => {
+                self.error(
+                    &expr.paren,
+                    &format!("Cannot call a value of type {}", callee),
+                );
+                Ok(Type::Any)
+            }
+        }
+    }
+
+    fn visit_conditional_expr(&self, expr: &ConditionalExpr) -> Result<Type, TeciResult> {
+        self.type_of(&expr.condition);
+        let then_branch = self.type_of(&expr.then_branch);
+        Ok(then_branch.join(&self.type_of(&expr.else_branch)))
+    }
+
+    // Only natives make values with properties, and they are all `any`
+    fn visit_get_expr(&self, expr: &GetExpr) -> Result<Type, TeciResult> {
+        let object = self.type_of(&expr.object);
+        if object != Type::Any {
+            self.error(
+                &expr.name,
+                &format!("A value of type {} has no properties", object),
+            );
+        }
+        Ok(Type::Any)
+    }
+
+    fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<Type, TeciResult> {
+        Ok(self.type_of(&expr.expression))
+    }
+
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> Result<Type, TeciResult> {
+        let left = self.type_of(&expr.left);
+        Ok(left.join(&self.type_of(&expr.right)))
+    }
+
+    fn visit_literal_expr(&self, expr: &LiteralExpr) -> Result<Type, TeciResult> {
+        Ok(match &expr.value {
+            Some(value) if value.is_number() => Type::Num,
+            Some(Object::Str(_)) => Type::Str,
+            Some(Object::Bool(_)) => Type::Bool,
+            Some(Object::Nil) => Type::Nil,
+            _ => Type::Any,
+        })
+    }
+
+    fn visit_match_expr(&self, expr: &MatchExpr) -> Result<Type, TeciResult> {
+        let value = self.type_of(&expr.value);
+        let mut result: Option<Type> = None;
+        for arm in &expr.arms {
+            let mut bindings = Vec::new();
+            self.destructure(&arm.pattern, &value, false, &mut bindings);
+            self.scopes.borrow_mut().push(HashMap::new());
+            for (name, found) in bindings {
+                self.declare(&name, found);
+            }
+            if let Some(guard) = &arm.guard {
+                self.type_of(guard);
+            }
+            let body = self.type_of(&arm.body);
+            self.scopes.borrow_mut().pop();
+            result = Some(match result {
+                Some(result) => result.join(&body),
+                None => body,
+            });
+        }
+        Ok(result.unwrap_or(Type::Any))
+    }
+
+    fn visit_tuple_expr(&self, expr: &TupleExpr) -> Result<Type, TeciResult> {
+        Ok(Type::Tuple(
+            expr.elements.iter().map(|e| self.type_of(e)).collect(),
+        ))
+    }
+
+    fn visit_tupleassign_expr(&self, expr: &TupleAssignExpr) -> Result<Type, TeciResult> {
+        let value = self.type_of(&expr.value);
+        let mut bindings = Vec::new();
+        self.destructure(&expr.target, &value, true, &mut bindings);
+        for (name, found) in bindings {
+            self.check_assignment(&name, &found);
+        }
+        Ok(value)
+    }
+
+    fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<Type, TeciResult> {
+        let right = self.type_of(&expr.right);
+        if expr.operator.ttype == TokenType::Bang {
+            return Ok(Type::Bool);
+        }
+        if !TypeChecker::is_number(&right) {
+            let message = format!(
+                "Unsupported operand type for '{}': {}",
+                expr.operator.lexeme, right
+            );
+            self.error(&expr.operator, &message);
+        }
+        Ok(Type::Num)
+    }
+
+    fn visit_update_expr(&self, expr: &UpdateExpr) -> Result<Type, TeciResult> {
+        let found = self.lookup(&expr.name);
+        if !TypeChecker::is_number(&found) {
+            let message = format!(
+                "Unsupported operand type for '{}': {}",
+                expr.operator.lexeme, found
+            );
+            self.error(&expr.operator, &message);
+        }
+        Ok(Type::Num)
+    }
+
+    fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<Type, TeciResult> {
+        Ok(self.lookup(&expr.name))
+    }
+}
+
+impl StmtVisitor<()> for TypeChecker {
+    fn visit_break_stmt(&self, _stmt: &BreakStmt) -> Result<(), TeciResult> {
+        Ok(())
+    }
+
+    fn visit_block_stmt(&self, stmt: &BlockStmt) -> Result<(), TeciResult> {
+        self.check_block(&stmt.statements);
+        Ok(())
+    }
+
+    fn visit_destructure_stmt(&self, stmt: &DestructureStmt) -> Result<(), TeciResult> {
+        let value = self.type_of(&stmt.initializer);
+        let mut bindings = Vec::new();
+        self.destructure(&stmt.pattern, &value, true, &mut bindings);
+        for (name, found) in bindings {
+            // Only constants keep the type of their value
+            self.declare(&name, if stmt.constant { found } else { Type::Any });
+        }
+        Ok(())
+    }
+
+    fn visit_forin_stmt(&self, stmt: &ForInStmt) -> Result<(), TeciResult> {
+        let iterable = self.type_of(&stmt.iterable);
+        let element = match iterable {
+            Type::Str => Type::Str,
+            Type::Any | Type::Fun(_) => Type::Any,
+            iterable => {
+                let message = format!("Cannot iterate over a value of type {}", iterable);
+                self.error(&stmt.keyword, &message);
+                Type::Any
+            }
+        };
+        self.scopes.borrow_mut().push(HashMap::new());
+        self.declare(&stmt.name, element);
+        let result = stmt.body.accept(self);
+        self.scopes.borrow_mut().pop();
+        result
+    }
+
+    fn visit_if_stmt(&self, stmt: &IfStmt) -> Result<(), TeciResult> {
+        self.type_of(&stmt.condition);
+        stmt.then_branch.accept(self)?;
+        if let Some(else_branch) = &stmt.else_branch {
+            else_branch.accept(self)?;
+        }
+        Ok(())
+    }
+
+    fn visit_expression_stmt(&self, stmt: &ExpressionStmt) -> Result<(), TeciResult> {
+        self.type_of(&stmt.expression);
+        Ok(())
+    }
+
+    fn visit_function_stmt(&self, stmt: &FunctionStmt) -> Result<(), TeciResult> {
+        self.declare(&stmt.name, TypeChecker::signature(stmt));
+
+        // What generators return is not what their calls give
+        let returns = if stmt.body.iter().any(contains_yield) {
+            Type::Any
+        } else {
+            stmt.returns.clone()
+        };
+        self.functions
+            .borrow_mut()
+            .push((stmt.name.clone(), returns));
+        self.scopes.borrow_mut().push(HashMap::new());
+        for (param, annotation) in stmt.params.iter().zip(&stmt.param_types) {
+            self.declare(param, annotation.clone());
+        }
+        self.check_statements(&stmt.body);
+        self.scopes.borrow_mut().pop();
+        self.functions.borrow_mut().pop();
+        Ok(())
+    }
+
+    fn visit_print_stmt(&self, stmt: &PrintStmt) -> Result<(), TeciResult> {
+        self.type_of(&stmt.expression);
+        Ok(())
+    }
+
+    fn visit_return_stmt(&self, stmt: &ReturnStmt) -> Result<(), TeciResult> {
+        let found = match &stmt.value {
+            Some(value) => self.type_of(value),
+            None => Type::Nil,
+        };
+        if let Some((name, returns)) = self.functions.borrow().last()
+            && !found.is_assignable_to(returns)
+        {
+            let message = format!(
+                "'{}' should return {}, found {}",
+                name.lexeme, returns, found
+            );
+            self.error(&stmt.keyword, &message);
+        }
+        Ok(())
+    }
+
+    fn visit_let_stmt(&self, stmt: &LetStmt) -> Result<(), TeciResult> {
+        let found = match &stmt.initializer {
+            Some(initializer) => self.type_of(initializer),
+            None => Type::Nil,
+        };
+        let declared = match &stmt.annotation {
+            Some(annotation) => {
+                if !found.is_assignable_to(annotation) {
+                    let message = format!(
+                        "'{}' is declared as {} but initialized with {}",
+                        stmt.name.lexeme, annotation, found
+                    );
+                    self.error(&stmt.name, &message);
+                }
+                annotation.clone()
+            }
+            // Constants keep the type of their value, variables may change
+            None if stmt.constant => found,
+            None => Type::Any,
+        };
+        self.declare(&stmt.name, declared);
+        Ok(())
+    }
+
+    fn visit_while_stmt(&self, stmt: &WhileStmt) -> Result<(), TeciResult> {
+        self.type_of(&stmt.condition);
+        stmt.body.accept(self)
+    }
+
+    fn visit_yield_stmt(&self, stmt: &YieldStmt) -> Result<(), TeciResult> {
+        self.type_of(&stmt.value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    fn check(source: &str) -> Vec<(usize, String)> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        TypeChecker::new()
+            .check(&statements)
+            .into_iter()
+            .map(|e| match e {
+                TeciResult::TypeError { token, message } => (token.line, message),
+                _ => panic!("expected a type error"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn t_unannotated_code_passes() {
+        let source = "fun f(a, b) { return a - b; }\nlet x = \"a\";\nx = 1;\nprint f(x, 2) + f(\"\", nil);\nlet s = clock() - 1;";
+        assert!(check(source).is_empty());
+    }
+
+    #[test]
+    fn t_type_errors() {
+        let source = r#"print "a" - 1;
+fun add(a: num, b: num) -> num { return a + b; }
+let x: num = add(1, "2");
+let y: str = add(1, 2);
+let name: str? = nil;
+print name + "!";
+fun greet(who: str?) -> str { return who; }
+let names: list<list<str>> = nil;
+const pair = (1, "one");
+const (n, s) = pair;
+print s * 2;
+let (a, b, c) = pair;
+print add(1) + -true;
+let ok: (num, str) = pair;
+let f: fun(num, num) -> num = add;
+f = clock;"#;
+        assert_eq!(
+            check(source),
+            vec![
+                (
+                    1,
+                    "Unsupported operand types for '-': str and num".to_string()
+                ),
+                (3, "Argument 2 should be num, found str".to_string()),
+                (
+                    4,
+                    "'y' is declared as str but initialized with num".to_string()
+                ),
+                (
+                    6,
+                    "Unsupported operand types for '+': str? and str".to_string()
+                ),
+                (7, "'greet' should return str, found str?".to_string()),
+                (
+                    8,
+                    "'names' is declared as list<list<str>> but initialized with nil".to_string()
+                ),
+                (
+                    11,
+                    "Unsupported operand types for '*': str and num".to_string()
+                ),
+                (
+                    12,
+                    "Expected a tuple of 3 values, found 2 values".to_string()
+                ),
+                (13, "Expected 2 arguments but found 1 instead".to_string()),
+                (13, "Unsupported operand type for '-': bool".to_string()),
+                (
+                    16,
+                    "'f' is declared as fun(num, num) -> num but assigned fun() -> num".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use std::fmt;
+
+// The static type of a value, as written in annotations or inferred by the
+// type checker. The interpreter never looks at them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    // Unannotated, so anything goes
+    Any,
+    Nil,
+    Bool,
+    Num,
+    Str,
+    // `fun(num, str) -> bool`, or just `fun` when the signature is unknown
+    Fun(Option<Box<Signature>>),
+    // `(num, str)`
+    Tuple(Vec<Type>),
+    // `list<str>`
+    List(Box<Type>),
+    // `str?`, a value of the type or nil
+    Nullable(Box<Type>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub params: Vec<Type>,
+    pub returns: Type,
+}
+
+impl Type {
+    pub fn function(params: Vec<Type>, returns: Type) -> Type {
+        Type::Fun(Some(Box::new(Signature { params, returns })))
+    }
+
+    // The type named `name` in an annotation, for the ones without arguments
+    pub fn named(name: &str) -> Option<Type> {
+        match name {
+            "any" => Some(Type::Any),
+            "bool" => Some(Type::Bool),
+            "num" => Some(Type::Num),
+            "str" => Some(Type::Str),
+            _ => None,
+        }
+    }
+
+    pub fn nullable(self) -> Type {
+        match self {
+            Type::Any | Type::Nil | Type::Nullable(_) => self,
+            other => Type::Nullable(Box::new(other)),
+        }
+    }
+
+    // Whether a value of this type can be used where `target` is expected.
+    // `any` goes both ways, which is what keeps unannotated code dynamic.
+    pub fn is_assignable_to(&self, target: &Type) -> bool {
+        match (self, target) {
+            (Type::Any, _) | (_, Type::Any) => true,
+            (Type::Nil, Type::Nullable(_)) => true,
+            (Type::Nullable(inner), Type::Nullable(target)) => inner.is_assignable_to(target),
+            (Type::Nullable(_), _) => false,
+            (inner, Type::Nullable(target)) => inner.is_assignable_to(target),
+            (Type::Fun(_), Type::Fun(None)) | (Type::Fun(None), Type::Fun(_)) => true,
+            (Type::Fun(Some(from)), Type::Fun(Some(to))) => {
+                from.params.len() == to.params.len()
+                    && to
+                        .params
+                        .iter()
+                        .zip(&from.params)
+                        .all(|(to, from)| to.is_assignable_to(from))
+                    && from.returns.is_assignable_to(&to.returns)
+            }
+            (Type::Tuple(from), Type::Tuple(to)) => {
+                from.len() == to.len() && from.iter().zip(to).all(|(f, t)| f.is_assignable_to(t))
+            }
+            (Type::List(from), Type::List(to)) => from.is_assignable_to(to),
+            (from, to) => from == to,
+        }
+    }
+
+    // The type of a value that is either of the two, like `a or b`
+    pub fn join(&self, other: &Type) -> Type {
+        match (self, other) {
+            _ if self == other => self.clone(),
+            (Type::Nil, other) | (other, Type::Nil) => other.clone().nullable(),
+            (Type::Nullable(inner), other) | (other, Type::Nullable(inner))
+                if **inner == *other =>
+            {
+                other.clone().nullable()
+            }
+            _ => Type::Any,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |types: &[Type]| {
+            types
+                .iter()
+                .map(Type::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            Type::Any => write!(f, "any"),
+            Type::Nil => write!(f, "nil"),
+            Type::Bool => write!(f, "bool"),
+            Type::Num => write!(f, "num"),
+            Type::Str => write!(f, "str"),
+            Type::Fun(None) => write!(f, "fun"),
+            Type::Fun(Some(signature)) => {
+                write!(
+                    f,
+                    "fun({}) -> {}",
+                    list(&signature.params),
+                    signature.returns
+                )
+            }
+            Type::Tuple(types) if types.len() == 1 => write!(f, "({},)", types[0]),
+            Type::Tuple(types) => write!(f, "({})", list(types)),
+            Type::List(inner) => write!(f, "list<{inner}>"),
+            Type::Nullable(inner) => write!(f, "{inner}?"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_assignability() {
+        let nullable_str = Type::Str.nullable();
+        assert!(Type::Str.is_assignable_to(&nullable_str));
+        assert!(Type::Nil.is_assignable_to(&nullable_str));
+        assert!(!nullable_str.is_assignable_to(&Type::Str));
+        assert!(!Type::Num.is_assignable_to(&nullable_str));
+        assert!(Type::Any.is_assignable_to(&Type::Num));
+        assert!(Type::Num.is_assignable_to(&Type::Any));
+
+        let list = |inner| Type::List(Box::new(inner));
+        assert!(!list(Type::Num).is_assignable_to(&list(Type::Str)));
+        let pair = Type::Tuple(vec![Type::Num, Type::Str]);
+        assert!(!pair.is_assignable_to(&Type::Tuple(vec![Type::Num])));
+
+        let f = Type::function(vec![Type::Any], Type::Num);
+        assert!(f.is_assignable_to(&Type::function(vec![Type::Str], Type::Num)));
+        assert!(f.is_assignable_to(&Type::Fun(None)));
+        assert!(!f.is_assignable_to(&Type::function(vec![], Type::Num)));
+
+        assert_eq!(Type::Nil.join(&Type::Str), nullable_str);
+        assert_eq!(Type::Num.join(&Type::Str), Type::Any);
+        assert_eq!(
+            Type::function(vec![Type::Num, list(nullable_str)], Type::Nil).to_string(),
+            "fun(num, list<str?>) -> nil"
+        );
+    }
+}